@@ -1,19 +1,25 @@
 use std::collections::HashSet;
+use std::io;
 use std::path::{Path, PathBuf};
 use std::process;
 
 use crossterm::style::Stylize;
-use miette::Diagnostic;
+use git2::{Repository as GitRepository, RepositoryInitOptions};
+use glob_match::glob_match;
+use miette::{Diagnostic, IntoDiagnostic};
+use regex::Regex;
 use run_script::ScriptOptions;
 use thiserror::Error;
-use tokio::fs::{self, File, OpenOptions};
-use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
 use unindent::Unindent;
 
-use crate::actions::State;
+use crate::actions::{Diagnostics, State};
 use crate::config::actions::*;
+use crate::config::Value;
+use crate::logger::info;
 use crate::path::{PathClean, Traverser};
 use crate::spinner::Spinner;
+use crate::utils::net;
+use crate::vfs::{EntryKind, Filesystem};
 
 #[derive(Debug, Diagnostic, Error)]
 pub enum ActionError {
@@ -24,21 +30,60 @@ pub enum ActionError {
     #[source]
     source: io::Error,
   },
+
+  #[error("{message}")]
+  #[diagnostic(code(decaff::actions::download))]
+  Download { message: String },
+
+  #[error("Failed to initialize git repository in '{path}'.")]
+  #[diagnostic(code(decaff::actions::git))]
+  Git {
+    path: PathBuf,
+    #[source]
+    source: git2::Error,
+  },
+
+  #[error("Undefined variable `{name}` in template '{template}'.")]
+  #[diagnostic(
+    code(decaff::actions::template),
+    help("Variables are defined by prompts, make sure one runs before this action.")
+  )]
+  UndefinedVariable { name: String, template: String },
+
+  #[error("Missing answers for prompts: {}.", .names.join(", "))]
+  #[diagnostic(
+    code(decaff::actions::answers),
+    help("Supply answers for these prompts with `--answers`, or default values with `--yes`.")
+  )]
+  Unanswered { names: Vec<String> },
+
+  #[error("No default values for prompts: {}.", .names.join(", "))]
+  #[diagnostic(
+    code(decaff::actions::defaults),
+    help("Supply answers for these prompts with `--answers`, or drop `--yes`.")
+  )]
+  NoDefaults { names: Vec<String> },
+
+  #[error("Invalid answer for prompt `{name}`: {message}")]
+  #[diagnostic(code(decaff::actions::answers))]
+  InvalidAnswer { name: String, message: String },
 }
 
 impl Copy {
-  pub async fn execute<P>(&self, root: P) -> miette::Result<()>
+  pub async fn execute<P>(&self, fs: &dyn Filesystem, root: P) -> miette::Result<()>
   where
     P: AsRef<Path>,
   {
     let destination = root.as_ref().join(&self.to);
 
     let traverser = Traverser::new(root.as_ref())
+      .filesystem(fs)
       .ignore_dirs(true)
       .contents_first(true)
-      .pattern(&self.from);
+      .pattern(&self.from)
+      .exclude(&self.exclude);
 
-    println!(
+    info!(
       "⋅ Copying: {}",
       format!("{} ╌╌ {}", &self.from, &self.to).dim()
     );
@@ -51,12 +96,12 @@ impl Copy {
 
       let target = destination.join(name).clean();
 
-      if !self.overwrite && target.is_file() {
+      if !self.overwrite && fs.is_file(&target) {
         continue;
       }
 
       if let Some(parent) = target.parent() {
-        fs::create_dir_all(parent).await.map_err(|source| {
+        fs.create_dir_all(parent).map_err(|source| {
           ActionError::Io {
             message: format!(
               "Failed to create directory structure for '{}'.",
@@ -66,7 +111,7 @@ impl Copy {
           }
         })?;
 
-        fs::copy(&matched.path, &target).await.map_err(|source| {
+        fs.copy(&matched.path, &target).map_err(|source| {
           ActionError::Io {
             message: format!(
               "Failed to copy from '{}' to '{}'.",
@@ -78,7 +123,7 @@ impl Copy {
         })?;
       }
 
-      println!("└─ {} ╌╌ {}", &matched.path.display(), &target.display());
+      info!("└─ {} ╌╌ {}", &matched.path.display(), &target.display());
     }
 
     Ok(())
@@ -86,18 +131,20 @@ impl Copy {
 }
 
 impl Move {
-  pub async fn execute<P>(&self, root: P) -> miette::Result<()>
+  pub async fn execute<P>(&self, fs: &dyn Filesystem, root: P) -> miette::Result<()>
   where
     P: AsRef<Path>,
   {
     let destination = root.as_ref().join(&self.to);
 
     let traverser = Traverser::new(root.as_ref())
+      .filesystem(fs)
       .ignore_dirs(false)
       .contents_first(true)
-      .pattern(&self.from);
+      .pattern(&self.from)
+      .exclude(&self.exclude);
 
-    println!(
+    info!(
       "⋅ Moving: {}",
       format!("{} ╌╌ {}", &self.from, &self.to).dim()
     );
@@ -110,14 +157,12 @@ impl Move {
 
       let target = destination.join(name).clean();
 
-      if !self.overwrite {
-        if let Ok(true) = target.try_exists() {
-          continue;
-        }
+      if !self.overwrite && fs.exists(&target) {
+        continue;
       }
 
       if let Some(parent) = target.parent() {
-        fs::create_dir_all(parent).await.map_err(|source| {
+        fs.create_dir_all(parent).map_err(|source| {
           ActionError::Io {
             message: format!(
               "Failed to create directory structure for '{}'.",
@@ -127,7 +172,7 @@ impl Move {
           }
         })?;
 
-        fs::rename(&matched.path, &target).await.map_err(|source| {
+        fs.rename(&matched.path, &target).map_err(|source| {
           ActionError::Io {
             message: format!(
               "Failed to move from '{}' to '{}'.",
@@ -139,7 +184,7 @@ impl Move {
         })?;
       }
 
-      println!("└─ {} ╌╌ {}", &matched.path.display(), &target.display());
+      info!("└─ {} ╌╌ {}", &matched.path.display(), &target.display());
     }
 
     Ok(())
@@ -147,29 +192,35 @@ impl Move {
 }
 
 impl Delete {
-  pub async fn execute<P>(&self, root: P) -> miette::Result<()>
+  pub async fn execute<P>(&self, fs: &dyn Filesystem, root: P) -> miette::Result<()>
   where
     P: AsRef<Path>,
   {
     let traverser = Traverser::new(root.as_ref())
+      .filesystem(fs)
       .ignore_dirs(false)
       .contents_first(false)
       .pattern(&self.target);
 
-    println!("⋅ Deleting: {}", &self.target.clone().dim());
+    info!("⋅ Deleting: {}", &self.target.clone().dim());
 
     for matched in traverser.iter().flatten() {
       let target = &matched.path.clean();
 
+      // Contents of already deleted directories may still be traversed.
+      if !fs.exists(target) {
+        continue;
+      }
+
       if matched.is_file() {
-        fs::remove_file(target).await.map_err(|source| {
+        fs.remove_file(target).map_err(|source| {
           ActionError::Io {
             message: format!("Failed to delete file '{}'.", target.display()),
             source,
           }
         })?;
       } else if matched.is_dir() {
-        fs::remove_dir_all(target).await.map_err(|source| {
+        fs.remove_dir_all(target).map_err(|source| {
           ActionError::Io {
             message: format!("Failed to delete directory '{}'.", target.display()),
             source,
@@ -179,15 +230,301 @@ impl Delete {
         continue;
       }
 
-      println!("└─ {}", &target.display());
+      info!("└─ {}", &target.display());
+    }
+
+    Ok(())
+  }
+}
+
+impl MakeDir {
+  pub async fn execute<P>(&self, fs: &dyn Filesystem, root: P) -> miette::Result<()>
+  where
+    P: AsRef<Path>,
+  {
+    let target = root.as_ref().join(&self.target).clean();
+
+    info!("⋅ Creating directory: {}", self.target.as_str().dim());
+
+    fs.create_dir_all(&target).map_err(|source| {
+      ActionError::Io {
+        message: format!("Failed to create directory '{}'.", target.display()),
+        source,
+      }
+    })?;
+
+    info!("└─ {}", &target.display());
+
+    Ok(())
+  }
+}
+
+impl Symlink {
+  pub async fn execute<P>(
+    &self,
+    fs: &dyn Filesystem,
+    root: P,
+    diagnostics: &Diagnostics,
+  ) -> miette::Result<()>
+  where
+    P: AsRef<Path>,
+  {
+    let original = root.as_ref().join(&self.from).clean();
+    let link = root.as_ref().join(&self.to).clean();
+
+    info!(
+      "⋅ Linking: {}",
+      format!("{} ╌╌ {}", &self.to, &self.from).dim()
+    );
+
+    let Some(kind) = fs.kind(&original) else {
+      miette::bail!("Failed to create symlink: '{}' doesn't exist.", self.from);
+    };
+
+    match fs.kind(&link) {
+      | Some(_) if !self.overwrite => {
+        info!("└─ {} {}", "✗".red(), "Already exists, skipping".dim());
+        return Ok(());
+      },
+      | Some(EntryKind::Dir) => {
+        miette::bail!(
+          "Failed to create symlink: '{}' is a directory.",
+          link.display()
+        );
+      },
+      | Some(_) => {
+        fs.remove_file(&link).map_err(|source| {
+          ActionError::Io {
+            message: format!("Failed to replace '{}'.", link.display()),
+            source,
+          }
+        })?;
+      },
+      | None => {},
+    }
+
+    if let Some(parent) = link.parent() {
+      fs.create_dir_all(parent).map_err(|source| {
+        ActionError::Io {
+          message: format!(
+            "Failed to create directory structure for '{}'.",
+            parent.display()
+          ),
+          source,
+        }
+      })?;
+    }
+
+    let target = relative_path(&original, link.parent().unwrap_or(Path::new("")));
+
+    match fs.symlink(&target, &link) {
+      | Ok(..) => {},
+      | Err(err) if err.kind() == io::ErrorKind::Unsupported => {
+        diagnostics.warn(format!(
+          "Symlinks are not supported, copied '{}' to '{}' instead",
+          self.from, self.to
+        ));
+
+        copy_all(fs, &original, kind, &link)?;
+      },
+      | Err(source) => {
+        return Err(
+          ActionError::Io {
+            message: format!("Failed to create symlink '{}'.", link.display()),
+            source,
+          }
+          .into(),
+        );
+      },
+    }
+
+    info!("└─ {} ╌╌ {}", &link.display(), &target.display());
+
+    Ok(())
+  }
+}
+
+impl Write {
+  pub async fn execute<P>(
+    &self,
+    fs: &dyn Filesystem,
+    root: P,
+    state: &State,
+    diagnostics: &Diagnostics,
+  ) -> miette::Result<()>
+  where
+    P: AsRef<Path>,
+  {
+    let target = root.as_ref().join(&self.to).clean();
+
+    info!("⋅ Writing: {}", self.to.as_str().dim());
+
+    if !self.overwrite && fs.is_file(&target) {
+      info!("└─ {} {}", "✗".red(), "Already exists, skipping".dim());
+      return Ok(());
+    }
+
+    let mut content = self.content.trim().unindent();
+
+    if let Some(injects) = &self.injects {
+      for inject in injects {
+        if let Some(value) = state.get(inject) {
+          content = content.replace(&format!("{{{inject}}}"), value.to_string().as_str());
+        } else {
+          diagnostics.warn(format!("Unknown injection: {inject}"));
+        }
+      }
+    }
+
+    // Trimming strips the trailing newline as well, so put it back.
+    if !content.is_empty() {
+      content.push('\n');
+    }
+
+    if let Some(parent) = target.parent() {
+      fs.create_dir_all(parent).map_err(|source| {
+        ActionError::Io {
+          message: format!(
+            "Failed to create directory structure for '{}'.",
+            parent.display()
+          ),
+          source,
+        }
+      })?;
+    }
+
+    fs.write(&target, content.as_bytes()).map_err(|source| {
+      ActionError::Io {
+        message: format!("Failed to write to the file '{}'.", target.display()),
+        source,
+      }
+    })?;
+
+    info!("└─ {}", &target.display());
+
+    Ok(())
+  }
+}
+
+impl Download {
+  pub async fn execute<P>(&self, fs: &dyn Filesystem, root: P) -> miette::Result<()>
+  where
+    P: AsRef<Path>,
+  {
+    let target = root.as_ref().join(&self.to).clean();
+
+    info!(
+      "⋅ Downloading: {}",
+      format!("{} ╌╌ {}", &self.url, &self.to).dim()
+    );
+
+    let failed = |reason: String| {
+      ActionError::Download {
+        message: format!("Failed to download '{}': {reason}.", self.url),
+      }
+    };
+
+    let client = net::client().map_err(|err| failed(err.to_string()))?;
+
+    let response = client
+      .get(&self.url)
+      .send()
+      .await
+      .map_err(|err| failed(err.to_string()))?;
+
+    let status = response.status();
+
+    if !status.is_success() {
+      return Err(failed(format!("server responded with {status}")).into());
+    }
+
+    let bytes = response
+      .bytes()
+      .await
+      .map_err(|err| failed(err.to_string()))?;
+
+    if let Some(parent) = target.parent() {
+      fs.create_dir_all(parent).map_err(|source| {
+        ActionError::Io {
+          message: format!(
+            "Failed to create directory structure for '{}'.",
+            parent.display()
+          ),
+          source,
+        }
+      })?;
+    }
+
+    fs.write(&target, &bytes).map_err(|source| {
+      ActionError::Io {
+        message: format!("Failed to write to the file '{}'.", target.display()),
+        source,
+      }
+    })?;
+
+    info!("└─ {}", &target.display());
+
+    Ok(())
+  }
+}
+
+impl Template {
+  pub async fn execute<P>(&self, fs: &dyn Filesystem, root: P, state: &State) -> miette::Result<()>
+  where
+    P: AsRef<Path>,
+  {
+    let source = root.as_ref().join(&self.from).clean();
+    let target = root.as_ref().join(&self.to).clean();
+
+    info!(
+      "⋅ Rendering: {}",
+      format!("{} ╌╌ {}", &self.from, &self.to).dim()
+    );
+
+    let template = fs
+      .read(&source)
+      .and_then(|bytes| {
+        String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+      })
+      .map_err(|source_err| {
+        ActionError::Io {
+          message: format!("Failed to read template '{}'.", source.display()),
+          source: source_err,
+        }
+      })?;
+
+    let rendered = match self.engine {
+      | TemplateEngine::Simple => render_simple(&template, state),
+    }
+    .map_err(|name| ActionError::UndefinedVariable { name, template: self.from.clone() })?;
+
+    if let Some(parent) = target.parent() {
+      fs.create_dir_all(parent).map_err(|source| {
+        ActionError::Io {
+          message: format!(
+            "Failed to create directory structure for '{}'.",
+            parent.display()
+          ),
+          source,
+        }
+      })?;
     }
 
+    fs.write(&target, rendered.as_bytes()).map_err(|source| {
+      ActionError::Io {
+        message: format!("Failed to write to the file '{}'.", target.display()),
+        source,
+      }
+    })?;
+
+    info!("└─ {}", &target.display());
+
     Ok(())
   }
 }
 
 impl Echo {
-  pub async fn execute(&self, state: &State) -> miette::Result<()> {
+  pub async fn execute(&self, state: &State, diagnostics: &Diagnostics) -> miette::Result<()> {
     let message = if self.trim {
       self.message.trim()
     } else {
@@ -203,22 +540,35 @@ impl Echo {
         if let Some(value) = state.get(inject) {
           message = message.replace(&format!("{{{inject}}}"), value.to_string().as_str());
         } else {
-          println!("{}", format!("? Unknown injection: {inject}").yellow());
+          diagnostics.warn(format!("Unknown injection: {inject}"));
           should_print_nl = true;
         }
       }
 
       if should_print_nl {
-        println!();
+        info!();
       }
     }
 
-    Ok(println!("{message}"))
+    // Values can also be interpolated the same way as in templates, without explicit injects.
+    match render_simple(&message, state) {
+      | Ok(rendered) => message = rendered,
+      | Err(name) => diagnostics.warn(format!("Unknown variable: {name}")),
+    }
+
+    info!("{message}");
+
+    Ok(())
   }
 }
 
 impl Run {
-  pub async fn execute<P>(&self, root: P, state: &State) -> miette::Result<()>
+  pub async fn execute<P>(
+    &self,
+    root: P,
+    state: &State,
+    diagnostics: &Diagnostics,
+  ) -> miette::Result<()>
   where
     P: Into<PathBuf> + AsRef<Path>,
   {
@@ -232,13 +582,13 @@ impl Run {
         if let Some(value) = state.get(inject) {
           command = command.replace(&format!("{{{inject}}}"), value.to_string().as_str());
         } else {
-          println!("{}", format!("? Unknown injection: {inject}").yellow());
+          diagnostics.warn(format!("Unknown injection: {inject}"));
           should_print_nl = true;
         }
       }
 
       if should_print_nl {
-        println!();
+        info!();
       }
     }
 
@@ -256,8 +606,29 @@ impl Run {
       })
       .unwrap();
 
+    let mut directory: PathBuf = root.into();
+
+    if let Some(cwd) = &self.cwd {
+      directory = directory.join(cwd).clean();
+
+      if !directory.is_dir() {
+        miette::bail!(
+          "Working directory '{}' doesn't exist or is not a directory.",
+          directory.display()
+        );
+      }
+    }
+
+    // Expose prompts' values first, so explicitly set variables take precedence.
+    let env_vars = state
+      .iter()
+      .map(|(name, value)| (env_var_name(name), value.to_string()))
+      .chain(self.env.clone())
+      .collect();
+
     let options = ScriptOptions {
-      working_directory: Some(root.into()),
+      working_directory: Some(directory),
+      env_vars: Some(env_vars),
       ..ScriptOptions::new()
     };
 
@@ -270,10 +641,14 @@ impl Run {
     let has_failed = code > 0;
 
     // Re-format depending on the exit code.
-    let name = if has_failed { name.red() } else { name.green() };
+    let styled = if has_failed {
+      name.clone().red()
+    } else {
+      name.clone().green()
+    };
 
     // Stopping before printing output/errors, otherwise the spinner message won't be cleared.
-    spinner.stop_with_message(format!("{name}\n",));
+    spinner.stop_with_message(format!("{styled}\n",));
 
     if has_failed {
       if !err.is_empty() {
@@ -295,27 +670,184 @@ impl Run {
         eprintln!("{message}");
       }
 
+      if self.continue_on_error {
+        diagnostics.warn(format!(
+          "Command failed with exit code {code}, continuing: {name}"
+        ));
+        return Ok(());
+      }
+
       process::exit(code);
     }
 
-    Ok(println!("{}", output.trim()))
+    info!("{}", output.trim());
+
+    Ok(())
+  }
+}
+
+impl Run {
+  /// Reports the command as skipped by the given CLI flag instead of running it.
+  pub async fn skip(&self, flag: &str, diagnostics: &Diagnostics) -> miette::Result<()> {
+    let name = self.name.clone().unwrap_or_else(|| {
+      let command = self.command.trim();
+
+      match command.lines().count() {
+        | 1 => command.to_string(),
+        | _ => command.lines().next().unwrap_or_default().to_string() + "...",
+      }
+    });
+
+    diagnostics.warn(format!("Skipped by {flag}: {name}"));
+
+    Ok(())
+  }
+}
+
+impl GitInit {
+  pub async fn execute<P>(&self, root: P) -> miette::Result<()>
+  where
+    P: AsRef<Path>,
+  {
+    let root = root.as_ref();
+
+    info!("⋅ Initializing git repository");
+
+    if GitRepository::open(root).is_ok() {
+      info!("└─ {} {}", "✗".red(), "Already initialized, skipping".dim());
+      return Ok(());
+    }
+
+    let mut options = RepositoryInitOptions::new();
+
+    if let Some(branch) = &self.initial_branch {
+      options.initial_head(branch);
+    }
+
+    GitRepository::init_opts(root, &options)
+      .map_err(|source| ActionError::Git { path: root.to_path_buf(), source })?;
+
+    info!("└─ {}", root.join(".git").display());
+
+    Ok(())
+  }
+
+  /// Reports the initialization as skipped by the given CLI flag instead of running it.
+  pub async fn skip(&self, flag: &str, diagnostics: &Diagnostics) -> miette::Result<()> {
+    diagnostics.warn(format!("Skipped by {flag}: git-init"));
+
+    Ok(())
   }
 }
 
 impl Prompt {
   pub async fn execute(&self, state: &mut State) -> miette::Result<()> {
+    if !self.should_ask(state) {
+      info!("⋅ Skipping prompt: {}", self.name().dim());
+
+      return Ok(());
+    }
+
+    if let Some(value) = self.preset(state)? {
+      let name = self.name().to_string();
+
+      info!("⋅ Answered: {}", name.as_str().dim());
+
+      if matches!(self, Self::Password(_)) {
+        state.set_secret(name, value);
+      } else {
+        state.set(name, value);
+      }
+
+      return Ok(());
+    }
+
     match self {
       | Self::Confirm(prompt) => prompt.execute(state).await,
       | Self::Editor(prompt) => prompt.execute(state).await,
       | Self::Input(prompt) => prompt.execute(state).await,
       | Self::Number(prompt) => prompt.execute(state).await,
       | Self::Select(prompt) => prompt.execute(state).await,
+      | Self::MultiSelect(prompt) => prompt.execute(state).await,
+      | Self::Password(prompt) => prompt.execute(state).await,
+      | Self::Path(prompt) => prompt.execute(state).await,
+    }
+  }
+
+  /// Returns the value to use instead of asking: either a supplied answer, or the default value if
+  /// defaults should be used. Returns `None` if the prompt should be asked interactively, and fails
+  /// if it can't be.
+  fn preset(&self, state: &State) -> Result<Option<Value>, ActionError> {
+    let name = self.name();
+
+    if let Some(answer) = state.answers().and_then(|answers| answers.get(name)) {
+      return self
+        .answer(answer.clone())
+        .map(Some)
+        .map_err(|message| ActionError::InvalidAnswer { name: name.to_string(), message });
+    }
+
+    if state.use_defaults() {
+      return self
+        .default_value()
+        .map(Some)
+        .ok_or_else(|| ActionError::NoDefaults { names: vec![name.to_string()] });
+    }
+
+    if !state.is_interactive() {
+      return Err(ActionError::Unanswered { names: vec![name.to_string()] });
+    }
+
+    Ok(None)
+  }
+
+  /// Returns the default value of the prompt, transformed if needed.
+  pub fn default_value(&self) -> Option<Value> {
+    match self {
+      | Self::Confirm(prompt) => prompt.default_value(),
+      | Self::Editor(prompt) => prompt.default_value(),
+      | Self::Input(prompt) => prompt.default_value(),
+      | Self::Number(prompt) => prompt.default_value(),
+      | Self::Select(prompt) => prompt.default_value(),
+      | Self::MultiSelect(prompt) => prompt.default_value(),
+      | Self::Password(_) => None,
+      | Self::Path(prompt) => prompt.default_value(),
+    }
+  }
+
+  /// Validates and transforms an answer supplied upfront.
+  fn answer(&self, value: Value) -> Result<Value, String> {
+    match self {
+      | Self::Confirm(prompt) => prompt.answer(value),
+      | Self::Editor(prompt) => prompt.answer(value),
+      | Self::Input(prompt) => prompt.answer(value),
+      | Self::Number(prompt) => prompt.answer(value),
+      | Self::Select(prompt) => prompt.answer(value),
+      | Self::MultiSelect(prompt) => prompt.answer(value),
+      | Self::Password(prompt) => prompt.answer(value),
+      | Self::Path(prompt) => prompt.answer(value),
     }
   }
+
+  /// Evaluates the `when` condition, if any, against the previous answers.
+  fn should_ask(&self, state: &State) -> bool {
+    let Some(condition) = self.when() else {
+      return true;
+    };
+
+    let (negated, name) = match condition.strip_prefix('!') {
+      | Some(name) => (true, name.trim()),
+      | None => (false, condition.trim()),
+    };
+
+    let truthy = state.get(name).is_some_and(|value| value.is_truthy());
+
+    truthy != negated
+  }
 }
 
 impl Replace {
-  pub async fn execute<P>(&self, root: P, state: &State) -> miette::Result<()>
+  pub async fn execute<P>(&self, fs: &dyn Filesystem, root: P, state: &State) -> miette::Result<()>
   where
     P: AsRef<Path>,
   {
@@ -323,6 +855,7 @@ impl Replace {
     let pattern = self.glob.clone().unwrap_or("**/*".to_string());
 
     let traverser = Traverser::new(root.as_ref())
+      .filesystem(fs)
       .ignore_dirs(true)
       .contents_first(true)
       .pattern(&pattern);
@@ -330,54 +863,38 @@ impl Replace {
     if !self.replacements.is_empty() {
       let mut performed = HashSet::new();
 
-      println!("⋅ Applying replacements:");
+      info!("⋅ Applying replacements:");
 
       for matched in traverser.iter().flatten() {
-        let mut buffer = String::new();
         let mut should_write = false;
 
-        let mut file = File::open(&matched.path).await.map_err(|source| {
-          ActionError::Io {
-            message: format!("Failed to open file '{}'.", &matched.path.display()),
-            source,
-          }
-        })?;
+        let mut buffer = fs
+          .read(&matched.path)
+          .and_then(|bytes| {
+            String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+          })
+          .map_err(|source| {
+            ActionError::Io {
+              message: format!("Failed to read file '{}'.", &matched.path.display()),
+              source,
+            }
+          })?;
 
-        file.read_to_string(&mut buffer).await.map_err(|source| {
-          ActionError::Io {
-            message: format!("Failed to read file '{}'.", &matched.path.display()),
-            source,
+        for replacement in &self.replacements {
+          if !replacement.applies_to(root.as_ref(), &matched.path) {
+            continue;
           }
-        })?;
 
-        for replacement in &self.replacements {
-          if let Some(value) = state.get(replacement) {
-            buffer = buffer.replace(&format!("{{{replacement}}}"), value.to_string().as_str());
+          if let Some(value) = replacement.value(state) {
+            buffer = buffer.replace(&replacement.placeholder(), &value);
             should_write = true;
 
-            performed.insert(replacement.to_string());
+            performed.insert(&replacement.name);
           }
         }
 
         if should_write {
-          let mut result = OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&matched.path)
-            .await
-            .map_err(|source| {
-              ActionError::Io {
-                message: format!(
-                  "Failed to open file '{}' for writing.",
-                  &matched.path.display()
-                ),
-                source,
-              }
-            })?;
-
-          result
-            .write_all(buffer.as_bytes())
-            .await
+          fs.write(&matched.path, buffer.as_bytes())
             .map_err(|source| {
               ActionError::Io {
                 message: format!("Failed to write to the file '{}'.", &matched.path.display()),
@@ -387,15 +904,70 @@ impl Replace {
         }
       }
 
+      // Replacements apply to names of matched files and directories as well. Deepest entries go
+      // first, so renaming a directory doesn't invalidate paths of its contents.
+      let traverser = Traverser::new(root.as_ref())
+        .filesystem(fs)
+        .ignore_dirs(false)
+        .contents_first(true)
+        .pattern(&pattern);
+
+      for matched in traverser.iter().flatten() {
+        let (Some(parent), Some(name)) = (matched.path.parent(), matched.path.file_name()) else {
+          continue;
+        };
+
+        let mut name = name.to_string_lossy().to_string();
+        let mut should_rename = false;
+
+        for replacement in &self.replacements {
+          if !replacement.applies_to(root.as_ref(), &matched.path) {
+            continue;
+          }
+
+          let placeholder = replacement.placeholder();
+
+          if let (true, Some(value)) = (name.contains(&placeholder), replacement.value(state)) {
+            name = name.replace(&placeholder, &value);
+            should_rename = true;
+
+            performed.insert(&replacement.name);
+          }
+        }
+
+        if should_rename {
+          let target = parent.join(name);
+
+          if fs.exists(&target) {
+            miette::bail!(
+              "Failed to rename '{}': '{}' already exists.",
+              matched.path.display(),
+              target.display()
+            );
+          }
+
+          fs.rename(&matched.path, &target).map_err(|source| {
+            ActionError::Io {
+              message: format!(
+                "Failed to rename '{}' to '{}'.",
+                matched.path.display(),
+                target.display()
+              ),
+              source,
+            }
+          })?;
+        }
+      }
+
       // Report whether replacements were performed or not.
       for replacement in &self.replacements {
-        let state = if performed.contains(replacement) {
+        let state = if performed.contains(&replacement.name) {
           "✓".green()
         } else {
           "✗".red()
         };
 
-        println!("└─ {state} {replacement}");
+        info!("└─ {state} {}", replacement.name);
       }
     }
 
@@ -403,11 +975,931 @@ impl Replace {
   }
 }
 
-impl Unknown {
-  pub async fn execute(&self) -> miette::Result<()> {
-    let name = self.name.as_str().yellow();
-    let message = format!("? Unknown action: {name}").yellow();
+impl Replacement {
+  /// Returns the placeholder to substitute, e.g. `{repo_name}`.
+  fn placeholder(&self) -> String {
+    format!("{{{}}}", self.name)
+  }
+
+  /// Checks whether the replacement applies to the given path, i.e. whether the path matches any of
+  /// the `files` patterns, resolved relative to the root. Applies to everything if there are none.
+  fn applies_to(&self, root: &Path, path: &Path) -> bool {
+    let Some(files) = &self.files else {
+      return true;
+    };
+
+    let candidate = path.display().to_string();
+
+    files
+      .iter()
+      .any(|pattern| glob_match(&root.join(pattern).display().to_string(), &candidate))
+  }
+
+  /// Returns the value provided by a prompt, falling back to the default one.
+  fn value(&self, state: &State) -> Option<String> {
+    state
+      .get(&self.name)
+      .map(ToString::to_string)
+      .or_else(|| self.default.clone())
+  }
+}
+
+impl ReplaceInFile {
+  pub async fn execute<P>(&self, fs: &dyn Filesystem, root: P) -> miette::Result<()>
+  where
+    P: AsRef<Path>,
+  {
+    let regex = if self.regex {
+      Regex::new(&self.pattern).into_diagnostic()?
+    } else {
+      Regex::new(&regex::escape(&self.pattern)).into_diagnostic()?
+    };
+
+    // Literal replacements shouldn't expand `$` references.
+    let replacement = if self.regex {
+      self.replacement.clone()
+    } else {
+      self.replacement.replace('$', "$$")
+    };
+
+    let traverser = Traverser::new(root.as_ref())
+      .filesystem(fs)
+      .ignore_dirs(true)
+      .contents_first(true)
+      .pattern(&self.target);
+
+    info!(
+      "⋅ Replacing in files: {}",
+      format!("{} ╌╌ {}", &self.pattern, &self.replacement).dim()
+    );
+
+    for matched in traverser.iter().flatten() {
+      let bytes = fs.read(&matched.path).map_err(|source| {
+        ActionError::Io {
+          message: format!("Failed to read file '{}'.", &matched.path.display()),
+          source,
+        }
+      })?;
+
+      // Skip binary files, i.e. those containing NUL bytes or not being valid UTF-8.
+      let Some(buffer) = String::from_utf8(bytes)
+        .ok()
+        .filter(|buffer| !buffer.contains('\0'))
+      else {
+        continue;
+      };
+
+      if !regex.is_match(&buffer) {
+        continue;
+      }
+
+      let buffer = regex.replace_all(&buffer, replacement.as_str());
+
+      fs.write(&matched.path, buffer.as_bytes())
+        .map_err(|source| {
+          ActionError::Io {
+            message: format!("Failed to write to the file '{}'.", &matched.path.display()),
+            source,
+          }
+        })?;
+
+      info!("└─ {}", &matched.path.display());
+    }
+
+    Ok(())
+  }
+}
+
+impl Rename {
+  pub async fn execute<P>(&self, fs: &dyn Filesystem, root: P) -> miette::Result<()>
+  where
+    P: AsRef<Path>,
+  {
+    // Deepest entries go first, so renaming a directory doesn't invalidate paths of its contents.
+    let traverser = Traverser::new(root.as_ref())
+      .filesystem(fs)
+      .ignore_dirs(false)
+      .contents_first(true)
+      .pattern(&self.from);
+
+    for matched in traverser.iter().flatten() {
+      let Some(parent) = matched.path.parent() else {
+        continue;
+      };
+
+      let target = parent.join(&self.to).clean();
+
+      if target == matched.path {
+        continue;
+      }
+
+      if fs.exists(&target) {
+        miette::bail!(
+          "Failed to rename '{}': '{}' already exists.",
+          matched.path.display(),
+          target.display()
+        );
+      }
+
+      fs.rename(&matched.path, &target).map_err(|source| {
+        ActionError::Io {
+          message: format!(
+            "Failed to rename '{}' to '{}'.",
+            matched.path.display(),
+            target.display()
+          ),
+          source,
+        }
+      })?;
+
+      info!("└─ {} ╌╌ {}", &matched.path.display(), &target.display());
+    }
+
+    Ok(())
+  }
+}
+
+impl Unknown {
+  pub async fn execute(&self, diagnostics: &Diagnostics) -> miette::Result<()> {
+    diagnostics.warn(format!("Unknown action: {}", self.name));
+
+    Ok(())
+  }
+}
+
+/// Returns the name of the environment variable a state value is exposed as to `run` actions, e.g.
+/// `DECAFF_REPO_NAME` for `repo-name`.
+fn env_var_name(name: &str) -> String {
+  let name = name
+    .chars()
+    .map(|char| {
+      if char.is_ascii_alphanumeric() {
+        char.to_ascii_uppercase()
+      } else {
+        '_'
+      }
+    })
+    .collect::<String>();
+
+  format!("DECAFF_{name}")
+}
+
+/// Substitutes `{{ name }}` placeholders with values from the state. Unterminated placeholders are
+/// left as is. Returns the name of the first undefined variable on failure.
+fn render_simple(template: &str, state: &State) -> Result<String, String> {
+  let mut output = String::with_capacity(template.len());
+  let mut rest = template;
+
+  while let Some(start) = rest.find("{{") {
+    let Some(end) = rest[start + 2..].find("}}") else {
+      break;
+    };
+
+    let name = rest[start + 2..start + 2 + end].trim();
+    let value = state.get(name).ok_or_else(|| name.to_string())?;
+
+    output.push_str(&rest[..start]);
+    output.push_str(&value.to_string());
+
+    rest = &rest[start + 2 + end + 2..];
+  }
+
+  output.push_str(rest);
+
+  Ok(output)
+}
+
+/// Returns the path of `path` relative to the `base` directory. Both paths are expected to be
+/// cleaned and relative to the same directory.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+  let path = path.components().collect::<Vec<_>>();
+  let base = base.components().collect::<Vec<_>>();
+
+  let common = path
+    .iter()
+    .zip(&base)
+    .take_while(|(left, right)| left == right)
+    .count();
+
+  let mut relative = PathBuf::new();
+
+  for _ in common..base.len() {
+    relative.push("..");
+  }
+
+  for component in &path[common..] {
+    relative.push(component);
+  }
+
+  relative
+}
+
+/// Copies the file or the directory tree at `from` to `to`.
+fn copy_all(fs: &dyn Filesystem, from: &Path, kind: EntryKind, to: &Path) -> miette::Result<()> {
+  let failed = |source| {
+    ActionError::Io {
+      message: format!(
+        "Failed to copy from '{}' to '{}'.",
+        from.display(),
+        to.display()
+      ),
+      source,
+    }
+  };
+
+  if kind != EntryKind::Dir {
+    return Ok(fs.copy(from, to).map_err(failed)?);
+  }
+
+  for entry in fs.walk(from, false) {
+    let (path, kind) = entry.map_err(failed)?;
+    let target = to.join(path.strip_prefix(from).unwrap_or(&path));
+
+    match kind {
+      | EntryKind::Dir => fs.create_dir_all(&target).map_err(failed)?,
+      | _ => fs.copy(&path, &target).map_err(failed)?,
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+  use std::fs;
+
+  use tempfile::TempDir;
+
+  use super::*;
+  use crate::config::prompts::InputPrompt;
+  use crate::logger::testing::Capture;
+  use crate::logger::Verbosity;
+  use crate::utils::testing;
+  use crate::vfs::{MemoryFs, RealFs};
+
+  #[tokio::test]
+  async fn copy_and_move_skip_excluded() {
+    let fs = MemoryFs::new();
+
+    fs.create_dir_all(Path::new("root/src/cache")).unwrap();
+    fs.write(Path::new("root/src/main.rs"), b"main").unwrap();
+    fs.write(Path::new("root/src/main.tmp"), b"tmp").unwrap();
+    fs.write(Path::new("root/src/cache/entry"), b"cache")
+      .unwrap();
+
+    let copy = Copy {
+      from: "src/**".to_string(),
+      to: "dist".to_string(),
+      overwrite: true,
+      exclude: vec!["**/*.tmp".to_string(), "src/cache".to_string()],
+    };
+
+    copy.execute(&fs, "root").await.unwrap();
+
+    assert!(fs.exists(Path::new("root/dist/main.rs")));
+    assert!(!fs.exists(Path::new("root/dist/main.tmp")));
+    assert!(!fs.exists(Path::new("root/dist/entry")));
+
+    let mv = Move {
+      from: "src/*".to_string(),
+      to: "moved".to_string(),
+      overwrite: true,
+      exclude: vec!["**/*.rs".to_string()],
+    };
+
+    mv.execute(&fs, "root").await.unwrap();
+
+    assert!(fs.exists(Path::new("root/src/main.rs")));
+    assert!(fs.exists(Path::new("root/moved/main.tmp")));
+    assert!(fs.exists(Path::new("root/moved/cache/entry")));
+  }
+
+  #[tokio::test]
+  async fn mkdir_creates_nested_directories() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    let mkdir = MakeDir { target: "src/assets/images".to_string() };
+
+    mkdir.execute(&RealFs, root).await.unwrap();
+    assert!(root.join("src/assets/images").is_dir());
+
+    // Existing directories are fine.
+    mkdir.execute(&RealFs, root).await.unwrap();
+  }
+
+  #[cfg(unix)]
+  #[tokio::test]
+  async fn symlink_relative_to_root() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::create_dir_all(root.join("shared")).unwrap();
+    fs::write(root.join("shared/.editorconfig"), "root = true").unwrap();
+    fs::write(root.join("existing"), "existing").unwrap();
+
+    let link = |to: &str, overwrite| {
+      Symlink {
+        from: "shared/.editorconfig".to_string(),
+        to: to.to_string(),
+        overwrite,
+      }
+    };
+
+    let diagnostics = Diagnostics::new();
+
+    link("app/.editorconfig", false)
+      .execute(&RealFs, root, &diagnostics)
+      .await
+      .unwrap();
+
+    assert_eq!(
+      fs::read_link(root.join("app/.editorconfig")).unwrap(),
+      Path::new("../shared/.editorconfig")
+    );
+
+    assert_eq!(
+      fs::read_to_string(root.join("app/.editorconfig")).unwrap(),
+      "root = true"
+    );
+
+    // Existing entries are only replaced if requested.
+    link("existing", false)
+      .execute(&RealFs, root, &diagnostics)
+      .await
+      .unwrap();
+
+    assert!(!root.join("existing").is_symlink());
+
+    link("existing", true)
+      .execute(&RealFs, root, &diagnostics)
+      .await
+      .unwrap();
+
+    assert!(root.join("existing").is_symlink());
+    assert!(diagnostics.warnings().is_empty());
+  }
+
+  #[tokio::test]
+  async fn symlink_falls_back_to_copy() {
+    let fs = MemoryFs::new();
+    let diagnostics = Diagnostics::new();
+
+    fs.create_dir_all(Path::new("root/shared/nested")).unwrap();
+    fs.write(Path::new("root/shared/nested/file"), b"contents")
+      .unwrap();
+
+    let link = Symlink {
+      from: "shared".to_string(),
+      to: "app/shared".to_string(),
+      overwrite: false,
+    };
+
+    link.execute(&fs, "root", &diagnostics).await.unwrap();
+
+    assert_eq!(
+      fs.read(Path::new("root/app/shared/nested/file")).unwrap(),
+      b"contents"
+    );
+
+    assert_eq!(diagnostics.warnings().len(), 1);
+  }
+
+  #[tokio::test]
+  async fn download_into_scaffold() {
+    let fs = MemoryFs::new();
+
+    let download = |url: String| Download { url, to: "licenses/LICENSE".to_string() };
+
+    let url = testing::serve_once("HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nMIT");
+
+    download(url).execute(&fs, "root").await.unwrap();
+
+    assert_eq!(fs.read(Path::new("root/licenses/LICENSE")).unwrap(), b"MIT");
+
+    let url = testing::serve_once("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+    let err = download(url).execute(&fs, "other").await.unwrap_err();
+
+    assert!(err.to_string().contains("404"));
+    assert!(!fs.exists(Path::new("other/licenses/LICENSE")));
+  }
+
+  #[tokio::test]
+  async fn template_renders_state_values() {
+    let fs = MemoryFs::new();
+
+    fs.create_dir_all(Path::new("root/.template")).unwrap();
+    fs.write(
+      Path::new("root/.template/README.md"),
+      b"# {{ NAME }}\n\n{{DESC}}, {{ NAME }}! {{ unterminated",
+    )
+    .unwrap();
+
+    let mut state = State::new();
+    state.set("NAME", Value::String("app".to_string()));
+    state.set("DESC", Value::String("Hello".to_string()));
+
+    let template = |to: &str| {
+      Template {
+        from: ".template/README.md".to_string(),
+        to: to.to_string(),
+        engine: TemplateEngine::Simple,
+      }
+    };
+
+    template("docs/README.md")
+      .execute(&fs, "root", &state)
+      .await
+      .unwrap();
+
+    assert_eq!(
+      fs.read(Path::new("root/docs/README.md")).unwrap(),
+      b"# app\n\nHello, app! {{ unterminated"
+    );
+
+    // Undefined variables are reported by name.
+    let err = template("README.md")
+      .execute(&fs, "root", &State::new())
+      .await
+      .unwrap_err();
+
+    assert!(err.to_string().contains("`NAME`"));
+    assert!(!fs.exists(Path::new("root/README.md")));
+  }
+
+  #[tokio::test]
+  async fn replace_in_templated_paths() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::create_dir_all(root.join("{CRATE}/src")).unwrap();
+    fs::write(root.join("{CRATE}/src/{MODULE}.rs"), "// {MODULE}").unwrap();
+
+    let mut state = State::new();
+    state.set("CRATE", Value::String("app".to_string()));
+    state.set("MODULE", Value::String("cli".to_string()));
+
+    let replace = Replace {
+      replacements: vec![
+        Replacement {
+          name: "CRATE".to_string(),
+          default: None,
+          files: None,
+        },
+        Replacement {
+          name: "MODULE".to_string(),
+          default: None,
+          files: None,
+        },
+      ],
+      glob: None,
+    };
+
+    replace.execute(&RealFs, root, &state).await.unwrap();
+
+    assert_eq!(
+      fs::read_to_string(root.join("app/src/cli.rs")).unwrap(),
+      "// cli"
+    );
+
+    assert!(!root.join("{CRATE}").exists());
+  }
+
+  #[tokio::test]
+  async fn replace_nested_tagged_paths_and_collisions() {
+    let fs = MemoryFs::new();
+
+    fs.create_dir_all(Path::new("root/__{NAME}__/__{NAME}__"))
+      .unwrap();
+    fs.write(Path::new("root/__{NAME}__/__{NAME}__/{NAME}.txt"), b"")
+      .unwrap();
+
+    let mut state = State::new();
+    state.set("NAME", Value::String("app".to_string()));
+
+    let replace = Replace {
+      replacements: vec![Replacement {
+        name: "NAME".to_string(),
+        default: None,
+        files: None,
+      }],
+      glob: None,
+    };
+
+    replace.execute(&fs, "root", &state).await.unwrap();
+
+    assert!(fs.is_file(Path::new("root/__app__/__app__/app.txt")));
+    assert!(!fs.exists(Path::new("root/__{NAME}__")));
+
+    // Renaming onto an existing entry is refused.
+    fs.write(Path::new("root/{NAME}.txt"), b"").unwrap();
+    fs.write(Path::new("root/app.txt"), b"").unwrap();
+
+    let err = replace.execute(&fs, "root", &state).await.unwrap_err();
+
+    assert!(err.to_string().contains("already exists"));
+  }
+
+  #[tokio::test]
+  async fn replace_scoped_to_files() {
+    let fs = MemoryFs::new();
+
+    fs.create_dir_all(Path::new("root/src")).unwrap();
+    fs.write(Path::new("root/LICENSE"), b"{AUTHOR}").unwrap();
+    fs.write(Path::new("root/README.md"), b"{AUTHOR}").unwrap();
+    fs.write(Path::new("root/src/{AUTHOR}.rs"), b"{AUTHOR}")
+      .unwrap();
+
+    let mut state = State::new();
+    state.set("AUTHOR", Value::String("norskeld".to_string()));
+
+    let replace = Replace {
+      replacements: vec![Replacement {
+        name: "AUTHOR".to_string(),
+        default: None,
+        files: Some(vec!["LICENSE".to_string(), "*.md".to_string()]),
+      }],
+      glob: None,
+    };
+
+    replace.execute(&fs, "root", &state).await.unwrap();
+
+    assert_eq!(fs.read(Path::new("root/LICENSE")).unwrap(), b"norskeld");
+    assert_eq!(fs.read(Path::new("root/README.md")).unwrap(), b"norskeld");
+    assert_eq!(
+      fs.read(Path::new("root/src/{AUTHOR}.rs")).unwrap(),
+      b"{AUTHOR}"
+    );
+  }
+
+  #[tokio::test]
+  async fn replace_falls_back_to_defaults() {
+    let fs = MemoryFs::new();
+
+    fs.create_dir_all(Path::new("root")).unwrap();
+    fs.write(Path::new("root/LICENSE"), b"{LICENSE} by {AUTHOR}")
+      .unwrap();
+
+    let mut state = State::new();
+    state.set("AUTHOR", Value::String("norskeld".to_string()));
+
+    let replacement = |name: &str, default: &str| {
+      Replacement {
+        name: name.to_string(),
+        default: Some(default.to_string()),
+        files: None,
+      }
+    };
+
+    // Values provided by prompts take precedence over defaults.
+    let replace = Replace {
+      replacements: vec![
+        replacement("LICENSE", "MIT"),
+        replacement("AUTHOR", "anonymous"),
+      ],
+      glob: None,
+    };
+
+    replace.execute(&fs, "root", &state).await.unwrap();
+
+    assert_eq!(
+      fs.read(Path::new("root/LICENSE")).unwrap(),
+      b"MIT by norskeld"
+    );
+  }
+
+  #[cfg(unix)]
+  #[tokio::test]
+  async fn run_in_working_directory() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::create_dir_all(root.join("packages/app")).unwrap();
+
+    let run = |cwd: &str| {
+      Run {
+        name: None,
+        command: "pwd > cwd.txt".to_string(),
+        injects: None,
+        cwd: Some(cwd.to_string()),
+        env: HashMap::new(),
+        continue_on_error: false,
+      }
+    };
+
+    run("packages/app")
+      .execute(root, &State::new(), &Diagnostics::new())
+      .await
+      .unwrap();
+
+    let cwd = fs::read_to_string(root.join("packages/app/cwd.txt")).unwrap();
+
+    assert_eq!(
+      fs::canonicalize(cwd.trim()).unwrap(),
+      fs::canonicalize(root.join("packages/app")).unwrap()
+    );
+
+    let err = run("missing")
+      .execute(root, &State::new(), &Diagnostics::new())
+      .await
+      .unwrap_err();
+
+    assert!(err.to_string().contains("doesn't exist"));
+  }
+
+  #[cfg(unix)]
+  #[tokio::test]
+  async fn run_with_env_vars() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    let mut state = State::new();
+    state.set("repo-name", Value::String("app".to_string()));
+    state.set("PM", Value::String("npm".to_string()));
+
+    let run = Run {
+      name: None,
+      command: r#"echo "$DECAFF_REPO_NAME $DECAFF_PM $NODE_ENV" > env.txt"#.to_string(),
+      injects: None,
+      cwd: None,
+      env: HashMap::from([
+        ("NODE_ENV".to_string(), "production".to_string()),
+        ("DECAFF_PM".to_string(), "pnpm".to_string()),
+      ]),
+      continue_on_error: false,
+    };
+
+    run
+      .execute(root, &state, &Diagnostics::new())
+      .await
+      .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(root.join("env.txt")).unwrap(),
+      "app pnpm production\n"
+    );
+  }
+
+  #[cfg(unix)]
+  #[tokio::test]
+  async fn run_continues_on_error() {
+    let temp = TempDir::new().unwrap();
+    let diagnostics = Diagnostics::new();
+
+    let run = Run {
+      name: Some("lint".to_string()),
+      command: "exit 3".to_string(),
+      injects: None,
+      cwd: None,
+      env: HashMap::new(),
+      continue_on_error: true,
+    };
+
+    run
+      .execute(temp.path(), &State::new(), &diagnostics)
+      .await
+      .unwrap();
+
+    assert_eq!(diagnostics.warnings().len(), 1);
+    assert_eq!(
+      diagnostics.warnings(),
+      vec!["Command failed with exit code 3, continuing: lint"]
+    );
+  }
+
+  #[tokio::test]
+  async fn git_init_with_initial_branch() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    let init = GitInit {
+      initial_branch: Some("trunk".to_string()),
+    };
+
+    init.execute(root).await.unwrap();
+    assert!(root.join(".git").is_dir());
+
+    let repository = GitRepository::open(root).unwrap();
+    let head = repository.find_reference("HEAD").unwrap();
+
+    assert_eq!(head.symbolic_target(), Some("refs/heads/trunk"));
+
+    // Initializing again is a no-op.
+    init.execute(root).await.unwrap();
+  }
+
+  #[tokio::test]
+  async fn echo_interpolates_state_values() {
+    let mut state = State::new();
+    state.set("PM", Value::String("npm".to_string()));
+
+    let diagnostics = Diagnostics::new();
+    let capture = Capture::start(Verbosity::Normal);
+
+    let echo = |message: &str| {
+      Echo {
+        message: message.to_string(),
+        injects: None,
+        trim: true,
+      }
+    };
+
+    echo(
+      r#"
+        Next steps:
+        {{ PM }} install
+      "#,
+    )
+    .execute(&state, &diagnostics)
+    .await
+    .unwrap();
+
+    echo("{{ MISSING }}")
+      .execute(&state, &diagnostics)
+      .await
+      .unwrap();
+
+    let messages = capture.finish();
+
+    assert_eq!(messages[0], "Next steps:\nnpm install");
+    assert_eq!(messages.last().unwrap(), "{{ MISSING }}");
+    assert_eq!(diagnostics.warnings(), vec!["Unknown variable: MISSING"]);
+  }
+
+  #[tokio::test]
+  async fn prompts_skipped_by_condition() {
+    let mut state = State::new();
+    state.set("use_database", Value::Bool(false));
+
+    let input = |when: &str| {
+      Prompt::Input(InputPrompt {
+        name: "database_url".to_string(),
+        hint: "Database URL".to_string(),
+        when: Some(when.to_string()),
+        default: None,
+        validate: None,
+        min_length: None,
+        max_length: None,
+        transform: None,
+        suggestions: Vec::new(),
+      })
+    };
+
+    // Would block on input if asked.
+    input("use_database").execute(&mut state).await.unwrap();
+    input("missing").execute(&mut state).await.unwrap();
+
+    assert!(state.get("database_url").is_none());
+
+    assert!(input("!use_database").should_ask(&state));
+    assert!(input("! missing").should_ask(&state));
+
+    state.set("use_database", Value::Bool(true));
+
+    assert!(input("use_database").should_ask(&state));
+    assert!(!input("!use_database").should_ask(&state));
+  }
+
+  #[tokio::test]
+  async fn replace_in_file_literal_and_regex() {
+    let fs = MemoryFs::new();
+
+    fs.create_dir_all(Path::new("root/src/nested")).unwrap();
+    fs.write(Path::new("root/src/a.rs"), b"v1 v2 (v3)").unwrap();
+    fs.write(Path::new("root/src/nested/b.rs"), b"v4").unwrap();
+    fs.write(Path::new("root/src/binary.rs"), b"v5\0").unwrap();
+    fs.write(Path::new("root/README.md"), b"v6").unwrap();
+
+    let replace = |pattern: &str, replacement: &str, regex| {
+      ReplaceInFile {
+        target: "src/**/*.rs".to_string(),
+        pattern: pattern.to_string(),
+        replacement: replacement.to_string(),
+        regex,
+      }
+    };
+
+    replace(r"v(\d)", "version $1", true)
+      .execute(&fs, "root")
+      .await
+      .unwrap();
+
+    assert_eq!(
+      fs.read(Path::new("root/src/a.rs")).unwrap(),
+      b"version 1 version 2 (version 3)"
+    );
+
+    assert_eq!(
+      fs.read(Path::new("root/src/nested/b.rs")).unwrap(),
+      b"version 4"
+    );
+
+    assert_eq!(fs.read(Path::new("root/src/binary.rs")).unwrap(), b"v5\0");
+    assert_eq!(fs.read(Path::new("root/README.md")).unwrap(), b"v6");
+
+    // Literal patterns match as is, and `$` in replacements is not expanded.
+    replace("(version 3)", "$3", false)
+      .execute(&fs, "root")
+      .await
+      .unwrap();
+
+    assert_eq!(
+      fs.read(Path::new("root/src/a.rs")).unwrap(),
+      b"version 1 version 2 $3"
+    );
+  }
+
+  #[tokio::test]
+  async fn write_interpolated_content() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    let mut state = State::new();
+    state.set("NAME", Value::String("app".to_string()));
+
+    let write = Write {
+      to: "config/app.toml".to_string(),
+      content: r#"
+        [package]
+        name = "{NAME}"
+      "#
+      .to_string(),
+      injects: Some(HashSet::from(["NAME".to_string()])),
+      overwrite: false,
+    };
+
+    write
+      .execute(&RealFs, root, &state, &Diagnostics::new())
+      .await
+      .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(root.join("config/app.toml")).unwrap(),
+      "[package]\nname = \"app\"\n"
+    );
+
+    // Existing files are kept intact if overwriting is disabled.
+    fs::write(root.join("config/app.toml"), "kept").unwrap();
+    write
+      .execute(&RealFs, root, &state, &Diagnostics::new())
+      .await
+      .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(root.join("config/app.toml")).unwrap(),
+      "kept"
+    );
+  }
+
+  #[tokio::test]
+  async fn rename_literal_and_glob() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::create_dir_all(root.join("nested/deep")).unwrap();
+    fs::write(root.join("gitignore"), "target").unwrap();
+    fs::write(root.join("nested/_env"), "A=1").unwrap();
+    fs::write(root.join("nested/deep/_env"), "B=2").unwrap();
+
+    let renames = [
+      Rename {
+        from: "gitignore".to_string(),
+        to: ".gitignore".to_string(),
+      },
+      Rename {
+        from: "**/_env".to_string(),
+        to: ".env".to_string(),
+      },
+    ];
+
+    for rename in &renames {
+      rename.execute(&RealFs, root).await.unwrap();
+    }
+
+    assert!(root.join(".gitignore").is_file());
+    assert!(!root.join("gitignore").exists());
+    assert_eq!(fs::read_to_string(root.join("nested/.env")).unwrap(), "A=1");
+    assert_eq!(
+      fs::read_to_string(root.join("nested/deep/.env")).unwrap(),
+      "B=2"
+    );
+  }
+
+  #[tokio::test]
+  async fn rename_refuses_to_overwrite() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::write(root.join("gitignore"), "new").unwrap();
+    fs::write(root.join(".gitignore"), "old").unwrap();
+
+    let rename = Rename {
+      from: "gitignore".to_string(),
+      to: ".gitignore".to_string(),
+    };
 
-    Ok(println!("{message}"))
+    assert!(rename.execute(&RealFs, root).await.is_err());
+    assert_eq!(fs::read_to_string(root.join(".gitignore")).unwrap(), "old");
   }
 }
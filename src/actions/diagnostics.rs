@@ -0,0 +1,68 @@
+use std::sync::Mutex;
+
+use crossterm::style::Stylize;
+
+use crate::logger::info;
+
+/// Collects non-fatal warnings emitted while executing actions, so they can be summarized at the
+/// end of a run instead of getting lost among the actions output.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+  warnings: Mutex<Vec<String>>,
+}
+
+impl Diagnostics {
+  /// Create a new collector.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Print a warning right away and record it for the summary.
+  pub fn warn<M>(&self, message: M)
+  where
+    M: Into<String>,
+  {
+    let message = message.into();
+
+    info!("{}", format!("? {message}").yellow());
+
+    self
+      .warnings
+      .lock()
+      .expect("Diagnostics lock is poisoned")
+      .push(message);
+  }
+
+  /// Get recorded warnings, in order of emission.
+  pub fn warnings(&self) -> Vec<String> {
+    self
+      .warnings
+      .lock()
+      .expect("Diagnostics lock is poisoned")
+      .clone()
+  }
+
+  /// Print a grouped summary of recorded warnings. Prints nothing if there were none.
+  pub fn report(&self) {
+    let warnings = self.warnings();
+
+    if warnings.is_empty() {
+      return;
+    }
+
+    let noun = if warnings.len() == 1 {
+      "warning"
+    } else {
+      "warnings"
+    };
+
+    info!(
+      "{}",
+      format!("? {} {noun}:", warnings.len()).yellow().bold()
+    );
+
+    for warning in warnings {
+      info!("└─ {}", warning.yellow());
+    }
+  }
+}
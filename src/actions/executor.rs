@@ -1,12 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::io;
+use std::sync::Arc;
 
 use crossterm::style::Stylize;
 use miette::Diagnostic;
 use thiserror::Error;
-use tokio::fs;
 
-use crate::config::{ActionSingle, ActionSuite, Actions, Config, Value};
+use super::actions::ActionError;
+use crate::actions::Diagnostics;
+use crate::config::{ActionSingle, ActionSuite, Actions, Answers, Config, Value};
+use crate::logger::info;
+use crate::vfs::{Filesystem, RealFs};
 
 #[derive(Debug, Diagnostic, Error)]
 pub enum ExecutorError {
@@ -19,16 +24,55 @@ pub enum ExecutorError {
   },
 }
 
-#[derive(Debug)]
 pub struct State {
   /// A map of replacements and associated values.
   values: HashMap<String, Value>,
+  /// Names of values that are secret, e.g. passwords. These are redacted in debug output.
+  secrets: HashSet<String>,
+  /// Answers supplied upfront.
+  answers: Option<Answers>,
+  /// Whether to use default values of prompts instead of asking.
+  defaults: bool,
+  /// Whether prompts lacking an answer or a default value can be asked interactively.
+  interactive: bool,
 }
 
 impl State {
   /// Create a new state.
   pub fn new() -> Self {
-    Self { values: HashMap::new() }
+    Self {
+      values: HashMap::new(),
+      secrets: HashSet::new(),
+      answers: None,
+      defaults: false,
+      interactive: true,
+    }
+  }
+
+  /// Create a new state for non-interactive prompts, answered with supplied answers if any, falling
+  /// back to default values if `defaults` is set.
+  pub fn non_interactive(answers: Option<Answers>, defaults: bool) -> Self {
+    Self {
+      answers,
+      defaults,
+      interactive: false,
+      ..Self::new()
+    }
+  }
+
+  /// Get answers supplied upfront, if any.
+  pub fn answers(&self) -> Option<&Answers> {
+    self.answers.as_ref()
+  }
+
+  /// Whether to use default values of prompts instead of asking.
+  pub fn use_defaults(&self) -> bool {
+    self.defaults
+  }
+
+  /// Whether prompts lacking an answer or a default value can be asked interactively.
+  pub fn is_interactive(&self) -> bool {
+    self.interactive
   }
 
   /// Get a value from the state.
@@ -36,10 +80,44 @@ impl State {
     self.values.get(name)
   }
 
+  /// Iterate over all values in the state.
+  pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+    self.values.iter()
+  }
+
   /// Set a value in the state.
   pub fn set<N: Into<String> + AsRef<str>>(&mut self, name: N, replacement: Value) {
     self.values.insert(name.into(), replacement);
   }
+
+  /// Set a secret value in the state. It's used like any other value, but never shows up in debug
+  /// output.
+  pub fn set_secret<N: Into<String> + AsRef<str>>(&mut self, name: N, replacement: Value) {
+    let name = name.into();
+
+    self.secrets.insert(name.clone());
+    self.values.insert(name, replacement);
+  }
+}
+
+impl fmt::Debug for State {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let values = self
+      .values
+      .iter()
+      .map(|(name, value)| {
+        let value = if self.secrets.contains(name) {
+          "[redacted]".to_string()
+        } else {
+          format!("{value:?}")
+        };
+
+        (name, value)
+      })
+      .collect::<HashMap<_, _>>();
+
+    f.debug_struct("State").field("values", &values).finish()
+  }
 }
 
 impl Default for State {
@@ -53,51 +131,202 @@ impl Default for State {
 pub struct Executor {
   /// The config to use for execution.
   config: Config,
+  /// Whether to skip `run` actions.
+  skip_runs: bool,
+  /// Whether to skip actions that can only run on disk.
+  dry_run: bool,
+  /// Filesystem to operate on.
+  fs: Arc<dyn Filesystem>,
+  /// Warnings emitted by actions.
+  diagnostics: Diagnostics,
+  /// Answers to prompts supplied upfront.
+  answers: Option<Answers>,
+  /// Whether to use default values of prompts instead of asking.
+  defaults: bool,
+  /// Whether to fail on prompts instead of asking.
+  non_interactive: bool,
 }
 
 impl Executor {
   /// Create a new executor.
   pub fn new(config: Config) -> Self {
-    Self { config }
+    Self {
+      config,
+      skip_runs: false,
+      dry_run: false,
+      fs: Arc::new(RealFs),
+      diagnostics: Diagnostics::new(),
+      answers: None,
+      defaults: false,
+      non_interactive: false,
+    }
+  }
+
+  /// Set the filesystem to operate on. Defaults to the disk. Note that `run` and `git-init` actions
+  /// always run on disk, so they should be skipped when operating on other filesystems, see
+  /// [Executor::dry_run].
+  pub fn filesystem(mut self, fs: Arc<dyn Filesystem>) -> Self {
+    self.fs = fs;
+    self
   }
 
-  /// Execute the actions.
-  pub async fn execute(&self) -> miette::Result<()> {
-    match &self.config.actions {
+  /// Get warnings emitted by actions so far.
+  pub fn diagnostics(&self) -> &Diagnostics {
+    &self.diagnostics
+  }
+
+  /// Set whether this is a dry run, skipping actions that can only run on disk, i.e. `run` and
+  /// `git-init`, and reporting them as skipped instead.
+  pub fn dry_run(mut self, dry_run: bool) -> Self {
+    self.dry_run = dry_run;
+    self
+  }
+
+  /// Set whether to skip `run` actions, reporting them as skipped instead.
+  pub fn skip_runs(mut self, skip_runs: bool) -> Self {
+    self.skip_runs = skip_runs;
+    self
+  }
+
+  /// Set answers to prompts. If set, prompts are not interactive, and all prompts must have an
+  /// answer, except for conditional ones that end up being skipped.
+  pub fn answers(mut self, answers: Option<Answers>) -> Self {
+    self.answers = answers;
+    self
+  }
+
+  /// Set whether to use default values of prompts instead of asking. Prompts without a default
+  /// value must have an answer then. Answers take precedence over default values.
+  pub fn defaults(mut self, defaults: bool) -> Self {
+    self.defaults = defaults;
+    self
+  }
+
+  /// Set whether to fail on prompts instead of asking. Prompts must have an answer or a default
+  /// value then. This is implied if answers were supplied or defaults should be used.
+  pub fn non_interactive(mut self, non_interactive: bool) -> Self {
+    self.non_interactive = non_interactive;
+    self
+  }
+
+  /// Whether prompts are answered without asking.
+  fn is_non_interactive(&self) -> bool {
+    self.non_interactive || self.answers.is_some() || self.defaults
+  }
+
+  /// Execute the actions, returning the number of executed actions.
+  pub async fn execute(&self) -> miette::Result<usize> {
+    self.check_answers()?;
+    self.renames().await?;
+
+    let executed = match &self.config.actions {
       | Actions::Suite(suites) => self.suite(suites).await?,
       | Actions::Flat(actions) => self.flat(actions).await?,
-      | Actions::Empty => return Ok(()),
+      | Actions::Empty => return Ok(0),
     };
 
-    // Delete the config file if needed.
+    // Delete the config file, along with imported ones, if needed.
     if self.config.options.delete {
-      fs::remove_file(&self.config.config)
-        .await
-        .map_err(|source| {
+      for config in [&self.config.config]
+        .into_iter()
+        .chain(&self.config.imports)
+      {
+        if !self.fs.exists(config) {
+          continue;
+        }
+
+        self.fs.remove_file(config).map_err(|source| {
           ExecutorError::Io {
             message: "Failed to delete config file.".to_string(),
             source,
           }
         })?;
+      }
+    }
+
+    Ok(executed)
+  }
+
+  /// Check that every unconditional prompt has an answer or a default value in non-interactive
+  /// mode, so we don't fail halfway through.
+  fn check_answers(&self) -> miette::Result<()> {
+    if !self.is_non_interactive() {
+      return Ok(());
+    }
+
+    let prompts = self
+      .config
+      .actions
+      .singles()
+      .into_iter()
+      .filter_map(|action| {
+        match action {
+          | ActionSingle::Prompt(prompt) if prompt.when().is_none() => Some(prompt),
+          | _ => None,
+        }
+      })
+      .filter(|prompt| {
+        let answered = self
+          .answers
+          .as_ref()
+          .is_some_and(|answers| answers.get(prompt.name()).is_some());
+
+        let defaulted = self.defaults && prompt.default_value().is_some();
+
+        !answered && !defaulted
+      })
+      .map(|prompt| prompt.name().to_string())
+      .collect::<Vec<_>>();
+
+    match prompts {
+      | names if names.is_empty() => Ok(()),
+      | names if self.defaults => Err(ActionError::NoDefaults { names }.into()),
+      | names => Err(ActionError::Unanswered { names }.into()),
+    }
+  }
+
+  /// Create a new state, non-interactive if needed.
+  fn state(&self) -> State {
+    if self.is_non_interactive() {
+      State::non_interactive(self.answers.clone(), self.defaults)
+    } else {
+      State::new()
+    }
+  }
+
+  /// Apply renames from the config.
+  async fn renames(&self) -> miette::Result<()> {
+    if self.config.renames.is_empty() {
+      return Ok(());
+    }
+
+    info!("⋅ Applying renames:");
+
+    for rename in &self.config.renames {
+      rename.execute(self.fs.as_ref(), &self.config.root).await?;
     }
 
+    info!();
+
     Ok(())
   }
 
   /// Execute suites of actions.
-  async fn suite(&self, suites: &[ActionSuite]) -> miette::Result<()> {
-    let mut state = State::new();
+  async fn suite(&self, suites: &[ActionSuite]) -> miette::Result<usize> {
+    let mut state = self.state();
+    let mut executed = 0;
 
     for ActionSuite { name, actions, .. } in suites {
       let hint = "Suite".cyan();
       let name = name.clone().green();
 
-      println!("[{hint}: {name}]\n");
+      info!("[{hint}: {name}]\n");
 
       let mut it = actions.iter().peekable();
 
       while let Some(action) = it.next() {
         self.single(action, &mut state).await?;
+        executed += 1;
 
         // Do not print a trailing newline if the current and the next actions are prompts to
         // slightly improve visual clarity. Essentially, this way prompts are grouped.
@@ -106,39 +335,214 @@ impl Executor {
           (ActionSingle::Prompt(_), Some(ActionSingle::Prompt(_)))
             | (ActionSingle::Unknown(_), Some(ActionSingle::Unknown(_)))
         ) {
-          println!();
+          info!();
         }
       }
     }
 
-    Ok(())
+    Ok(executed)
   }
 
   /// Execute a flat list of actions.
-  async fn flat(&self, actions: &[ActionSingle]) -> miette::Result<()> {
-    let mut state = State::new();
+  async fn flat(&self, actions: &[ActionSingle]) -> miette::Result<usize> {
+    let mut state = self.state();
 
     for action in actions {
       self.single(action, &mut state).await?;
-      println!();
+      info!();
     }
 
-    Ok(())
+    Ok(actions.len())
   }
 
   /// Execute a single action.
   async fn single(&self, action: &ActionSingle, state: &mut State) -> miette::Result<()> {
     let root = &self.config.root;
+    let fs = self.fs.as_ref();
+    let diagnostics = &self.diagnostics;
 
     match action {
-      | ActionSingle::Copy(action) => action.execute(root).await,
-      | ActionSingle::Move(action) => action.execute(root).await,
-      | ActionSingle::Delete(action) => action.execute(root).await,
-      | ActionSingle::Echo(action) => action.execute(state).await,
-      | ActionSingle::Run(action) => action.execute(root, state).await,
+      | ActionSingle::Copy(action) => action.execute(fs, root).await,
+      | ActionSingle::Move(action) => action.execute(fs, root).await,
+      | ActionSingle::Delete(action) => action.execute(fs, root).await,
+      | ActionSingle::MakeDir(action) => action.execute(fs, root).await,
+      | ActionSingle::Symlink(action) => action.execute(fs, root, diagnostics).await,
+      | ActionSingle::Write(action) => action.execute(fs, root, state, diagnostics).await,
+      | ActionSingle::Download(action) => action.execute(fs, root).await,
+      | ActionSingle::Template(action) => action.execute(fs, root, state).await,
+      | ActionSingle::Echo(action) => action.execute(state, diagnostics).await,
+      | ActionSingle::Run(action) if self.dry_run => action.skip("--dry-run", diagnostics).await,
+      | ActionSingle::Run(action) if self.skip_runs => action.skip("--no-run", diagnostics).await,
+      | ActionSingle::Run(action) => action.execute(root, state, diagnostics).await,
+      | ActionSingle::GitInit(action) if self.dry_run => {
+        action.skip("--dry-run", diagnostics).await
+      },
+      | ActionSingle::GitInit(action) => action.execute(root).await,
       | ActionSingle::Prompt(action) => action.execute(state).await,
-      | ActionSingle::Replace(action) => action.execute(root, state).await,
-      | ActionSingle::Unknown(action) => action.execute().await,
+      | ActionSingle::Replace(action) => action.execute(fs, root, state).await,
+      | ActionSingle::ReplaceInFile(action) => action.execute(fs, root).await,
+      | ActionSingle::Unknown(action) => action.execute(diagnostics).await,
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashSet;
+  use std::fs;
+  use std::path::Path;
+
+  use tempfile::TempDir;
+
+  use super::*;
+  use crate::config::actions::{Copy, Run, Unknown, Write};
+  use crate::logger::testing::Capture;
+  use crate::logger::Verbosity;
+  use crate::vfs::{EntryKind, MemoryFs};
+
+  #[test]
+  fn redact_secrets_in_debug_output() {
+    let mut state = State::new();
+
+    state.set("NAME", Value::String("app".to_string()));
+    state.set_secret("TOKEN", Value::String("s3cr3t".to_string()));
+
+    let debug = format!("{state:?}");
+
+    assert!(debug.contains("app"));
+    assert!(debug.contains("[redacted]"));
+    assert!(!debug.contains("s3cr3t"));
+
+    // Secrets are still usable as values.
+    assert_eq!(state.get("TOKEN").unwrap().to_string(), "s3cr3t");
+  }
+
+  #[tokio::test]
+  async fn skip_run_actions() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::write(root.join("source.txt"), "contents").unwrap();
+
+    let mut config = Config::new(root);
+
+    config.options.delete = false;
+    config.actions = Actions::Flat(vec![
+      ActionSingle::Copy(Copy {
+        from: "source.txt".to_string(),
+        to: "copied".to_string(),
+        overwrite: true,
+        exclude: Vec::new(),
+      }),
+      ActionSingle::Run(Run {
+        name: None,
+        command: "touch ran.txt".to_string(),
+        injects: None,
+        cwd: None,
+        env: HashMap::new(),
+        continue_on_error: false,
+      }),
+    ]);
+
+    Executor::new(config)
+      .skip_runs(true)
+      .execute()
+      .await
+      .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(root.join("copied/source.txt")).unwrap(),
+      "contents"
+    );
+
+    assert!(!root.join("ran.txt").exists());
+  }
+
+  #[tokio::test]
+  async fn report_warnings_at_the_end() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    let mut config = Config::new(root);
+
+    config.options.delete = false;
+    config.actions = Actions::Flat(vec![
+      ActionSingle::Unknown(Unknown { name: "frobnicate".to_string() }),
+      ActionSingle::Write(Write {
+        to: "hello.txt".to_string(),
+        content: "Hello, {name}".to_string(),
+        injects: Some(HashSet::from(["name".to_string()])),
+        overwrite: true,
+      }),
+    ]);
+
+    let capture = Capture::start(Verbosity::Normal);
+    let executor = Executor::new(config);
+
+    executor.execute().await.unwrap();
+    executor.diagnostics().report();
+
+    let output = capture.finish();
+    let summary = &output[output.len() - 3..];
+
+    assert!(summary[0].contains("2 warnings:"));
+    assert!(summary[1].contains("Unknown action: frobnicate"));
+    assert!(summary[2].contains("Unknown injection: name"));
+  }
+
+  #[tokio::test]
+  async fn scaffold_in_memory() {
+    let fs = Arc::new(MemoryFs::new());
+    let root = Path::new("project");
+
+    let config = r##"
+      renames {
+        "gitignore" ".gitignore"
+      }
+
+      actions {
+        cp from=".template/*.toml" to="."
+        mv from=".template/src/*" to="src"
+        rm ".template"
+        write to="README.md" {
+          content "# project"
+        }
+        run "touch ran.txt"
+      }
+    "##;
+
+    fs.create_dir_all(&root.join(".template/src")).unwrap();
+    fs.write(&root.join("decaff.kdl"), config.as_bytes())
+      .unwrap();
+    fs.write(&root.join("gitignore"), b"target").unwrap();
+    fs.write(&root.join(".template/Cargo.toml"), b"[package]")
+      .unwrap();
+    fs.write(&root.join(".template/src/main.rs"), b"fn main() {}")
+      .unwrap();
+
+    let mut config = Config::new(root);
+    assert!(config.load_from(fs.as_ref()).unwrap());
+
+    Executor::new(config)
+      .filesystem(fs.clone())
+      .skip_runs(true)
+      .execute()
+      .await
+      .unwrap();
+
+    let mut files = fs
+      .walk(root, false)
+      .flatten()
+      .filter(|(_, kind)| *kind == EntryKind::File)
+      .map(|(path, _)| path.strip_prefix(root).unwrap().display().to_string())
+      .collect::<Vec<_>>();
+
+    files.sort();
+
+    assert_eq!(
+      files,
+      [".gitignore", "Cargo.toml", "README.md", "src/main.rs"]
+    );
+    assert_eq!(fs.read(&root.join("README.md")).unwrap(), b"# project\n");
+  }
+}
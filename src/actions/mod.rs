@@ -1,5 +1,7 @@
+pub use diagnostics::*;
 pub use executor::*;
 
 mod actions;
+mod diagnostics;
 mod executor;
 mod prompts;
@@ -1,8 +1,14 @@
-use inquire::{Confirm, CustomType, Editor, Select, Text};
+use std::path::Path;
+
+use inquire::validator::Validation;
+use inquire::{
+  Confirm, CustomType, Editor, MultiSelect, Password, PasswordDisplayMode, Select, Text,
+};
+use regex::Regex;
 
 use crate::actions::State;
 use crate::config::prompts::*;
-use crate::config::{Number, Value};
+use crate::config::{Number, NumberParseError, Value};
 use crate::utils::prompts as helpers;
 
 impl ConfirmPrompt {
@@ -41,8 +47,34 @@ impl InputPrompt {
       prompt = prompt.with_validator(inquire::required!("This field is required."));
     }
 
+    if let Some(regex) = self.validate.clone() {
+      prompt = prompt.with_validator(move |value: &str| {
+        Ok(match check_pattern(value, &regex) {
+          | Ok(()) => Validation::Valid,
+          | Err(message) => Validation::Invalid(message.into()),
+        })
+      });
+    }
+
+    if !self.suggestions.is_empty() {
+      let suggestions = self.suggestions.clone();
+
+      prompt = prompt.with_autocomplete(move |input: &str| Ok(suggest(&suggestions, input)));
+    }
+
+    if self.min_length.is_some() || self.max_length.is_some() {
+      let (min, max) = (self.min_length, self.max_length);
+
+      prompt = prompt.with_validator(move |value: &str| {
+        Ok(match check_length(value, min, max) {
+          | Ok(()) => Validation::Valid,
+          | Err(message) => Validation::Invalid(message.into()),
+        })
+      });
+    }
+
     match prompt.prompt() {
-      | Ok(value) => state.set(name, Value::String(value)),
+      | Ok(value) => state.set(name, Value::String(transform(value, self.transform))),
       | Err(err) => helpers::interrupt(err),
     }
 
@@ -54,9 +86,19 @@ impl NumberPrompt {
   pub async fn execute(&self, state: &mut State) -> miette::Result<()> {
     let (name, hint, help) = helpers::messages(&self.name, &self.hint);
 
+    let (integer, min, max) = (self.integer, self.min.clone(), self.max.clone());
+
     let mut prompt = CustomType::<Number>::new(&hint)
       .with_help_message(&help)
       .with_formatter(&|input| input.to_string())
+      .with_validator(move |value: &Number| {
+        Ok(
+          match check_number(value, integer, min.as_ref(), max.as_ref()) {
+            | Ok(()) => Validation::Valid,
+            | Err(message) => Validation::Invalid(message.into()),
+          },
+        )
+      })
       .with_render_config(helpers::theme());
 
     if let Some(default) = &self.default {
@@ -76,6 +118,104 @@ impl NumberPrompt {
   }
 }
 
+/// Returns suggestions containing the input, case-insensitively, or all of them if the input is
+/// empty.
+fn suggest(suggestions: &[String], input: &str) -> Vec<String> {
+  let input = input.to_lowercase();
+
+  suggestions
+    .iter()
+    .filter(|suggestion| suggestion.to_lowercase().contains(&input))
+    .cloned()
+    .collect()
+}
+
+/// Checks that the answer matches the regular expression.
+fn check_pattern(value: &str, regex: &Regex) -> Result<(), String> {
+  if regex.is_match(value) {
+    Ok(())
+  } else {
+    Err(format!("Expected a value matching `{}`.", regex.as_str()))
+  }
+}
+
+/// Checks that the length of the answer in characters is within the given inclusive range.
+fn check_length(value: &str, min: Option<usize>, max: Option<usize>) -> Result<(), String> {
+  let length = value.chars().count();
+
+  match (min, max) {
+    | (Some(min), _) if length < min => Err(format!("Expected at least {min} character(s).")),
+    | (_, Some(max)) if length > max => Err(format!("Expected at most {max} character(s).")),
+    | _ => Ok(()),
+  }
+}
+
+/// Checks that the number is an integer if required, and is within the given inclusive range.
+fn check_number(
+  value: &Number,
+  integer: bool,
+  min: Option<&Number>,
+  max: Option<&Number>,
+) -> Result<(), String> {
+  if integer && matches!(value, Number::Float(_)) {
+    return Err("Expected an integer.".to_string());
+  }
+
+  let value = value.as_f64();
+
+  match (min, max) {
+    | (Some(min), Some(max)) if value < min.as_f64() || value > max.as_f64() => {
+      Err(format!("Expected a number between {min} and {max}."))
+    },
+    | (Some(min), None) if value < min.as_f64() => {
+      Err(format!("Expected a number greater than or equal to {min}."))
+    },
+    | (None, Some(max)) if value > max.as_f64() => {
+      Err(format!("Expected a number less than or equal to {max}."))
+    },
+    | _ => Ok(()),
+  }
+}
+
+/// Applies the transform, if any, to the answer.
+fn transform(value: String, transform: Option<Transform>) -> String {
+  match transform {
+    | None => value,
+    | Some(Transform::Upper) => value.to_uppercase(),
+    | Some(Transform::Lower) => value.to_lowercase(),
+    | Some(Transform::Slug) => join_words(&value, '-', |c| c.is_ascii_alphanumeric(), false),
+    | Some(Transform::Kebab) => join_words(&value, '-', char::is_alphanumeric, true),
+  }
+}
+
+/// Splits the value into lowercased words of characters accepted by `is_word`, optionally splitting
+/// camel case words as well, and joins them with the separator.
+fn join_words(value: &str, separator: char, is_word: fn(char) -> bool, camel: bool) -> String {
+  let mut result = String::with_capacity(value.len());
+  let mut previous: Option<char> = None;
+
+  for c in value.chars() {
+    if !is_word(c) {
+      previous = None;
+      continue;
+    }
+
+    let starts_word = match previous {
+      | None => true,
+      | Some(previous) => camel && previous.is_lowercase() && c.is_uppercase(),
+    };
+
+    if starts_word && !result.is_empty() {
+      result.push(separator);
+    }
+
+    result.extend(c.to_lowercase());
+    previous = Some(c);
+  }
+
+  result
+}
+
 impl SelectPrompt {
   pub async fn execute(&self, state: &mut State) -> miette::Result<()> {
     let (name, hint, help) = helpers::messages(&self.name, &self.hint);
@@ -83,11 +223,262 @@ impl SelectPrompt {
     let options = self.options.iter().map(String::to_string).collect();
 
     let prompt = Select::new(&hint, options)
+      .with_starting_cursor(self.starting_cursor())
       .with_help_message(&help)
       .with_render_config(helpers::theme());
 
     match prompt.prompt() {
-      | Ok(value) => state.set(name, Value::String(value)),
+      | Ok(value) => state.set(name, Value::String(transform(value, self.transform))),
+      | Err(err) => helpers::interrupt(err),
+    }
+
+    Ok(())
+  }
+}
+
+impl MultiSelectPrompt {
+  pub async fn execute(&self, state: &mut State) -> miette::Result<()> {
+    let (name, hint, help) = helpers::messages(&self.name, &self.hint);
+
+    let options = self.options.iter().map(String::to_string).collect();
+
+    let defaults = self
+      .default
+      .iter()
+      .flatten()
+      .filter_map(|default| self.options.iter().position(|option| option == default))
+      .collect::<Vec<_>>();
+
+    let prompt = MultiSelect::new(&hint, options)
+      .with_default(&defaults)
+      .with_help_message(&help)
+      .with_render_config(helpers::theme());
+
+    match prompt.prompt() {
+      | Ok(values) => state.set(name, Value::Array(values)),
+      | Err(err) => helpers::interrupt(err),
+    }
+
+    Ok(())
+  }
+}
+
+impl PasswordPrompt {
+  pub async fn execute(&self, state: &mut State) -> miette::Result<()> {
+    let (name, hint, help) = helpers::messages(&self.name, &self.hint);
+
+    let mut prompt = Password::new(&hint)
+      .with_help_message(&help)
+      .with_display_mode(PasswordDisplayMode::Masked)
+      .with_formatter(&|_| String::new())
+      .with_validator(inquire::required!("This field is required."))
+      .with_render_config(helpers::theme());
+
+    if !self.confirm {
+      prompt = prompt.without_confirmation();
+    }
+
+    match prompt.prompt() {
+      | Ok(value) => state.set_secret(name, Value::String(value)),
+      | Err(err) => helpers::interrupt(err),
+    }
+
+    Ok(())
+  }
+}
+
+// Answers supplied upfront, e.g. via `--answers`. These are validated the same way as interactive
+// input, and transformed if needed.
+
+impl ConfirmPrompt {
+  pub fn answer(&self, value: Value) -> Result<Value, String> {
+    match value {
+      | Value::Bool(_) => Ok(value),
+      | _ => Err("Expected a boolean.".to_string()),
+    }
+  }
+}
+
+impl InputPrompt {
+  pub fn answer(&self, value: Value) -> Result<Value, String> {
+    let value = expect_string(value)?;
+
+    if let Some(regex) = &self.validate {
+      check_pattern(&value, regex)?;
+    }
+
+    check_length(&value, self.min_length, self.max_length)?;
+
+    Ok(Value::String(transform(value, self.transform)))
+  }
+}
+
+impl NumberPrompt {
+  pub fn answer(&self, value: Value) -> Result<Value, String> {
+    let number = match value {
+      | Value::Number(number) => number,
+      | Value::String(string) => {
+        string
+          .parse()
+          .map_err(|err: NumberParseError| err.to_string())?
+      },
+      | _ => return Err("Expected a number.".to_string()),
+    };
+
+    check_number(&number, self.integer, self.min.as_ref(), self.max.as_ref())?;
+
+    Ok(Value::Number(number))
+  }
+}
+
+impl SelectPrompt {
+  pub fn answer(&self, value: Value) -> Result<Value, String> {
+    let value = expect_option(expect_string(value)?, &self.options)?;
+
+    Ok(Value::String(transform(value, self.transform)))
+  }
+}
+
+impl MultiSelectPrompt {
+  pub fn answer(&self, value: Value) -> Result<Value, String> {
+    let values = match value {
+      | Value::Array(values) => values,
+      | value => vec![expect_string(value)?],
+    };
+
+    values
+      .into_iter()
+      .map(|value| expect_option(value, &self.options))
+      .collect::<Result<_, _>>()
+      .map(Value::Array)
+  }
+}
+
+impl PasswordPrompt {
+  pub fn answer(&self, value: Value) -> Result<Value, String> {
+    expect_string(value).map(Value::String)
+  }
+}
+
+impl PathPrompt {
+  pub fn answer(&self, value: Value) -> Result<Value, String> {
+    let value = expand_tilde(&expect_string(value)?);
+
+    check_path(&value, self.must_exist, self.kind)?;
+
+    Ok(Value::String(value))
+  }
+}
+
+impl EditorPrompt {
+  pub fn answer(&self, value: Value) -> Result<Value, String> {
+    expect_string(value).map(Value::String)
+  }
+}
+
+// Default values, used instead of asking with `--yes`.
+
+impl ConfirmPrompt {
+  pub fn default_value(&self) -> Option<Value> {
+    self.default.map(Value::Bool)
+  }
+}
+
+impl InputPrompt {
+  pub fn default_value(&self) -> Option<Value> {
+    self
+      .default
+      .clone()
+      .map(|default| Value::String(transform(default, self.transform)))
+  }
+}
+
+impl NumberPrompt {
+  pub fn default_value(&self) -> Option<Value> {
+    self.default.clone().map(Value::Number)
+  }
+}
+
+impl SelectPrompt {
+  pub fn default_value(&self) -> Option<Value> {
+    self
+      .default
+      .clone()
+      .map(|default| Value::String(transform(default, self.transform)))
+  }
+}
+
+impl MultiSelectPrompt {
+  pub fn default_value(&self) -> Option<Value> {
+    self.default.clone().map(Value::Array)
+  }
+}
+
+impl PathPrompt {
+  pub fn default_value(&self) -> Option<Value> {
+    self
+      .default
+      .as_deref()
+      .map(|default| Value::String(expand_tilde(default)))
+  }
+}
+
+impl EditorPrompt {
+  pub fn default_value(&self) -> Option<Value> {
+    self.default.clone().map(Value::String)
+  }
+}
+
+/// Expects a string answer. Numbers are accepted as well and converted to strings.
+fn expect_string(value: Value) -> Result<String, String> {
+  match value {
+    | Value::String(string) => Ok(string),
+    | Value::Number(number) => Ok(number.to_string()),
+    | _ => Err("Expected a string.".to_string()),
+  }
+}
+
+/// Expects an answer to be one of the options.
+fn expect_option(value: String, options: &[String]) -> Result<String, String> {
+  if options.contains(&value) {
+    Ok(value)
+  } else {
+    Err(format!(
+      "Expected one of: {}.",
+      options
+        .iter()
+        .map(|option| format!("`{option}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+    ))
+  }
+}
+
+impl PathPrompt {
+  pub async fn execute(&self, state: &mut State) -> miette::Result<()> {
+    let (name, hint, help) = helpers::messages(&self.name, &self.hint);
+
+    let (must_exist, kind) = (self.must_exist, self.kind);
+
+    let mut prompt = Text::new(&hint)
+      .with_help_message(&help)
+      .with_formatter(helpers::empty_formatter())
+      .with_validator(move |value: &str| {
+        Ok(match check_path(&expand_tilde(value), must_exist, kind) {
+          | Ok(()) => Validation::Valid,
+          | Err(message) => Validation::Invalid(message.into()),
+        })
+      })
+      .with_render_config(helpers::theme());
+
+    if let Some(default) = &self.default {
+      prompt = prompt.with_default(default);
+    } else {
+      prompt = prompt.with_validator(inquire::required!("This field is required."));
+    }
+
+    match prompt.prompt() {
+      | Ok(value) => state.set(name, Value::String(expand_tilde(&value))),
       | Err(err) => helpers::interrupt(err),
     }
 
@@ -95,6 +486,53 @@ impl SelectPrompt {
   }
 }
 
+/// Expands the leading `~` to the home directory, if it can be resolved.
+fn expand_tilde(value: &str) -> String {
+  expand_tilde_with(value, home::home_dir().as_deref())
+}
+
+fn expand_tilde_with(value: &str, home: Option<&Path>) -> String {
+  let rest = match value.strip_prefix('~') {
+    | Some(rest) if rest.is_empty() || rest.starts_with(['/', std::path::MAIN_SEPARATOR]) => rest,
+    | _ => return value.to_string(),
+  };
+
+  match home {
+    | Some(home) => format!("{}{rest}", home.display()),
+    | None => value.to_string(),
+  }
+}
+
+/// Checks that the path exists if required, and that it is of the given kind if it exists.
+fn check_path(value: &str, must_exist: bool, kind: Option<PathKind>) -> Result<(), String> {
+  let path = Path::new(value);
+
+  if !path.exists() {
+    return if must_exist {
+      Err(format!("Path '{value}' doesn't exist."))
+    } else {
+      Ok(())
+    };
+  }
+
+  match kind {
+    | Some(PathKind::File) if !path.is_file() => Err(format!("Path '{value}' is not a file.")),
+    | Some(PathKind::Dir) if !path.is_dir() => Err(format!("Path '{value}' is not a directory.")),
+    | _ => Ok(()),
+  }
+}
+
+impl SelectPrompt {
+  /// Returns the index of the default option, or of the first one if there's no default.
+  fn starting_cursor(&self) -> usize {
+    self
+      .default
+      .as_ref()
+      .and_then(|default| self.options.iter().position(|option| option == default))
+      .unwrap_or(0)
+  }
+}
+
 impl EditorPrompt {
   pub async fn execute(&self, state: &mut State) -> miette::Result<()> {
     let (name, hint, help) = helpers::messages(&self.name, &self.hint);
@@ -107,6 +545,10 @@ impl EditorPrompt {
       prompt = prompt.with_predefined_text(default);
     }
 
+    if let Some(extension) = &self.extension {
+      prompt = prompt.with_file_extension(extension);
+    }
+
     match prompt.prompt() {
       | Ok(value) => state.set(name, Value::String(value)),
       | Err(err) => helpers::interrupt(err),
@@ -115,3 +557,164 @@ impl EditorPrompt {
     Ok(())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use tempfile::TempDir;
+
+  use super::*;
+
+  fn check_port(value: Number, integer: bool) -> Result<(), String> {
+    check_number(
+      &value,
+      integer,
+      Some(&Number::Integer(1)),
+      Some(&Number::Integer(65535)),
+    )
+  }
+
+  #[test]
+  fn input_matches_pattern() {
+    let regex = Regex::new("^[a-z][a-z0-9-]*$").unwrap();
+
+    assert!(check_pattern("decaff-cli", &regex).is_ok());
+
+    assert_eq!(
+      check_pattern("Decaff CLI", &regex),
+      Err("Expected a value matching `^[a-z][a-z0-9-]*$`.".to_string())
+    );
+  }
+
+  #[test]
+  fn input_suggestions_filtered() {
+    let suggestions = ["MIT", "Apache-2.0", "MPL-2.0", "GPL-3.0"].map(String::from);
+
+    assert_eq!(suggest(&suggestions, ""), suggestions);
+    assert_eq!(suggest(&suggestions, "m"), vec!["MIT", "MPL-2.0"]);
+    assert_eq!(suggest(&suggestions, "PL-"), vec!["MPL-2.0", "GPL-3.0"]);
+    assert!(suggest(&suggestions, "BSD").is_empty());
+  }
+
+  #[test]
+  fn input_length_within_range() {
+    assert_eq!(
+      check_length("", Some(1), Some(3)),
+      Err("Expected at least 1 character(s).".to_string())
+    );
+
+    assert_eq!(
+      check_length("four", Some(1), Some(3)),
+      Err("Expected at most 3 character(s).".to_string())
+    );
+
+    assert!(check_length("abc", Some(1), Some(3)).is_ok());
+    assert!(check_length("äöü", None, Some(3)).is_ok());
+    assert!(check_length("", None, None).is_ok());
+  }
+
+  #[test]
+  fn transforms() {
+    let apply = |value: &str, kind| transform(value.to_string(), Some(kind));
+
+    assert_eq!(apply("  My Project!", Transform::Slug), "my-project");
+    assert_eq!(apply("Café au lait", Transform::Slug), "caf-au-lait");
+    assert_eq!(apply("myProject", Transform::Slug), "myproject");
+
+    assert_eq!(apply("My Project", Transform::Upper), "MY PROJECT");
+    assert_eq!(apply("My Project", Transform::Lower), "my project");
+
+    assert_eq!(apply("myProject name", Transform::Kebab), "my-project-name");
+    assert_eq!(apply("Crème_Brûlée 2", Transform::Kebab), "crème-brûlée-2");
+
+    assert_eq!(transform("My Project".to_string(), None), "My Project");
+  }
+
+  #[test]
+  fn path_exists_and_of_kind() {
+    let temp = TempDir::new().unwrap();
+    let dir = temp.path().to_str().unwrap().to_string();
+    let file = temp.path().join("file.txt");
+
+    fs::write(&file, "").unwrap();
+
+    let file = file.to_str().unwrap().to_string();
+    let missing = temp.path().join("missing").to_str().unwrap().to_string();
+
+    // Existing paths.
+    assert!(check_path(&dir, true, Some(PathKind::Dir)).is_ok());
+    assert!(check_path(&file, true, Some(PathKind::File)).is_ok());
+    assert!(check_path(&file, true, None).is_ok());
+
+    // Non-existent paths.
+    assert!(check_path(&missing, true, None).is_err());
+    assert!(check_path(&missing, false, Some(PathKind::File)).is_ok());
+
+    // Wrong kinds.
+    assert!(check_path(&file, false, Some(PathKind::Dir))
+      .is_err_and(|err| err.contains("not a directory")));
+    assert!(
+      check_path(&dir, true, Some(PathKind::File)).is_err_and(|err| err.contains("not a file"))
+    );
+  }
+
+  #[test]
+  fn path_expands_tilde() {
+    let home = Path::new("/home/user");
+
+    assert_eq!(expand_tilde_with("~", Some(home)), "/home/user");
+    assert_eq!(
+      expand_tilde_with("~/projects", Some(home)),
+      "/home/user/projects"
+    );
+    assert_eq!(
+      expand_tilde_with("~user/projects", Some(home)),
+      "~user/projects"
+    );
+    assert_eq!(expand_tilde_with("a/~", Some(home)), "a/~");
+    assert_eq!(expand_tilde_with("~/projects", None), "~/projects");
+  }
+
+  #[test]
+  fn select_starts_at_default() {
+    let select = |default: Option<&str>| {
+      SelectPrompt {
+        name: "pm".to_string(),
+        hint: "Package manager".to_string(),
+        when: None,
+        options: vec!["npm".to_string(), "pnpm".to_string(), "yarn".to_string()],
+        default: default.map(str::to_string),
+        transform: None,
+      }
+    };
+
+    assert_eq!(select(Some("yarn")).starting_cursor(), 2);
+    assert_eq!(select(None).starting_cursor(), 0);
+  }
+
+  #[test]
+  fn number_within_range() {
+    assert!(check_port(Number::Integer(8080), true).is_ok());
+    assert!(check_port(Number::Integer(1), true).is_ok());
+    assert!(check_port(Number::Integer(65535), true).is_ok());
+
+    assert_eq!(
+      check_port(Number::Integer(0), true),
+      Err("Expected a number between 1 and 65535.".to_string())
+    );
+
+    assert!(check_port(Number::Integer(65536), true).is_err());
+  }
+
+  #[test]
+  fn number_rejects_floats_and_non_numbers() {
+    assert_eq!(
+      check_port(Number::Float(80.5), true),
+      Err("Expected an integer.".to_string())
+    );
+
+    assert!(check_port(Number::Float(80.5), false).is_ok());
+    assert!("eighty".parse::<Number>().is_err());
+  }
+}
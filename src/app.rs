@@ -1,18 +1,37 @@
+use std::env;
+use std::fmt::{self, Display};
 use std::fs;
-use std::io;
-use std::path::{Path, PathBuf};
+use std::future::Future;
+use std::io::{self, IsTerminal};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::time::Instant;
 
 use clap::{Args, Parser, Subcommand};
 use crossterm::style::Stylize;
-use miette::Diagnostic;
+use futures_util::{future, StreamExt};
+use indicatif::HumanBytes;
+use inquire::Confirm;
+use miette::{Diagnostic, IntoDiagnostic};
 use thiserror::Error;
+use tokio::task;
+use walkdir::WalkDir;
 
 use crate::actions::Executor;
-use crate::cache::Cache;
-use crate::config::{Config, ConfigOptionsOverrides};
+use crate::auth::{CredentialProvider, DefaultCredentials};
+use crate::cache::{Cache, ListFormat};
+use crate::config::{ActionSingle, Answers, Config, ConfigOptionsOverrides, STARTER_CONFIG};
+use crate::logger::{self, info, summary, verbose, Verbosity};
+use crate::path::Normalization;
 use crate::report;
-use crate::repository::{LocalRepository, RemoteRepository};
-use crate::unpacker::Unpacker;
+use crate::repository::{
+  FetchError, LocalRepository, RefPrecedence, RemoteRepository, RepositoryHost, RepositoryMeta,
+};
+use crate::unpacker::{UnpackOptions, UnpackStats, Unpacker};
+use crate::utils::open;
+use crate::utils::prompts as helpers;
+use crate::vfs::{EntryKind, Filesystem, MemoryFs, RealFs};
 
 #[derive(Debug, Diagnostic, Error)]
 pub enum AppError {
@@ -36,13 +55,38 @@ pub struct AppState {
 
 #[derive(Clone, Debug, Parser)]
 #[command(version, about, long_about = None)]
-pub enum Cli {
+pub struct Cli {
+  #[command(subcommand)]
+  command: Command,
+  /// Suppress all output except errors.
+  #[arg(short, long, global = true, conflicts_with = "verbose")]
+  quiet: bool,
+  /// Print more details, like URLs, resolved hashes and timings.
+  #[arg(short, long, global = true)]
+  verbose: bool,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum Command {
   /// Scaffold from a remote repository.
   #[command(visible_alias = "r")]
   Remote(RepositoryArgs),
   /// Scaffold from a local repository.
   #[command(visible_alias = "l")]
   Local(RepositoryArgs),
+  /// Download and cache remote repositories without scaffolding, so later runs hit the cache.
+  #[command(visible_alias = "p")]
+  Prefetch(PrefetchArgs),
+  /// Run prompts and actions of an already unpacked template in place, skipping fetching and
+  /// unpacking. Useful for iterating on templates.
+  #[command(visible_alias = "a")]
+  Apply(ApplyArgs),
+  /// Check that a config parses and only contains known actions, without running anything.
+  #[command(visible_alias = "v")]
+  Validate(ValidateArgs),
+  /// Write a starter config with commented examples, to get started with authoring templates.
+  #[command(visible_alias = "i")]
+  Init(InitArgs),
   /// Commands for interacting with the cache.
   #[command(visible_alias = "c")]
   Cache {
@@ -57,35 +101,172 @@ pub struct RepositoryArgs {
   src: String,
   /// Directory to scaffold to.
   path: Option<String>,
-  /// Scaffold from a specified ref (branch, tag, or commit).
+  /// Scaffold from a specified ref (branch, tag, or commit). Use `<branch>@<commit>` to pin a
+  /// branch at a specific commit, `release:<tag>` to download the release's tarball asset, or
+  /// `latest` to use the highest semver tag. Semver requirements like `^1.2` and glob patterns
+  /// like `v1.*` are supported too.
   #[arg(name = "REF", short = 'r', long = "ref")]
   meta: Option<String>,
+  /// Force the host, treating the source as `user/repo` literally even if it looks like it has a
+  /// host prefix. Only applies to remote repositories.
+  #[arg(long, value_name = "HOST", value_parser = parse_host)]
+  force_host: Option<RepositoryHost>,
+  /// Which ref to prefer if the given ref names both a tag and a branch. Only applies to remote
+  /// repositories.
+  #[arg(long, value_name = "KIND", value_enum, default_value_t)]
+  prefer: RefPrecedence,
   /// Clean up on failure. No-op if failed because target directory already exists.
   #[arg(short = 'C', long)]
   cleanup: bool,
+  /// Scaffold into an existing directory without asking for confirmation, overwriting existing
+  /// files. Refuses to scaffold into ancestors of the current directory, or the home directory,
+  /// unless they are empty.
+  #[arg(short, long)]
+  force: bool,
   /// Delete config after scaffolding is complete.
   #[arg(short, long)]
   delete: Option<bool>,
   /// Skip reading config and running actions.
   #[arg(short, long)]
   skip: bool,
+  /// Name of the config file. Defaults to `$DECAFF_CONFIG` or `decaff.kdl`.
+  #[arg(long, value_name = "NAME")]
+  config: Option<String>,
+  /// Skip all `run` actions, while still applying file actions. Useful for untrusted templates.
+  #[arg(long)]
+  no_run: bool,
+  /// Preview scaffolding without touching the destination. Files are unpacked and actions are
+  /// applied in memory, while `run` and `git-init` actions are only reported.
+  #[arg(long)]
+  dry_run: bool,
   /// Use cached template if available.
   #[arg(short = 'c', long, default_value = "true")]
   cache: bool,
+  /// Unicode normalization to apply to generated filenames.
+  #[arg(long, value_enum, default_value_t = Normalization::None)]
+  normalize: Normalization,
+  /// Initialize and update git submodules after checking out. Only applies to local repositories.
+  #[arg(long)]
+  recurse_submodules: bool,
+  /// Scaffold from a subdirectory of the repository, e.g. `templates/web`.
+  #[arg(long, value_parser = parse_subdir)]
+  subdir: Option<PathBuf>,
+  /// Only unpack entries matching the glob pattern. Can be specified multiple times.
+  #[arg(long, value_name = "GLOB")]
+  include: Vec<String>,
+  /// Skip entries matching the glob pattern, e.g. `.github`. Can be specified multiple times.
+  #[arg(long, value_name = "GLOB")]
+  exclude: Vec<String>,
+  /// Open the scaffolded project in the editor from `$VISUAL` or `$EDITOR`, or open the given URL
+  /// after scaffolding. Skipped in non-interactive environments.
+  #[arg(long, value_name = "URL", require_equals = true)]
+  open: Option<Option<String>>,
+  /// Suppress all progress and actions output, printing only a final one-line summary with the
+  /// destination, ref and number of executed actions.
+  #[arg(long)]
+  summary_only: bool,
+  /// Answer prompts from a JSON or KDL file instead of asking, e.g. in CI. Missing answers are
+  /// errors.
+  #[arg(long, value_name = "PATH")]
+  answers: Option<PathBuf>,
+  /// Use default values of prompts instead of asking. Prompts without a default value are errors,
+  /// unless answered via `--answers`. Also implies `--force`.
+  #[arg(short, long, visible_alias = "defaults")]
+  yes: bool,
+  /// Fail on prompts lacking an answer or a default value instead of asking. Implied by
+  /// `--answers` and `--yes`.
+  #[arg(long)]
+  non_interactive: bool,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct PrefetchArgs {
+  /// Repositories to prefetch.
+  #[arg(required = true)]
+  sources: Vec<String>,
+  /// Prefetch a specified ref (branch, tag, or commit) of each repository.
+  #[arg(name = "REF", short = 'r', long = "ref")]
+  meta: Option<String>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct ApplyArgs {
+  /// Directory containing the config to apply.
+  path: PathBuf,
+  /// Name of the config file. Defaults to `$DECAFF_CONFIG` or `decaff.kdl`.
+  #[arg(long, value_name = "NAME")]
+  config: Option<String>,
+  /// Delete config after applying. Unlike scaffolding, the config is kept by default.
+  #[arg(short, long)]
+  delete: Option<bool>,
+  /// Skip all `run` actions, while still applying file actions.
+  #[arg(long)]
+  no_run: bool,
+  /// Answer prompts from a JSON or KDL file instead of asking. Missing answers are errors.
+  #[arg(long, value_name = "PATH")]
+  answers: Option<PathBuf>,
+  /// Use default values of prompts instead of asking. Prompts without a default value are errors,
+  /// unless answered via `--answers`.
+  #[arg(short, long, visible_alias = "defaults")]
+  yes: bool,
+  /// Fail on prompts lacking an answer or a default value instead of asking. Implied by
+  /// `--answers` and `--yes`.
+  #[arg(long)]
+  non_interactive: bool,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct ValidateArgs {
+  /// Directory containing the config to validate.
+  #[arg(default_value = ".")]
+  path: PathBuf,
+  /// Name of the config file. Defaults to `$DECAFF_CONFIG` or `decaff.kdl`.
+  #[arg(long, value_name = "NAME")]
+  config: Option<String>,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct InitArgs {
+  /// Directory to write the config to. Created if it doesn't exist.
+  #[arg(default_value = ".")]
+  path: PathBuf,
+  /// Name of the config file. Defaults to `$DECAFF_CONFIG` or `decaff.kdl`.
+  #[arg(long, value_name = "NAME")]
+  config: Option<String>,
+  /// Overwrite the config if it already exists.
+  #[arg(short, long)]
+  force: bool,
 }
 
 #[derive(Clone, Debug, Subcommand)]
 pub enum CacheCommand {
   /// List cache entries.
-  List,
+  List {
+    /// Only list entries matching the given source, partial source, ref name or hash.
+    source: Option<String>,
+    /// Print entries as JSON.
+    #[arg(long)]
+    json: bool,
+  },
   /// Remove cache entries.
   Remove {
     /// List of cache entries to remove.
     entries: Vec<String>,
     /// Remove all cache entries.
-    #[arg(short, long, conflicts_with_all = ["entries", "interactive"])]
+    #[arg(short, long, conflicts_with = "entries")]
     all: bool,
+    /// Print what would be removed, without removing anything.
+    #[arg(long)]
+    dry_run: bool,
   },
+  /// Remove all cache entries. Same as `remove --all`.
+  Clear {
+    /// Print what would be removed, without removing anything.
+    #[arg(long)]
+    dry_run: bool,
+  },
+  /// Remove cached tarballs that aren't referenced by the manifest.
+  Gc,
 }
 
 #[derive(Debug)]
@@ -94,6 +275,8 @@ pub struct App {
   cli: Cli,
   /// Current state of the application.
   state: AppState,
+  /// Provider of credentials for fetching refs, tarballs and submodules.
+  credentials: Arc<dyn CredentialProvider>,
 }
 
 impl App {
@@ -102,9 +285,19 @@ impl App {
     Self {
       cli: Cli::parse(),
       state: AppState::default(),
+      credentials: Arc::new(DefaultCredentials),
     }
   }
 
+  /// Sets the credential provider to use instead of [DefaultCredentials].
+  pub fn credentials<P>(mut self, provider: P) -> Self
+  where
+    P: CredentialProvider + 'static,
+  {
+    self.credentials = Arc::new(provider);
+    self
+  }
+
   /// Runs the app and prints any errors.
   pub async fn run(&mut self) {
     miette::set_hook(Box::new(|_| {
@@ -123,92 +316,175 @@ impl App {
     if scaffold_res.is_err() {
       report::try_report(scaffold_res);
       report::try_report(self.cleanup());
+
+      process::exit(1);
     }
   }
 
   /// Kicks of the scaffolding process.
   pub async fn scaffold(&mut self) -> miette::Result<()> {
-    match self.cli.clone() {
-      | Cli::Remote(args) => self.scaffold_remote(args).await,
-      | Cli::Local(args) => self.scaffold_local(args).await,
-      | Cli::Cache { command } => self.handle_cache(command),
+    if self.cli.quiet {
+      logger::set_verbosity(Verbosity::Quiet);
+    } else if self.cli.verbose {
+      logger::set_verbosity(Verbosity::Verbose);
+    }
+
+    match self.cli.command.clone() {
+      | Command::Remote(args) => self.scaffold_remote(args).await,
+      | Command::Local(args) => self.scaffold_local(args).await,
+      | Command::Prefetch(args) => self.prefetch(args).await,
+      | Command::Apply(args) => self.apply(args).await,
+      | Command::Validate(args) => self.validate(args),
+      | Command::Init(args) => self.init(args),
+      | Command::Cache { command } => self.handle_cache(command),
     }
   }
 
   async fn scaffold_remote(&mut self, args: RepositoryArgs) -> miette::Result<()> {
-    let mut remote = RemoteRepository::new(args.src, args.meta)?;
+    // Never raise verbosity, since `--quiet` suppresses the summary as well.
+    if args.summary_only {
+      logger::set_verbosity(logger::verbosity().min(Verbosity::Summary));
+    }
+
+    // Read answers early, so we don't download anything if they're malformed.
+    let answers = args.answers.as_deref().map(Answers::load).transpose()?;
+
+    let mut remote = match args.force_host {
+      | Some(host) => RemoteRepository::with_host(args.src.clone(), args.meta.clone(), host)?,
+      | None => RemoteRepository::new(args.src.clone(), args.meta.clone())?,
+    };
+
+    if args.recurse_submodules {
+      info!(
+        "{}",
+        "? Tarballs don't include submodules, ignoring --recurse-submodules".yellow()
+      );
+    }
 
     // Try to fetch refs early. If we can't get them, there's no point in continuing.
-    remote.fetch_refs()?;
+    remote.fetch_refs(args.prefer, self.credentials.as_ref())?;
+
+    // Resolve special selectors like `latest` to actual refs.
+    remote.resolve_meta()?;
 
     // Try to resolve a ref to specific hash.
     let hash = remote.resolve_hash()?;
 
+    verbose!("{}", format!("~ Resolved {} to {hash}", remote.meta).dim());
+
     let name = args.path.as_ref().unwrap_or(&remote.repo);
     let destination = PathBuf::from(name);
 
-    // Cleanup on failure.
-    self.state.cleanup = args.cleanup;
+    // Cleanup on failure. Dry runs don't create anything to clean up.
+    self.state.cleanup = args.cleanup && !args.dry_run;
     self.state.cleanup_path = Some(destination.clone());
 
-    // Check if destination already exists before downloading.
+    // Check if destination already exists before downloading. Unpacking overwrites existing files,
+    // so forcing needs no special handling.
     if let Ok(true) = &destination.try_exists() {
       // We do not want to remove already existing directory.
       self.state.cleanup = false;
 
-      miette::bail!(
-        "Failed to scaffold: '{}' already exists.",
-        destination.display()
-      );
+      check_existing(&destination, &args)?;
     }
 
+    let fs = filesystem(args.dry_run);
     let mut cache = Cache::init()?;
-    let mut bytes = None;
+
+    let options = UnpackOptions {
+      normalization: args.normalize,
+      subdir: args.subdir.clone(),
+      include: args.include.clone(),
+      exclude: args.exclude.clone(),
+      ..UnpackOptions::default()
+    };
 
     let source = remote.get_source();
+    let key = remote.cache_key(&hash);
     let mut should_fetch = !args.cache;
+    let mut stats = UnpackStats::default();
+    let started = Instant::now();
 
     if args.cache {
-      println!("{}", "~ Attempting to read from cache".dim());
+      info!("{}", "~ Attempting to read from cache".dim());
+
+      if let Some(cached) = cache.read(&source, &key)? {
+        info!("{}", "~ Found in cache, reading".dim());
+        stats = Unpacker::new(cached)
+          .options(options.clone())
+          .unpack_into(fs.as_ref(), &destination)?;
 
-      if let Some(cached) = cache.read(&source, &hash)? {
-        println!("{}", "~ Found in cache, reading".dim());
-        bytes = Some(cached);
+        info!("{}", format!("~ Extracted {stats}").dim());
+        verbose!("{}", format!("~ Took {:.2?}", started.elapsed()).dim());
       } else {
-        println!("{}", "~ Nothing found in cache, fetching".dim());
+        info!("{}", "~ Nothing found in cache, fetching".dim());
         should_fetch = true;
       }
     }
 
+    // Decompress and unpack the tarball while it's being downloaded. If caching is enabled, the
+    // tarball is written to the cache along the way and stored once it's complete. Dry runs don't
+    // write to the cache.
     if should_fetch {
-      bytes = Some(remote.fetch().await?);
-    }
+      let should_cache = args.cache && !args.dry_run;
+      let mut tarball = should_cache.then(|| cache.pending(&key)).transpose()?;
+
+      let chunks = remote
+        .fetch_stream(self.credentials.as_ref())
+        .await?
+        .inspect(|chunk| {
+          if let (Some(tarball), Ok(chunk)) = (tarball.as_mut(), chunk) {
+            tarball.append(chunk);
+          }
+        });
 
-    // Decompress and unpack the tarball. If somehow the tarball is empty, bail.
-    if let Some(bytes) = bytes {
-      if should_fetch {
-        cache.write(&source, &remote.meta.to_string(), &hash, &bytes)?;
+      stats = Unpacker::unpack_stream_into(chunks, fs.clone(), &destination, options).await?;
+
+      info!("{}", format!("~ Extracted {stats}").dim());
+      verbose!("{}", format!("~ Took {:.2?}", started.elapsed()).dim());
+
+      if let Some(tarball) = tarball {
+        cache.store(&source, &remote.meta.to_string(), &key, tarball)?;
+        verbose!("{}", format!("~ Cached {source} @ {key}").dim());
       }
+    }
 
-      let unpacker = Unpacker::new(bytes);
-      unpacker.unpack_to(&destination)?;
-    } else {
-      miette::bail!("Failed to scaffold: zero bytes.");
+    let executed = self
+      .scaffold_execute(&destination, &args, answers, fs)
+      .await?;
+
+    if let (Some(target), false) = (&args.open, args.dry_run) {
+      open::open(target.as_deref(), &destination);
     }
 
-    self
-      .scaffold_execute(
-        &destination,
-        args.skip,
-        ConfigOptionsOverrides { delete: args.delete },
-      )
-      .await
+    summary!(
+      "{}",
+      Summary {
+        source: source.clone(),
+        meta: remote.meta.to_string(),
+        hash: Some(hash),
+        destination: &destination,
+        files: stats.files,
+        executed,
+        dry_run: args.dry_run,
+      }
+    );
+
+    Ok(())
   }
 
   async fn scaffold_local(&mut self, args: RepositoryArgs) -> miette::Result<()> {
-    let local = LocalRepository::new(args.src, args.meta);
+    // Never raise verbosity, since `--quiet` suppresses the summary as well.
+    if args.summary_only {
+      logger::set_verbosity(logger::verbosity().min(Verbosity::Summary));
+    }
 
-    let destination = if let Some(destination) = args.path {
+    // Read answers early, so we don't download anything if they're malformed.
+    let answers = args.answers.as_deref().map(Answers::load).transpose()?;
+
+    let local = LocalRepository::new(args.src.clone(), args.meta.clone());
+
+    let destination = if let Some(destination) = &args.path {
       PathBuf::from(destination)
     } else {
       local
@@ -218,99 +494,298 @@ impl App {
         .unwrap_or_default()
     };
 
-    // Cleanup on failure.
-    self.state.cleanup = args.cleanup;
+    // Cleanup on failure. Dry runs don't create anything to clean up.
+    self.state.cleanup = args.cleanup && !args.dry_run;
     self.state.cleanup_path = Some(destination.clone());
 
     // Check if destination already exists before performing local clone.
-    if let Ok(true) = &destination.try_exists() {
+    let exists = matches!(destination.try_exists(), Ok(true));
+
+    if exists {
       // We do not want to remove already existing directory.
       self.state.cleanup = false;
 
-      miette::bail!(
-        "Failed to scaffold: '{}' already exists.",
-        destination.display()
-      );
+      check_existing(&destination, &args)?;
     }
 
+    let fs = filesystem(args.dry_run);
+
+    // When forced into an existing directory, copy into a staging directory first and merge it
+    // afterwards, so checking out and removing the inner .git directory don't touch the existing
+    // contents. Dry runs copy into memory, so there's nothing to protect.
+    let staged = exists && !args.dry_run;
+
+    let target = if staged {
+      staging_for(&destination)?
+    } else {
+      destination.clone()
+    };
+
     // Copy the directory.
-    local.copy(&destination)?;
+    verbose!(
+      "{}",
+      format!("~ Copying from {}", local.source.display()).dim()
+    );
+
+    let started = Instant::now();
+
+    local.copy_into(fs.as_ref(), &target, args.normalize)?;
+
+    verbose!("{}", format!("~ Took {:.2?}", started.elapsed()).dim());
+
+    let mut hash = None;
+
+    // Checking out needs a repository on disk, so dry runs preview the working tree as-is.
+    if local.is_git() && args.dry_run {
+      info!("{}", "~ Cloned repository".dim());
+
+      info!(
+        "{} {}",
+        "? Dry run, not checking out ref:".yellow(),
+        local.meta.0.as_str().yellow()
+      );
 
-    // .git directory path.
-    let inner_git = destination.join(".git");
+      fs.remove_dir_all(&target.join(".git")).map_err(|source| {
+        AppError::Io {
+          message: "Failed to remove inner .git directory.".to_string(),
+          source,
+        }
+      })?;
 
-    // If we copied a repository, we also need to checkout the ref.
-    if let Ok(true) = inner_git.try_exists() {
-      println!("{}", "~ Cloned repository".dim());
+      info!("{}", "~ Removed inner .git directory".dim());
+    } else if local.is_git() {
+      // If we copied a repository, we also need to checkout the ref. Plain directories are used
+      // as-is.
+      info!("{}", "~ Cloned repository".dim());
 
       // Checkout the ref.
-      local.checkout(&destination)?;
+      hash = Some(local.checkout(&target)?);
+
+      info!(
+        "{} {}",
+        "~ Checked out ref:".dim(),
+        local.meta.0.as_str().dim()
+      );
+
+      if args.recurse_submodules {
+        let submodules = local.update_submodules(&target, self.credentials.as_ref())?;
 
-      println!("{} {}", "~ Checked out ref:".dim(), local.meta.0.dim());
+        // Submodules have their own .git files pointing into the inner .git directory.
+        for submodule in &submodules {
+          fs::remove_file(target.join(submodule).join(".git")).map_err(|source| {
+            AppError::Io {
+              message: format!(
+                "Failed to remove .git file of the '{}' submodule.",
+                submodule.display()
+              ),
+              source,
+            }
+          })?;
+
+          info!(
+            "{} {}",
+            "~ Updated submodule:".dim(),
+            submodule.display().to_string().dim()
+          );
+        }
+      }
 
       // At last, remove the inner .git directory.
-      fs::remove_dir_all(inner_git).map_err(|source| {
+      fs::remove_dir_all(target.join(".git")).map_err(|source| {
         AppError::Io {
           message: "Failed to remove inner .git directory.".to_string(),
           source,
         }
       })?;
 
-      println!("{}", "~ Removed inner .git directory".dim());
+      info!("{}", "~ Removed inner .git directory".dim());
     } else {
-      println!("{}", "~ Copied directory".dim());
+      info!("{}", "~ Copied directory".dim());
+
+      if local.meta != RepositoryMeta::default() {
+        info!(
+          "{}",
+          "? Not a git repository, ignoring the requested ref".yellow()
+        );
+      }
     }
 
-    self
-      .scaffold_execute(
-        &destination,
-        args.skip,
-        ConfigOptionsOverrides { delete: args.delete },
-      )
-      .await
+    if let Some(subdir) = &args.subdir {
+      local.extract_subdir_in(fs.as_ref(), &target, subdir)?;
+
+      info!(
+        "{} {}",
+        "~ Extracted subdirectory:".dim(),
+        subdir.display().to_string().dim()
+      );
+    }
+
+    let files = count_files(fs.as_ref(), &target);
+
+    if staged {
+      merge_into(&target, &destination)?;
+
+      info!(
+        "{} {}",
+        "~ Merged into existing directory:".dim(),
+        destination.display().to_string().dim()
+      );
+    }
+
+    let executed = self
+      .scaffold_execute(&destination, &args, answers, fs)
+      .await?;
+
+    if let (Some(target), false) = (&args.open, args.dry_run) {
+      open::open(target.as_deref(), &destination);
+    }
+
+    summary!(
+      "{}",
+      Summary {
+        source: local.source.display().to_string(),
+        meta: local.meta.0.clone(),
+        hash,
+        destination: &destination,
+        files,
+        executed,
+        dry_run: args.dry_run,
+      }
+    );
+
+    Ok(())
   }
 
   async fn scaffold_execute(
     &mut self,
     destination: &Path,
-    should_skip: bool,
-    overrides: ConfigOptionsOverrides,
-  ) -> miette::Result<()> {
-    if should_skip {
-      println!("{}", "~ Skipping running actions".dim());
-      return Ok(());
+    args: &RepositoryArgs,
+    answers: Option<Answers>,
+    fs: Arc<dyn Filesystem>,
+  ) -> miette::Result<usize> {
+    if args.skip {
+      info!("{}", "~ Skipping running actions".dim());
+      return Ok(0);
     }
 
     // Read the config (if it is present).
-    let mut config = Config::new(destination);
+    let config_name = Config::resolve_name(args.config.clone());
+    let mut config = Config::new(destination).name(&config_name);
 
-    if config.load()? {
-      println!();
+    if config.load_from(fs.as_ref())? {
+      info!();
 
-      config.override_with(overrides);
+      config.override_with(ConfigOptionsOverrides { delete: args.delete });
 
       // Create executor and kick off execution.
-      let executor = Executor::new(config);
+      let executor = Executor::new(config)
+        .filesystem(fs)
+        .dry_run(args.dry_run)
+        .skip_runs(args.no_run)
+        .answers(answers)
+        .defaults(args.yes)
+        .non_interactive(args.non_interactive);
+      let started = Instant::now();
+      let executed = executor.execute().await?;
+
+      verbose!(
+        "{}",
+        format!("~ Executed actions in {:.2?}", started.elapsed()).dim()
+      );
+
+      executor.diagnostics().report();
 
-      executor.execute().await
+      Ok(executed)
     } else {
-      Ok(())
+      Ok(0)
     }
   }
 
-  fn handle_cache(&mut self, command: CacheCommand) -> miette::Result<()> {
+  async fn apply(&mut self, args: ApplyArgs) -> miette::Result<()> {
+    let delete = args.delete.unwrap_or(false);
+    let name = Config::resolve_name(args.config);
+    let answers = args.answers.as_deref().map(Answers::load).transpose()?;
+    let options = ApplyOptions {
+      no_run: args.no_run,
+      delete,
+      answers,
+      defaults: args.yes,
+      non_interactive: args.non_interactive,
+    };
+
+    let executed = apply(&args.path, &name, options).await?;
+
+    let noun = if executed == 1 { "action" } else { "actions" };
+
+    summary!("Applied {} ({executed} {noun})", args.path.display());
+
+    Ok(())
+  }
+
+  fn validate(&mut self, args: ValidateArgs) -> miette::Result<()> {
+    let name = Config::resolve_name(args.config);
+    let config = validate(&args.path, &name)?;
+
+    let count = config.actions.singles().len();
+    let noun = if count == 1 { "action" } else { "actions" };
+
+    summary!(
+      "{} {} is valid ({count} {noun})",
+      "✓".green(),
+      config.config.display()
+    );
+
+    Ok(())
+  }
+
+  fn init(&mut self, args: InitArgs) -> miette::Result<()> {
+    let name = Config::resolve_name(args.config);
+    let config = init(&args.path, &name, args.force)?;
+
+    summary!("{} Created {}", "✓".green(), config.display());
+
+    Ok(())
+  }
+
+  async fn prefetch(&mut self, args: PrefetchArgs) -> miette::Result<()> {
     let mut cache = Cache::init()?;
 
-    match command {
-      | CacheCommand::List => Ok(cache.list()?),
-      | CacheCommand::Remove { entries, all } => {
-        if all {
-          cache.remove_all()
-        } else {
-          cache.remove(entries)
-        }
-      },
+    // Resolving refs is blocking, so each source is resolved on a separate thread.
+    let resolutions = args.sources.into_iter().map(|source| {
+      let meta = args.meta.clone();
+      let credentials = Arc::clone(&self.credentials);
+
+      task::spawn_blocking(move || -> miette::Result<(RemoteRepository, String)> {
+        let mut remote = RemoteRepository::new(source, meta)?;
+
+        remote.fetch_refs(RefPrecedence::default(), credentials.as_ref())?;
+        remote.resolve_meta()?;
+
+        let hash = remote.resolve_hash()?;
+
+        Ok((remote, hash))
+      })
+    });
+
+    let mut remotes = Vec::new();
+
+    for resolution in future::join_all(resolutions).await {
+      let resolved = resolution.map_err(|err| miette::miette!("Failed to resolve refs: {err}."))?;
+      remotes.push(resolved?);
     }
+
+    let credentials = self.credentials.as_ref();
+
+    prefetch(&mut cache, remotes, |remote| {
+      async move { remote.fetch(credentials).await }
+    })
+    .await
+  }
+
+  fn handle_cache(&mut self, command: CacheCommand) -> miette::Result<()> {
+    let mut cache = Cache::init()?;
+
+    handle_cache(&mut cache, command)
   }
 
   /// Clean up on failure.
@@ -329,3 +804,1285 @@ impl App {
     Ok(())
   }
 }
+
+/// Downloads tarballs of the given resolved remotes concurrently using the `fetch` function and
+/// writes them to the cache. Remotes that are already cached are skipped.
+async fn prefetch<F, Fut>(
+  cache: &mut Cache,
+  remotes: Vec<(RemoteRepository, String)>,
+  fetch: F,
+) -> miette::Result<()>
+where
+  F: Fn(RemoteRepository) -> Fut,
+  Fut: Future<Output = Result<Vec<u8>, FetchError>>,
+{
+  let mut downloads = Vec::new();
+
+  for (remote, hash) in remotes {
+    let source = remote.get_source();
+    let name = remote.meta.to_string();
+    let key = remote.cache_key(&hash);
+
+    if cache.contains(&source, &key) {
+      info!("{} {source} @ {name}", "~ Already cached:".dim());
+      continue;
+    }
+
+    let download = fetch(remote);
+
+    downloads.push(async move { (source, name, key, download.await) });
+  }
+
+  for (source, name, key, tarball) in future::join_all(downloads).await {
+    cache.write(&source, &name, &key, &tarball?)?;
+
+    info!("{} {source} @ {name}", "~ Cached:".dim());
+  }
+
+  Ok(())
+}
+
+/// Parses a host from one of its aliases.
+fn parse_host(input: &str) -> Result<RepositoryHost, String> {
+  RepositoryHost::from_alias(input)
+    .ok_or_else(|| format!("must be one of: {}", RepositoryHost::aliases_hint()))
+}
+
+/// Parses a subdirectory path, making sure it stays within the repository.
+fn parse_subdir(input: &str) -> Result<PathBuf, String> {
+  let path = Path::new(input)
+    .components()
+    .filter(|component| *component != Component::CurDir)
+    .collect::<PathBuf>();
+
+  let is_nested = path
+    .components()
+    .all(|component| matches!(component, Component::Normal(..)));
+
+  if is_nested && !path.as_os_str().is_empty() {
+    Ok(path)
+  } else {
+    Err("subdirectory must be a relative path inside the repository".to_string())
+  }
+}
+
+/// Runs the cache command against the given cache.
+fn handle_cache(cache: &mut Cache, command: CacheCommand) -> miette::Result<()> {
+  match command {
+    | CacheCommand::List { source, json } => {
+      let format = if json {
+        ListFormat::Json
+      } else {
+        ListFormat::Human
+      };
+
+      Ok(cache.list(source.as_deref(), format)?)
+    },
+    | CacheCommand::Remove { entries, all, dry_run } => {
+      if all {
+        cache.remove_all(dry_run)
+      } else {
+        cache.remove(entries, dry_run)
+      }
+    },
+    | CacheCommand::Clear { dry_run } => cache.remove_all(dry_run),
+    | CacheCommand::Gc => {
+      let (removed, freed) = cache.gc()?;
+      let message = format!(
+        "~ Removed {removed} orphaned tarball(s) ({})",
+        HumanBytes(freed)
+      );
+
+      info!("{}", message.dim());
+
+      Ok(())
+    },
+  }
+}
+
+/// Options controlling how a config is applied in place.
+#[derive(Debug, Default)]
+struct ApplyOptions {
+  /// Skip all `run` actions.
+  no_run: bool,
+  /// Delete the config after applying.
+  delete: bool,
+  /// Answers to prompts, used instead of asking.
+  answers: Option<Answers>,
+  /// Use default values of prompts instead of asking.
+  defaults: bool,
+  /// Fail on prompts lacking an answer or a default value instead of asking.
+  non_interactive: bool,
+}
+
+/// Runs prompts and actions of the config in the given directory in place. Unlike scaffolding, a
+/// missing config is an error. Returns the number of executed actions.
+async fn apply(
+  directory: &Path,
+  config_name: &str,
+  options: ApplyOptions,
+) -> miette::Result<usize> {
+  if !directory.is_dir() {
+    miette::bail!(
+      "Failed to apply: '{}' is not a directory.",
+      directory.display()
+    );
+  }
+
+  let mut config = Config::new(directory).name(config_name);
+
+  if !config.load()? {
+    miette::bail!(
+      "Failed to apply: no '{config_name}' found in '{}'.",
+      directory.display()
+    );
+  }
+
+  config.override_with(ConfigOptionsOverrides { delete: Some(options.delete) });
+
+  let executor = Executor::new(config)
+    .skip_runs(options.no_run)
+    .answers(options.answers)
+    .defaults(options.defaults)
+    .non_interactive(options.non_interactive);
+  let executed = executor.execute().await?;
+
+  executor.diagnostics().report();
+
+  Ok(executed)
+}
+
+/// Checks whether scaffolding into the existing destination is allowed: only into a directory, only
+/// if it's empty or is not an ancestor of the current directory or the home directory, and only if
+/// forced with `--force`/`--yes` or confirmed interactively.
+fn check_existing(destination: &Path, args: &RepositoryArgs) -> miette::Result<()> {
+  let cwd = env::current_dir().ok();
+  let home = home::home_dir();
+  let protected = [cwd.as_deref().and_then(Path::parent), home.as_deref()];
+
+  let interactive = !args.non_interactive && args.answers.is_none() && io::stdin().is_terminal();
+
+  let confirm = |destination: &Path| {
+    if !interactive {
+      return false;
+    }
+
+    let hint = format!(
+      "'{}' already exists. Scaffold into it, overwriting existing files?",
+      destination.display()
+    );
+
+    let prompt = Confirm::new(&hint)
+      .with_default(false)
+      .with_render_config(helpers::theme());
+
+    match prompt.prompt() {
+      | Ok(confirmed) => confirmed,
+      | Err(err) => {
+        helpers::interrupt(err);
+        false
+      },
+    }
+  };
+
+  check_existing_with(
+    destination,
+    args.force || args.yes,
+    protected.into_iter().flatten(),
+    confirm,
+  )
+}
+
+/// Same as [check_existing], but with explicit protected directories and confirmation. Protected
+/// directories and their ancestors are refused unless empty.
+fn check_existing_with<'p, I, C>(
+  destination: &Path,
+  force: bool,
+  protected: I,
+  confirm: C,
+) -> miette::Result<()>
+where
+  I: IntoIterator<Item = &'p Path>,
+  C: FnOnce(&Path) -> bool,
+{
+  let already_exists = || {
+    miette::miette!(
+      help = "Use `--force` to scaffold into it anyway, overwriting existing files.",
+      "Failed to scaffold: '{}' already exists.",
+      destination.display()
+    )
+  };
+
+  if !destination.is_dir() {
+    if !force {
+      return Err(already_exists());
+    }
+
+    miette::bail!(
+      "Failed to scaffold: '{}' is not a directory.",
+      destination.display()
+    );
+  }
+
+  let is_empty = fs::read_dir(destination).is_ok_and(|mut entries| entries.next().is_none());
+
+  if !is_empty {
+    check_protected(destination, protected)?;
+  }
+
+  if !force && !confirm(destination) {
+    return Err(already_exists());
+  }
+
+  Ok(())
+}
+
+/// Refuses the destination if it's one of the protected directories or their ancestor.
+fn check_protected<'p, I>(destination: &Path, protected: I) -> miette::Result<()>
+where
+  I: IntoIterator<Item = &'p Path>,
+{
+  let canonical = destination.canonicalize().map_err(|source| {
+    AppError::Io {
+      message: format!("Failed to resolve '{}'.", destination.display()),
+      source,
+    }
+  })?;
+
+  let is_protected = protected.into_iter().any(|path| {
+    path
+      .canonicalize()
+      .is_ok_and(|path| path.starts_with(&canonical))
+  });
+
+  if is_protected {
+    return Err(miette::miette!(
+      help = "Scaffold into a subdirectory instead.",
+      "Refusing to scaffold into '{}', it's an ancestor of the current directory, or the home \
+       directory.",
+      destination.display()
+    ));
+  }
+
+  Ok(())
+}
+
+/// Returns a fresh staging directory path next to the destination.
+fn staging_for(destination: &Path) -> miette::Result<PathBuf> {
+  let mut staging = destination.as_os_str().to_owned();
+  staging.push(".decaff-staging");
+
+  let staging = PathBuf::from(staging);
+
+  if staging.exists() {
+    miette::bail!(
+      help = "It's probably left over from a failed run, remove it and try again.",
+      "Failed to scaffold: staging directory '{}' already exists.",
+      staging.display()
+    );
+  }
+
+  Ok(staging)
+}
+
+/// Moves contents of the `source` directory into the `destination` directory, overwriting existing
+/// files, and removes the `source` directory afterwards.
+fn merge_into(source: &Path, destination: &Path) -> miette::Result<()> {
+  let io = |message: String| move |source| AppError::Io { message, source };
+
+  for entry in WalkDir::new(source).min_depth(1) {
+    let entry = entry.into_diagnostic()?;
+    let relative = entry.path().strip_prefix(source).into_diagnostic()?;
+    let target = destination.join(relative);
+
+    if entry.file_type().is_dir() {
+      fs::create_dir_all(&target).map_err(io(format!(
+        "Failed to create directory '{}'.",
+        target.display()
+      )))?;
+    } else {
+      fs::rename(entry.path(), &target).map_err(io(format!(
+        "Failed to move '{}' to '{}'.",
+        entry.path().display(),
+        target.display()
+      )))?;
+    }
+  }
+
+  fs::remove_dir_all(source).map_err(io(format!("Failed to remove '{}'.", source.display())))?;
+
+  Ok(())
+}
+
+/// Writes the starter config into the given directory, creating it if needed. Refuses to overwrite
+/// an existing config unless forced. Returns the path to the written config.
+fn init(directory: &Path, config_name: &str, force: bool) -> miette::Result<PathBuf> {
+  let config = directory.join(config_name);
+
+  if config.exists() && !force {
+    return Err(miette::miette!(
+      help = "Use `--force` to overwrite it.",
+      "Failed to init: '{}' already exists.",
+      config.display()
+    ));
+  }
+
+  fs::create_dir_all(directory).map_err(|source| {
+    AppError::Io {
+      message: format!("Failed to create directory '{}'.", directory.display()),
+      source,
+    }
+  })?;
+
+  fs::write(&config, STARTER_CONFIG).map_err(|source| {
+    AppError::Io {
+      message: format!("Failed to write config '{}'.", config.display()),
+      source,
+    }
+  })?;
+
+  Ok(config)
+}
+
+/// Loads and parses the config in the given directory without running anything, failing if it
+/// doesn't exist, doesn't parse, or contains unknown actions.
+fn validate(directory: &Path, config_name: &str) -> miette::Result<Config> {
+  let mut config = Config::new(directory).name(config_name);
+
+  if !config.load()? {
+    miette::bail!(
+      "Failed to validate: no '{config_name}' found in '{}'.",
+      directory.display()
+    );
+  }
+
+  let unknown = config
+    .actions
+    .singles()
+    .into_iter()
+    .filter_map(|action| {
+      match action {
+        | ActionSingle::Unknown(unknown) => Some(format!("`{}`", unknown.name)),
+        | _ => None,
+      }
+    })
+    .collect::<Vec<_>>();
+
+  if !unknown.is_empty() {
+    miette::bail!(
+      code = "decaff::config::actions",
+      "Failed to validate '{}': unknown actions {}.",
+      config.config.display(),
+      unknown.join(", ")
+    );
+  }
+
+  Ok(config)
+}
+
+/// Returns the filesystem to scaffold on: in memory for dry runs, on disk otherwise.
+fn filesystem(dry_run: bool) -> Arc<dyn Filesystem> {
+  if dry_run {
+    Arc::new(MemoryFs::new())
+  } else {
+    Arc::new(RealFs)
+  }
+}
+
+/// Counts files under the given root, e.g. to report how many were copied.
+fn count_files(fs: &dyn Filesystem, root: &Path) -> usize {
+  fs.walk(root, false)
+    .flatten()
+    .filter(|(_, kind)| *kind != EntryKind::Dir)
+    .count()
+}
+
+/// Final one-line summary of scaffolding. This is the only output with `--summary-only`, and is
+/// suppressed by `--quiet`.
+struct Summary<'s> {
+  /// Source of the template, e.g. `github:foo/bar` or a local path.
+  source: String,
+  /// Requested ref.
+  meta: String,
+  /// Commit hash the ref resolved to. Missing for plain directories.
+  hash: Option<String>,
+  /// Scaffolded directory.
+  destination: &'s Path,
+  /// Number of extracted or copied files.
+  files: usize,
+  /// Number of executed actions.
+  executed: usize,
+  /// Whether this was a dry run.
+  dry_run: bool,
+}
+
+impl Display for Summary<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let Summary { source, meta, files, executed, .. } = self;
+
+    let verb = if self.dry_run {
+      "Previewed"
+    } else {
+      "Scaffolded"
+    };
+    let files_noun = if *files == 1 { "file" } else { "files" };
+    let actions_noun = if *executed == 1 { "action" } else { "actions" };
+
+    write!(f, "{verb} {source} @ {meta}")?;
+
+    if let Some(hash) = &self.hash {
+      write!(f, " ({})", &hash[..hash.len().min(7)])?;
+    }
+
+    write!(
+      f,
+      " to {} ({files} {files_noun}, {executed} {actions_noun})",
+      self.destination.display()
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use tempfile::TempDir;
+
+  use super::*;
+  use crate::config::{Number, Value};
+  use crate::logger::testing::Capture;
+
+  // Helpers.
+
+  /// Creates a git repository at the given path with a single committed README. Returns the hash
+  /// of the commit.
+  fn git_template(root: &Path) -> String {
+    let repository = git2::Repository::init(root).unwrap();
+    let signature = git2::Signature::now("decaff", "decaff@example.com").unwrap();
+
+    fs::write(root.join("README.md"), "template").unwrap();
+
+    let mut index = repository.index().unwrap();
+    index.add_path(Path::new("README.md")).unwrap();
+    index.write().unwrap();
+
+    let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+
+    repository
+      .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+      .unwrap()
+      .to_string()
+  }
+
+  // Tests.
+
+  #[tokio::test]
+  async fn prefetch_populates_cache() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("cache");
+    let mut cache = Cache::load(root.clone()).unwrap();
+
+    let remotes = vec![
+      (
+        RemoteRepository::new("foo/bar".to_string(), None).unwrap(),
+        "aaaaaaa".to_string(),
+      ),
+      (
+        RemoteRepository::new("gitlab:foo/baz".to_string(), None).unwrap(),
+        "bbbbbbb".to_string(),
+      ),
+    ];
+
+    prefetch(&mut cache, remotes, |remote| {
+      async move { Ok(remote.repo.into_bytes()) }
+    })
+    .await
+    .unwrap();
+
+    let mut cache = Cache::load(root).unwrap();
+
+    assert_eq!(
+      cache.read("github:foo/bar", "aaaaaaa").unwrap(),
+      Some(b"bar".to_vec())
+    );
+
+    assert_eq!(
+      cache.read("gitlab:foo/baz", "bbbbbbb").unwrap(),
+      Some(b"baz".to_vec())
+    );
+
+    // Nothing is scaffolded, only the cache is written.
+    assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+  }
+
+  #[tokio::test]
+  async fn prefetch_caches_releases_separately() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("cache");
+    let mut cache = Cache::load(root.clone()).unwrap();
+
+    let hash = "a".repeat(40);
+
+    let remotes = ["v1", "release:v1"]
+      .into_iter()
+      .map(|meta| {
+        let remote = RemoteRepository::new("foo/bar".to_string(), Some(meta.to_string())).unwrap();
+        (remote, hash.clone())
+      })
+      .collect();
+
+    prefetch(&mut cache, remotes, |remote| {
+      async move { Ok(remote.meta.to_string().into_bytes()) }
+    })
+    .await
+    .unwrap();
+
+    let mut cache = Cache::load(root).unwrap();
+
+    let source = RemoteRepository::new("foo/bar".to_string(), Some("v1".to_string())).unwrap();
+    let release =
+      RemoteRepository::new("foo/bar".to_string(), Some("release:v1".to_string())).unwrap();
+
+    assert_ne!(source.cache_key(&hash), release.cache_key(&hash));
+
+    assert_eq!(
+      cache
+        .read("github:foo/bar", &source.cache_key(&hash))
+        .unwrap(),
+      Some(b"v1".to_vec())
+    );
+
+    assert_eq!(
+      cache
+        .read("github:foo/bar", &release.cache_key(&hash))
+        .unwrap(),
+      Some(b"release:v1".to_vec())
+    );
+
+    // Short hashes only match the source archive.
+    assert_eq!(
+      cache.read("github:foo/bar", &hash[..7]).unwrap(),
+      Some(b"v1".to_vec())
+    );
+  }
+
+  #[tokio::test]
+  async fn apply_in_place() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::write(root.join("old.txt"), "old").unwrap();
+    fs::write(root.join("keep.txt"), "keep").unwrap();
+
+    fs::write(
+      root.join("decaff.kdl"),
+      r#"
+      actions {
+        rm "old.txt"
+        write to="new.txt" {
+          content "new"
+        }
+      }
+      "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+      apply(root, "decaff.kdl", ApplyOptions::default())
+        .await
+        .unwrap(),
+      2
+    );
+
+    assert!(!root.join("old.txt").exists());
+    assert_eq!(fs::read_to_string(root.join("new.txt")).unwrap(), "new\n");
+    assert_eq!(fs::read_to_string(root.join("keep.txt")).unwrap(), "keep");
+
+    // The config is kept, so the template can be applied again.
+    assert!(root.join("decaff.kdl").exists());
+  }
+
+  #[tokio::test]
+  async fn apply_without_config() {
+    let temp = TempDir::new().unwrap();
+
+    assert!(apply(temp.path(), "decaff.kdl", ApplyOptions::default())
+      .await
+      .is_err());
+  }
+
+  #[tokio::test]
+  async fn apply_with_answers() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::write(
+      root.join("decaff.kdl"),
+      r#"
+      actions {
+        input "name" transform="slug" {
+          hint "Name"
+        }
+        confirm "license" {
+          hint "Add license?"
+        }
+        input "license_holder" when="license" {
+          hint "License holder"
+        }
+        write to="name.txt" {
+          content "{name}"
+          inject "name"
+        }
+      }
+      "#,
+    )
+    .unwrap();
+
+    let answers = |values: Vec<(&str, Value)>| Some(values.into_iter().collect::<Answers>());
+
+    // Supplied answers are used as is, conditional prompts that are skipped need no answers.
+    let executed = apply(
+      root,
+      "decaff.kdl",
+      ApplyOptions {
+        answers: answers(vec![
+          ("name", Value::String("My Project".to_string())),
+          ("license", Value::Bool(false)),
+        ]),
+        ..ApplyOptions::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(executed, 4);
+    assert_eq!(
+      fs::read_to_string(root.join("name.txt")).unwrap(),
+      "my-project\n"
+    );
+
+    // Missing answers are listed before running anything.
+    fs::remove_file(root.join("name.txt")).unwrap();
+
+    let err = apply(
+      root,
+      "decaff.kdl",
+      ApplyOptions {
+        answers: answers(Vec::new()),
+        ..ApplyOptions::default()
+      },
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(
+      err.to_string(),
+      "Missing answers for prompts: name, license."
+    );
+    assert!(!root.join("name.txt").exists());
+
+    // Conditional prompts that end up being asked need answers as well.
+    let err = apply(
+      root,
+      "decaff.kdl",
+      ApplyOptions {
+        answers: answers(vec![
+          ("name", Value::String("app".to_string())),
+          ("license", Value::Bool(true)),
+        ]),
+        ..ApplyOptions::default()
+      },
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(
+      err.to_string(),
+      "Missing answers for prompts: license_holder."
+    );
+
+    // Answers are validated.
+    let err = apply(
+      root,
+      "decaff.kdl",
+      ApplyOptions {
+        answers: answers(vec![
+          ("name", Value::String("app".to_string())),
+          ("license", Value::String("yes".to_string())),
+        ]),
+        ..ApplyOptions::default()
+      },
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(
+      err.to_string(),
+      "Invalid answer for prompt `license`: Expected a boolean."
+    );
+  }
+
+  #[tokio::test]
+  async fn apply_with_defaults() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    let config = |prompts: &str| {
+      fs::write(
+        root.join("decaff.kdl"),
+        format!(
+          r#"
+          actions {{
+            {prompts}
+            write to="answers.txt" {{
+              content "{{name}} {{port}} {{commit}}"
+              inject "name" "port" "commit"
+            }}
+          }}
+          "#
+        ),
+      )
+      .unwrap();
+    };
+
+    config(
+      r#"
+      input "name" transform="upper" {
+        hint "Name"
+        default "app"
+      }
+      number "port" {
+        hint "Port"
+        default 3000
+      }
+      confirm "commit" {
+        hint "Commit?"
+        default false
+      }
+      "#,
+    );
+
+    // Defaults populate the state without asking.
+    assert_eq!(
+      apply(
+        root,
+        "decaff.kdl",
+        ApplyOptions {
+          defaults: true,
+          ..ApplyOptions::default()
+        },
+      )
+      .await
+      .unwrap(),
+      4
+    );
+
+    assert_eq!(
+      fs::read_to_string(root.join("answers.txt")).unwrap(),
+      "APP 3000 false\n"
+    );
+
+    // Answers take precedence over defaults.
+    let answers = [("port", Value::Number(Number::Integer(8080)))]
+      .into_iter()
+      .collect::<Answers>();
+
+    apply(
+      root,
+      "decaff.kdl",
+      ApplyOptions {
+        answers: Some(answers),
+        defaults: true,
+        ..ApplyOptions::default()
+      },
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(root.join("answers.txt")).unwrap(),
+      "APP 8080 false\n"
+    );
+
+    // Prompts without defaults are reported.
+    config(
+      r#"
+      input "name" {
+        hint "Name"
+      }
+      number "port" {
+        hint "Port"
+        default 3000
+      }
+      confirm "commit" {
+        hint "Commit?"
+      }
+      select "pm" {
+        hint "Package manager"
+        options "npm" "pnpm"
+      }
+      "#,
+    );
+
+    let err = apply(
+      root,
+      "decaff.kdl",
+      ApplyOptions {
+        defaults: true,
+        ..ApplyOptions::default()
+      },
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(
+      err.to_string(),
+      "No default values for prompts: name, commit, pm."
+    );
+  }
+
+  #[tokio::test]
+  async fn apply_non_interactive() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::write(
+      root.join("decaff.kdl"),
+      r#"
+      actions {
+        write to="started.txt" {
+          content "started"
+        }
+        input "name" {
+          hint "Name"
+        }
+      }
+      "#,
+    )
+    .unwrap();
+
+    // Unanswerable prompts abort the run before anything is executed.
+    let err = apply(
+      root,
+      "decaff.kdl",
+      ApplyOptions {
+        non_interactive: true,
+        ..ApplyOptions::default()
+      },
+    )
+    .await
+    .unwrap_err();
+
+    assert_eq!(err.to_string(), "Missing answers for prompts: name.");
+    assert!(!root.join("started.txt").exists());
+  }
+
+  #[test]
+  fn cli_is_consistent() {
+    use clap::CommandFactory;
+
+    Cli::command().debug_assert();
+  }
+
+  #[test]
+  fn cache_commands() {
+    let temp = TempDir::new().unwrap();
+    let mut cache = Cache::with_root(temp.path().to_path_buf()).unwrap();
+
+    cache
+      .write("github:foo/bar", "main", "abc123", b"tarball")
+      .unwrap();
+
+    cache
+      .write("github:foo/baz", "main", "def456", b"tarball")
+      .unwrap();
+
+    let run = |cache: &mut Cache, args: &[&str]| {
+      let args = ["decaff", "cache"].iter().chain(args);
+
+      let Command::Cache { command } = Cli::try_parse_from(args).unwrap().command else {
+        panic!("expected a cache command");
+      };
+
+      handle_cache(cache, command)
+    };
+
+    run(&mut cache, &["list", "--json"]).unwrap();
+
+    run(&mut cache, &["remove", "github:foo/bar"]).unwrap();
+
+    assert!(!cache.contains("github:foo/bar", "abc123"));
+    assert!(cache.contains("github:foo/baz", "def456"));
+
+    run(&mut cache, &["clear", "--dry-run"]).unwrap();
+
+    assert!(cache.contains("github:foo/baz", "def456"));
+
+    run(&mut cache, &["clear"]).unwrap();
+
+    assert!(!cache.contains("github:foo/baz", "def456"));
+  }
+
+  #[test]
+  fn refuse_existing_destinations() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().to_path_buf();
+    let project = root.join("project");
+    let empty = root.join("empty");
+
+    fs::create_dir_all(project.join("src")).unwrap();
+    fs::create_dir(&empty).unwrap();
+    fs::write(root.join("file.txt"), "").unwrap();
+
+    let confirm = |answer: bool| move |_: &Path| answer;
+    let unreachable = |_: &Path| -> bool { panic!("Should not ask for confirmation") };
+
+    // Refused by default, unless confirmed.
+    assert!(check_existing_with(&project, false, [], confirm(false))
+      .is_err_and(|err| err.to_string().contains("already exists")));
+
+    assert!(check_existing_with(&project, false, [], confirm(true)).is_ok());
+
+    // Allowed without asking if forced.
+    assert!(check_existing_with(&project, true, [], unreachable).is_ok());
+
+    // Still refused if not a directory.
+    assert!(
+      check_existing_with(&root.join("file.txt"), true, [], unreachable)
+        .is_err_and(|err| err.to_string().contains("not a directory"))
+    );
+
+    // Still refused if it's a protected directory or its ancestor, unless it's empty, without
+    // asking for confirmation.
+    let protected = project.join("src");
+
+    assert!(
+      check_existing_with(&project, true, [protected.as_path()], unreachable)
+        .is_err_and(|err| err.to_string().starts_with("Refusing to scaffold"))
+    );
+
+    assert!(check_existing_with(&project, false, [protected.as_path()], unreachable).is_err());
+    assert!(check_existing_with(&root, true, [protected.as_path()], unreachable).is_err());
+    assert!(check_existing_with(&empty, true, [empty.as_path()], unreachable).is_ok());
+  }
+
+  #[tokio::test]
+  async fn force_scaffold_into_existing_directory() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("template");
+    let destination = temp.path().join("project");
+
+    fs::create_dir_all(source.join("src")).unwrap();
+    fs::write(source.join("README.md"), "template").unwrap();
+    fs::write(source.join("src/main.rs"), "fn main() {}").unwrap();
+
+    fs::create_dir_all(destination.join("src")).unwrap();
+    fs::write(destination.join("README.md"), "existing").unwrap();
+    fs::write(destination.join("src/lib.rs"), "existing").unwrap();
+
+    let app = |force: bool| {
+      let mut args = vec![
+        "decaff",
+        "local",
+        source.to_str().unwrap(),
+        destination.to_str().unwrap(),
+        "--skip",
+      ];
+
+      // Never ask for confirmation, even if tests run in a terminal.
+      if force {
+        args.push("--force");
+      } else {
+        args.push("--non-interactive");
+      }
+
+      App {
+        cli: Cli::try_parse_from(args).unwrap(),
+        state: AppState::default(),
+        credentials: Arc::new(DefaultCredentials),
+      }
+    };
+
+    // Refused by default, leaving the destination intact.
+    assert!(app(false).scaffold().await.is_err());
+    assert_eq!(
+      fs::read_to_string(destination.join("README.md")).unwrap(),
+      "existing"
+    );
+
+    // Merged and overwritten if forced.
+    app(true).scaffold().await.unwrap();
+
+    assert_eq!(
+      fs::read_to_string(destination.join("README.md")).unwrap(),
+      "template"
+    );
+
+    assert!(destination.join("src/main.rs").exists());
+    assert!(destination.join("src/lib.rs").exists());
+    assert!(!temp.path().join("project.decaff-staging").exists());
+  }
+
+  #[tokio::test]
+  async fn dry_run_leaves_destination_untouched() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("template");
+    let destination = temp.path().join("project");
+
+    fs::create_dir_all(&source).unwrap();
+    fs::write(source.join("README.md"), "template").unwrap();
+    fs::write(
+      source.join("decaff.kdl"),
+      format!(
+        r#"
+        actions {{
+          write to="hello.txt" {{
+            content "Hello"
+          }}
+          git-init
+          run "touch {}"
+        }}
+        "#,
+        temp.path().join("ran").display()
+      ),
+    )
+    .unwrap();
+
+    let mut app = App {
+      cli: Cli::try_parse_from([
+        "decaff",
+        "local",
+        source.to_str().unwrap(),
+        destination.to_str().unwrap(),
+        "--dry-run",
+      ])
+      .unwrap(),
+      state: AppState::default(),
+      credentials: Arc::new(DefaultCredentials),
+    };
+
+    let capture = Capture::start(Verbosity::Normal);
+
+    app.scaffold().await.unwrap();
+
+    let output = capture.finish();
+
+    assert!(!destination.exists());
+    assert!(!temp.path().join("ran").exists());
+    assert!(source.join("decaff.kdl").exists());
+
+    assert!(output
+      .iter()
+      .any(|line| line.contains("Skipped by --dry-run: git-init")));
+    assert!(output
+      .iter()
+      .any(|line| line.contains("Skipped by --dry-run: touch")));
+    assert!(output
+      .iter()
+      .any(|line| line.starts_with(&format!("Previewed {}", source.display()))));
+  }
+
+  #[tokio::test]
+  async fn summary_after_scaffolding() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("template");
+    let destination = temp.path().join("project");
+
+    let hash = git_template(&source);
+
+    let mut app = App {
+      cli: Cli::try_parse_from([
+        "decaff",
+        "local",
+        source.to_str().unwrap(),
+        destination.to_str().unwrap(),
+        "--summary-only",
+      ])
+      .unwrap(),
+      state: AppState::default(),
+      credentials: Arc::new(DefaultCredentials),
+    };
+
+    let capture = Capture::start(Verbosity::Normal);
+
+    app.scaffold().await.unwrap();
+
+    assert_eq!(
+      capture.finish(),
+      vec![format!(
+        "Scaffolded {} @ HEAD ({}) to {} (1 file, 0 actions)",
+        source.display(),
+        &hash[..7],
+        destination.display()
+      )]
+    );
+  }
+
+  #[tokio::test]
+  async fn quiet_suppresses_output() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("template");
+
+    git_template(&source);
+
+    let scaffold = |name: &str, quiet: bool| {
+      let destination = temp.path().join(name);
+      let mut args = vec![
+        "decaff".to_string(),
+        "local".to_string(),
+        source.display().to_string(),
+        destination.display().to_string(),
+      ];
+
+      if quiet {
+        args.push("--quiet".to_string());
+      }
+
+      let mut app = App {
+        cli: Cli::try_parse_from(args).unwrap(),
+        state: AppState::default(),
+        credentials: Arc::new(DefaultCredentials),
+      };
+
+      async move {
+        let capture = Capture::start(Verbosity::Normal);
+
+        app.scaffold().await.unwrap();
+
+        assert!(destination.join("README.md").exists());
+        assert!(!destination.join(".git").exists());
+
+        capture.finish()
+      }
+    };
+
+    let output = scaffold("loud", false).await;
+
+    assert!(output
+      .iter()
+      .any(|line| line.contains("Removed inner .git directory")));
+
+    let output = scaffold("quiet", true).await;
+
+    assert!(output.is_empty(), "{output:?}");
+  }
+
+  #[test]
+  fn init_starter_config() {
+    let temp = TempDir::new().unwrap();
+    let directory = temp.path().join("template");
+
+    let config = init(&directory, "decaff.kdl", false).unwrap();
+
+    assert_eq!(config, directory.join("decaff.kdl"));
+
+    // The starter config is valid.
+    let parsed = validate(&directory, "decaff.kdl").unwrap();
+
+    assert!(!parsed.actions.singles().is_empty());
+
+    // Existing configs are kept, unless forced.
+    fs::write(&config, "actions {}").unwrap();
+
+    assert!(init(&directory, "decaff.kdl", false)
+      .is_err_and(|err| err.to_string().contains("already exists")));
+    assert_eq!(fs::read_to_string(&config).unwrap(), "actions {}");
+
+    init(&directory, "decaff.kdl", true).unwrap();
+
+    assert_eq!(fs::read_to_string(&config).unwrap(), STARTER_CONFIG);
+  }
+
+  #[test]
+  fn validate_configs() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::write(
+      root.join("decaff.kdl"),
+      r#"
+      actions {
+        suite "hello" {
+          echo "Hello"
+          run "npm install"
+        }
+      }
+      "#,
+    )
+    .unwrap();
+
+    fs::write(
+      root.join("unknown.kdl"),
+      r#"
+      actions {
+        echo "Hello"
+        frobnicate "foo"
+      }
+      "#,
+    )
+    .unwrap();
+
+    fs::write(
+      root.join("invalid.kdl"),
+      r#"
+      actions {
+        cp to="dest"
+      }
+      "#,
+    )
+    .unwrap();
+
+    let config = validate(root, "decaff.kdl").unwrap();
+    assert_eq!(config.actions.singles().len(), 2);
+
+    let err = validate(root, "unknown.kdl").unwrap_err();
+    assert!(err.to_string().contains("unknown actions `frobnicate`"));
+
+    let err = validate(root, "invalid.kdl").unwrap_err();
+    assert_eq!(err.to_string(), "Missing required attribute: `from`.");
+
+    assert!(validate(root, "missing.kdl").is_err());
+  }
+
+  #[tokio::test]
+  async fn summary_only_prints_summary() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    fs::write(
+      root.join("decaff.kdl"),
+      r#"
+      actions {
+        echo "Hello"
+        write to="hello.txt" {
+          content "Hello"
+        }
+      }
+      "#,
+    )
+    .unwrap();
+
+    let capture = Capture::start(Verbosity::Summary);
+
+    let mut config = Config::new(root);
+    config.load().unwrap();
+
+    let executed = Executor::new(config).execute().await.unwrap();
+    summary!(
+      "{}",
+      Summary {
+        source: "github:foo/bar".to_string(),
+        meta: "main".to_string(),
+        hash: Some("0123456789abcdef".to_string()),
+        destination: Path::new("foo"),
+        files: 1,
+        executed,
+        dry_run: false,
+      }
+    );
+
+    assert_eq!(
+      capture.finish(),
+      vec!["Scaffolded github:foo/bar @ main (0123456) to foo (1 file, 2 actions)"]
+    );
+    assert!(root.join("hello.txt").exists());
+  }
+}
@@ -0,0 +1,253 @@
+use std::cell::Cell;
+use std::env;
+use std::fmt::{self, Debug, Display};
+use std::fs;
+use std::path::PathBuf;
+
+/// Environment variable pointing to a netrc file to use instead of `~/.netrc`.
+const NETRC_VAR: &str = "NETRC";
+
+/// Environment variables to read tokens from, per host. Usernames are the ones hosts expect when
+/// a token is used as a password for git over HTTPS.
+const TOKEN_VARS: [(&[&str], &str, &[&str]); 3] = [
+  (
+    &["github.com", "api.github.com", "codeload.github.com"],
+    "x-access-token",
+    &["GITHUB_TOKEN", "GH_TOKEN"],
+  ),
+  (&["gitlab.com"], "oauth2", &["GITLAB_TOKEN"]),
+  (
+    &["bitbucket.org", "api.bitbucket.org"],
+    "x-token-auth",
+    &["BITBUCKET_TOKEN"],
+  ),
+];
+
+/// Secret value, e.g. a token or a password. Redacted when printed, so it never ends up in logs or
+/// error messages.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+  pub fn new<S>(secret: S) -> Self
+  where
+    S: Into<String>,
+  {
+    Self(secret.into())
+  }
+
+  /// Returns the actual secret value.
+  pub fn expose(&self) -> &str {
+    &self.0
+  }
+}
+
+impl Debug for Secret {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Secret(\"[redacted]\")")
+  }
+}
+
+impl Display for Secret {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "[redacted]")
+  }
+}
+
+/// Credentials to authenticate to a host with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Credentials {
+  /// Username, used for git over HTTPS. HTTP requests only send the secret as a bearer token.
+  pub username: String,
+  /// Token or password.
+  pub secret: Secret,
+}
+
+impl Credentials {
+  pub fn new<U, S>(username: U, secret: S) -> Self
+  where
+    U: Into<String>,
+    S: Into<String>,
+  {
+    Self {
+      username: username.into(),
+      secret: Secret::new(secret),
+    }
+  }
+}
+
+/// Provides credentials for hosts. Consulted when fetching refs, tarballs, release assets and
+/// submodules.
+pub trait CredentialProvider: Debug + Send + Sync {
+  /// Returns credentials for the given host name, e.g. `github.com`, or `None` to make the
+  /// request anonymously.
+  fn credentials(&self, host: &str) -> Option<Credentials>;
+}
+
+/// Default credential provider. Reads tokens from host-specific environment variables (e.g.
+/// `GITHUB_TOKEN`), falling back to the netrc file.
+#[derive(Debug, Default)]
+pub struct DefaultCredentials;
+
+impl CredentialProvider for DefaultCredentials {
+  fn credentials(&self, host: &str) -> Option<Credentials> {
+    from_env(host, |name| env::var(name).ok()).or_else(|| {
+      let contents = fs::read_to_string(netrc_path()?).ok()?;
+
+      from_netrc(&contents, host)
+    })
+  }
+}
+
+/// Provider that never returns any credentials.
+#[derive(Debug, Default)]
+pub struct Anonymous;
+
+impl CredentialProvider for Anonymous {
+  fn credentials(&self, _host: &str) -> Option<Credentials> {
+    None
+  }
+}
+
+/// Looks up a token for the given host using the given lookup function.
+fn from_env<F>(host: &str, lookup: F) -> Option<Credentials>
+where
+  F: Fn(&str) -> Option<String>,
+{
+  let (_, username, vars) = TOKEN_VARS
+    .iter()
+    .find(|(hosts, ..)| hosts.contains(&host))?;
+
+  vars
+    .iter()
+    .filter_map(|name| lookup(name))
+    .find(|token| !token.trim().is_empty())
+    .map(|token| Credentials::new(*username, token.trim()))
+}
+
+/// Returns the path to the netrc file.
+fn netrc_path() -> Option<PathBuf> {
+  env::var_os(NETRC_VAR)
+    .map(PathBuf::from)
+    .or_else(|| home::home_dir().map(|home| home.join(".netrc")))
+}
+
+/// Finds credentials for the given host in netrc contents. The `default` entry is used if there's
+/// no entry for the host.
+fn from_netrc(contents: &str, host: &str) -> Option<Credentials> {
+  let mut entries: Vec<(Option<&str>, Option<&str>, Option<&str>)> = Vec::new();
+  let mut tokens = contents.split_whitespace();
+
+  while let Some(token) = tokens.next() {
+    match token {
+      | "machine" => entries.push((Some(tokens.next()?), None, None)),
+      | "default" => entries.push((None, None, None)),
+      | "login" => {
+        let login = tokens.next();
+
+        if let Some(entry) = entries.last_mut() {
+          entry.1 = login;
+        }
+      },
+      | "password" => {
+        let password = tokens.next();
+
+        if let Some(entry) = entries.last_mut() {
+          entry.2 = password;
+        }
+      },
+      | _ => {},
+    }
+  }
+
+  entries
+    .iter()
+    .find(|(machine, ..)| *machine == Some(host))
+    .or_else(|| entries.iter().find(|(machine, ..)| machine.is_none()))
+    .and_then(|(_, login, password)| {
+      Some(Credentials::new(login.unwrap_or_default(), (*password)?))
+    })
+}
+
+/// Builds git callbacks that authenticate using the given provider. Credentials are offered only
+/// once, as libgit2 keeps asking for them for as long as authentication fails.
+pub(crate) fn git_callbacks(provider: &dyn CredentialProvider) -> git2::RemoteCallbacks<'_> {
+  let attempted = Cell::new(false);
+  let mut callbacks = git2::RemoteCallbacks::new();
+
+  callbacks.credentials(move |url, _, allowed| {
+    let host = reqwest::Url::parse(url)
+      .ok()
+      .and_then(|url| url.host_str().map(str::to_string));
+
+    let credentials = host
+      .filter(|_| allowed.contains(git2::CredentialType::USER_PASS_PLAINTEXT))
+      .filter(|_| !attempted.replace(true))
+      .and_then(|host| provider.credentials(&host));
+
+    match credentials {
+      | Some(Credentials { username, secret }) => {
+        git2::Cred::userpass_plaintext(&username, secret.expose())
+      },
+      | None => Err(git2::Error::from_str("no credentials available")),
+    }
+  });
+
+  callbacks
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use super::*;
+
+  #[test]
+  fn secret_is_redacted() {
+    let credentials = Credentials::new("user", "hunter2");
+
+    assert!(!format!("{credentials:?}").contains("hunter2"));
+    assert!(!format!("{}", credentials.secret).contains("hunter2"));
+    assert_eq!(credentials.secret.expose(), "hunter2");
+  }
+
+  #[test]
+  fn credentials_from_env() {
+    let vars = HashMap::from([("GITHUB_TOKEN", " "), ("GH_TOKEN", "gh-token")]);
+    let lookup = |name: &str| vars.get(name).map(|value| value.to_string());
+
+    assert_eq!(
+      from_env("api.github.com", lookup),
+      Some(Credentials::new("x-access-token", "gh-token"))
+    );
+
+    assert_eq!(from_env("gitlab.com", lookup), None);
+    assert_eq!(from_env("example.com", lookup), None);
+  }
+
+  #[test]
+  fn credentials_from_netrc() {
+    let contents = "
+      machine github.com login octocat password gh-token
+      machine gitlab.com
+        login tanuki
+        password gl-token
+      default login anonymous password fallback
+    ";
+
+    assert_eq!(
+      from_netrc(contents, "gitlab.com"),
+      Some(Credentials::new("tanuki", "gl-token"))
+    );
+
+    assert_eq!(
+      from_netrc(contents, "example.com"),
+      Some(Credentials::new("anonymous", "fallback"))
+    );
+
+    assert_eq!(
+      from_netrc("machine github.com login octocat", "github.com"),
+      None
+    );
+  }
+}
@@ -1,18 +1,25 @@
 use std::cmp::Ordering;
-use std::collections::HashMap;
-use std::fs;
-use std::io;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::process;
 use std::str::FromStr;
 
 use base32::Alphabet;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use crossterm::style::Stylize;
+use fs4::fs_std::FileExt;
+use indicatif::HumanBytes;
 use itertools::Itertools;
 use miette::{Diagnostic, Report};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
+use crate::logger::{info, summary};
 use crate::repository::RemoteRepository;
 
 /// Unpadded Base 32 alphabet.
@@ -32,6 +39,21 @@ const CACHE_TARBALLS_DIR: &str = "tarballs";
 /// `<CACHE_ROOT>/manifest.toml`
 const CACHE_MANIFEST: &str = "manifest.toml";
 
+/// `<CACHE_ROOT>/.lock`
+const CACHE_LOCK: &str = ".lock";
+
+/// Current version of the manifest schema.
+const MANIFEST_VERSION: u32 = 1;
+
+/// Environment variable with the cache root directory, overriding the home-based default.
+const CACHE_DIR_VAR: &str = "DECAFF_CACHE_DIR";
+
+/// Environment variable with the max age of cached branch/tag tarballs, in seconds.
+const CACHE_TTL_VAR: &str = "DECAFF_CACHE_TTL";
+
+/// Environment variable with the max total size of cached tarballs, in bytes.
+const CACHE_MAX_SIZE_VAR: &str = "DECAFF_CACHE_MAX_SIZE";
+
 #[derive(Debug, Diagnostic, Error)]
 pub enum CacheError {
   #[error("{message}")]
@@ -52,6 +74,12 @@ pub enum CacheError {
   Diagnostic(Report),
 }
 
+/// Advisory lock on the cache root, serializing access of concurrent processes. Released on drop.
+#[derive(Debug)]
+struct CacheLock {
+  _file: File,
+}
+
 /// Entry name in the form of Base 32 encoded source string.
 type Entry = String;
 
@@ -60,20 +88,27 @@ type Entry = String;
 /// # Structure
 ///
 /// ```toml
+/// version = <version>
+///
 /// [templates.<entry>]
 /// name = "<name>"
 /// hash = "<hash>"
 /// timestamp = <timestamp>
+/// checksum = "<checksum>"
 /// ```
 ///
 /// Where:
 ///
+/// - `<version>` - Manifest schema version. Missing in manifests written before versioning.
 /// - `<entry>` - Base 32 encoded source string in the form of: `<host>:<user>/<repo>`.
 /// - `<name>` - Ref name or commit hash.
 /// - `<hash>` - Ref/commit hash, either short or full. Used in filenames.
 /// - `<timestamp>` - Unix timestamp in milliseconds.
+/// - `<checksum>` - SHA-256 checksum of the tarball. Missing in manifests prior to version 1.
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Manifest {
+  #[serde(default)]
+  version: u32,
   templates: HashMap<Entry, Vec<Item>>,
 }
 
@@ -108,6 +143,7 @@ impl Manifest {
   /// Writes manifest to disk.
   fn write(&mut self, root: impl AsRef<Path>) -> miette::Result<()> {
     self.normalize();
+    self.version = MANIFEST_VERSION;
 
     // Create cache directory if it doesn't exist.
     fs::create_dir_all(root.as_ref()).map_err(|source| {
@@ -117,15 +153,20 @@ impl Manifest {
       }
     })?;
 
-    // Serialize and write manifest.
+    // Serialize and write manifest. It's written to a temporary file first and then renamed, so
+    // readers that don't hold the lock never see a partially written manifest.
     let manifest = toml::to_string(&self).map_err(CacheError::TomlSerialize)?;
-
-    fs::write(root.as_ref().join(CACHE_MANIFEST), manifest).map_err(|source| {
-      CacheError::Io {
-        message: "Failed to write the manifest to disk.".to_string(),
-        source,
-      }
-    })?;
+    let location = root.as_ref().join(CACHE_MANIFEST);
+    let temporary = location.with_extension("toml.tmp");
+
+    fs::write(&temporary, manifest)
+      .and_then(|_| fs::rename(&temporary, &location))
+      .map_err(|source| {
+        CacheError::Io {
+          message: "Failed to write the manifest to disk.".to_string(),
+          source,
+        }
+      })?;
 
     Ok(())
   }
@@ -181,6 +222,87 @@ pub struct Item {
   hash: String,
   /// Unix timestamp in milliseconds.
   timestamp: i64,
+  /// SHA-256 checksum of the tarball. Items written before checksums were introduced don't have
+  /// it, and are not verified.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  checksum: Option<String>,
+}
+
+impl Item {
+  /// Checks if the item was requested by a commit hash rather than by a ref name. Such items are
+  /// immutable, so they never expire.
+  fn is_commit(&self) -> bool {
+    Cache::compare_hashes(&self.name, &self.hash)
+  }
+}
+
+/// Cache entry with its decoded source, used for listing.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ListedEntry {
+  /// Source string in the form of: `<host>:<user>/<repo>`.
+  pub source: String,
+  /// Host of the repository, e.g. `github`.
+  pub host: String,
+  /// User or organization owning the repository.
+  pub user: String,
+  /// Repository name.
+  pub repo: String,
+  /// Cached items.
+  pub items: Vec<ListedItem>,
+}
+
+/// Cached item along with the size of its tarball, used for listing.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ListedItem {
+  #[serde(flatten)]
+  pub item: Item,
+  /// Size of the tarball in bytes, or `None` if the tarball is missing.
+  pub size: Option<u64>,
+}
+
+/// Output format of cache listings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ListFormat {
+  /// Colored, human-readable tree.
+  #[default]
+  Human,
+  /// Machine-readable JSON array of entries.
+  Json,
+}
+
+/// Tarball being written to the cache chunk by chunk, e.g. while it's downloaded. It's written to a
+/// temporary file next to cached tarballs, and its checksum is computed along the way. Write errors
+/// are deferred until it's stored via [Cache::store], so they don't interrupt the download. The
+/// temporary file is removed on drop.
+#[derive(Debug)]
+pub struct PendingTarball {
+  path: PathBuf,
+  file: Option<File>,
+  hasher: Sha256,
+  error: Option<io::Error>,
+}
+
+impl PendingTarball {
+  /// Appends a chunk to the tarball. Chunks are ignored after the first failed write.
+  pub fn append(&mut self, chunk: &[u8]) {
+    let Some(file) = self.file.as_mut().filter(|_| self.error.is_none()) else {
+      return;
+    };
+
+    match file.write_all(chunk) {
+      | Ok(..) => self.hasher.update(chunk),
+      | Err(err) => self.error = Some(err),
+    }
+  }
+}
+
+impl Drop for PendingTarball {
+  fn drop(&mut self) {
+    // The file must be closed first, otherwise it can't be removed on Windows.
+    self.file.take();
+
+    let _ = fs::remove_file(&self.path);
+  }
 }
 
 #[derive(Debug)]
@@ -189,22 +311,160 @@ pub struct Cache {
   root: PathBuf,
   /// Manifest.
   manifest: Manifest,
+  /// Max age of cached items. Items older than that are treated as missing. Items requested by a
+  /// commit hash never expire.
+  ttl: Option<Duration>,
+  /// Max total size of cached tarballs, in bytes. When exceeded, least recently written items are
+  /// evicted.
+  max_size: Option<u64>,
 }
 
 impl Cache {
-  /// Initializes cache and creates manifest if it doesn't exist.
+  /// Initializes cache located at `DECAFF_CACHE_DIR`, or in the home directory by default. See
+  /// [Cache::with_root] for details.
   pub fn init() -> miette::Result<Self> {
-    let root = Self::get_root()?;
+    Self::with_root(Self::get_root()?)
+  }
+
+  /// Initializes cache located at the given root directory, creating the directory if it doesn't
+  /// exist. Max age of cached items and max size of the cache are read from the `DECAFF_CACHE_TTL`
+  /// and `DECAFF_CACHE_MAX_SIZE` environment variables.
+  pub fn with_root(root: PathBuf) -> miette::Result<Self> {
+    fs::create_dir_all(&root).map_err(|source| {
+      CacheError::Io {
+        message: format!("Failed to create the cache directory '{}'.", root.display()),
+        source,
+      }
+    })?;
+
+    let cache = Self::load(root)?;
+    let ttl = Self::get_ttl()?;
+    let max_size = Self::get_max_size()?;
+
+    Ok(cache.ttl(ttl).max_size(max_size))
+  }
+
+  /// Loads cache located at the given root directory.
+  pub(crate) fn load(root: PathBuf) -> miette::Result<Self> {
     let manifest = Manifest::read(&root)?;
 
-    Ok(Self { root, manifest })
+    Ok(Self {
+      root,
+      manifest,
+      ttl: None,
+      max_size: None,
+    })
+  }
+
+  /// Acquires an exclusive lock on the cache, blocking until other processes release it. The
+  /// manifest is re-read afterwards, so changes made by other processes since loading aren't lost.
+  fn lock(&mut self) -> miette::Result<CacheLock> {
+    fs::create_dir_all(&self.root).map_err(|source| {
+      CacheError::Io {
+        message: "Failed to create the cache directory.".to_string(),
+        source,
+      }
+    })?;
+
+    let file = File::options()
+      .create(true)
+      .truncate(false)
+      .write(true)
+      .open(self.root.join(CACHE_LOCK))
+      .map_err(|source| {
+        CacheError::Io {
+          message: "Failed to open the cache lock file.".to_string(),
+          source,
+        }
+      })?;
+
+    file.lock_exclusive().map_err(|source| {
+      CacheError::Io {
+        message: "Failed to lock the cache.".to_string(),
+        source,
+      }
+    })?;
+
+    self.manifest = Manifest::read(&self.root)?;
+
+    Ok(CacheLock { _file: file })
+  }
+
+  /// Sets the max age of cached items.
+  pub fn ttl(mut self, ttl: Option<Duration>) -> Self {
+    self.ttl = ttl;
+    self
+  }
+
+  /// Returns the max age of cached items set via the environment variable, if any.
+  fn get_ttl() -> Result<Option<Duration>, CacheError> {
+    let Ok(value) = env::var(CACHE_TTL_VAR) else {
+      return Ok(None);
+    };
+
+    value
+      .trim()
+      .parse::<i64>()
+      .ok()
+      .and_then(Duration::try_seconds)
+      .filter(|ttl| *ttl >= Duration::zero())
+      .map(Some)
+      .ok_or_else(|| {
+        CacheError::Diagnostic(miette::miette!(
+          code = "decaff::cache::ttl",
+          help = "Set it to the max age of cached tarballs in seconds, e.g. `86400`.",
+          "Invalid value of `{CACHE_TTL_VAR}`: `{value}`."
+        ))
+      })
+  }
+
+  /// Sets the max total size of cached tarballs, in bytes.
+  pub fn max_size(mut self, max_size: Option<u64>) -> Self {
+    self.max_size = max_size;
+    self
+  }
+
+  /// Returns the max total size of cached tarballs set via the environment variable, if any.
+  fn get_max_size() -> Result<Option<u64>, CacheError> {
+    let Ok(value) = env::var(CACHE_MAX_SIZE_VAR) else {
+      return Ok(None);
+    };
+
+    value.trim().parse::<u64>().map(Some).map_err(|_| {
+      CacheError::Diagnostic(miette::miette!(
+        code = "decaff::cache::max_size",
+        help = "Set it to the max total size of cached tarballs in bytes, e.g. `1073741824`.",
+        "Invalid value of `{CACHE_MAX_SIZE_VAR}`: `{value}`."
+      ))
+    })
+  }
+
+  /// Checks if the item is older than the max age of cached items.
+  fn is_expired(&self, item: &Item) -> bool {
+    match self.ttl {
+      | Some(ttl) if !item.is_commit() => {
+        Utc::now().timestamp_millis() - item.timestamp > ttl.num_milliseconds()
+      },
+      | _ => false,
+    }
   }
 
   /// Returns the root cache directory.
   fn get_root() -> miette::Result<PathBuf> {
-    home::home_dir()
-      .map(|home| home.join(CACHE_ROOT))
-      .ok_or(miette::miette!("Failed to resolve home directory."))
+    Self::resolve_root(env::var_os(CACHE_DIR_VAR), home::home_dir())
+  }
+
+  /// Resolves the cache root from the given override, falling back to the given home directory.
+  /// Empty overrides are ignored.
+  fn resolve_root(dir: Option<OsString>, home: Option<PathBuf>) -> miette::Result<PathBuf> {
+    match dir.filter(|dir| !dir.is_empty()) {
+      | Some(dir) => Ok(PathBuf::from(dir)),
+      | None => {
+        home
+          .map(|home| home.join(CACHE_ROOT))
+          .ok_or(miette::miette!("Failed to resolve home directory."))
+      },
+    }
   }
 
   /// Parses a string into a [RemoteRepository].
@@ -227,7 +487,7 @@ impl Cache {
     }
   }
 
-  /// Writes contents to cache.
+  /// Writes contents to cache. See [Cache::store] for details.
   pub fn write(
     &mut self,
     source: &str,
@@ -235,8 +495,103 @@ impl Cache {
     hash: &str,
     contents: &[u8],
   ) -> miette::Result<()> {
+    let mut tarball = self.pending(hash)?;
+    tarball.append(contents);
+
+    self.store(source, name, hash, tarball)
+  }
+
+  /// Creates a tarball with the given hash to be written chunk by chunk and then stored via
+  /// [Cache::store].
+  pub fn pending(&self, hash: &str) -> miette::Result<PendingTarball> {
+    let tarballs_dir = self.root.join(CACHE_TARBALLS_DIR);
+
+    fs::create_dir_all(&tarballs_dir).map_err(|source| {
+      CacheError::Io {
+        message: format!("Failed to create the '{CACHE_TARBALLS_DIR}' directory."),
+        source,
+      }
+    })?;
+
+    // Leading dot and the suffix keep it from being mistaken for a cached tarball.
+    let path = tarballs_dir.join(format!(".{hash}.{}.part", process::id()));
+
+    let file = File::create(&path).map_err(|source| {
+      CacheError::Io {
+        message: format!(
+          "Failed to create the temporary tarball '{}'.",
+          path.display()
+        ),
+        source,
+      }
+    })?;
+
+    Ok(PendingTarball {
+      path,
+      file: Some(file),
+      hasher: Sha256::new(),
+      error: None,
+    })
+  }
+
+  /// Moves the written tarball into the cache and records it along with its checksum. Refs are
+  /// resolved before reading the cache, so a moved branch misses it. Storing the new tip then drops
+  /// the stale item cached under the same name.
+  pub fn store(
+    &mut self,
+    source: &str,
+    name: &str,
+    hash: &str,
+    mut tarball: PendingTarball,
+  ) -> miette::Result<()> {
+    if let Some(source) = tarball.error.take() {
+      return Err(
+        CacheError::Io {
+          message: "Failed to write the tarball contents to disk.".to_string(),
+          source,
+        }
+        .into(),
+      );
+    }
+
+    let _lock = self.lock()?;
+
+    // Drop expired items first, so they are replaced with fresh ones.
+    self.prune()?;
+
     let entry = base32::encode(BASE32_ALPHABET, source.as_bytes());
+
+    // Moving refs like branches are cached by name, so once the ref resolves to another hash, the
+    // item of the previous tip is stale.
+    let stale: Vec<Item> = self
+      .manifest
+      .templates
+      .get(&entry)
+      .map(|items| {
+        items
+          .iter()
+          .filter(|item| item.name == name && !Self::compare_hashes(hash, &item.hash))
+          .cloned()
+          .collect()
+      })
+      .unwrap_or_default();
+
+    if !stale.is_empty() {
+      self.drop_items(HashMap::from([(entry.clone(), stale)]))?;
+    }
+
+    // The file must be closed first, otherwise it can't be renamed on Windows.
+    tarball.file.take();
+
+    fs::rename(&tarball.path, self.get_tarball(hash)).map_err(|source| {
+      CacheError::Io {
+        message: "Failed to move the tarball into the cache.".to_string(),
+        source,
+      }
+    })?;
+
     let timestamp = Utc::now().timestamp_millis();
+    let checksum = Some(Self::encode_checksum(&tarball.hasher.clone().finalize()));
 
     self
       .manifest
@@ -246,11 +601,23 @@ impl Cache {
         let hash = hash.to_string();
         let name = name.to_string();
 
-        if !items
-          .iter()
-          .any(|item| Self::compare_hashes(&hash, &item.hash))
+        match items
+          .iter_mut()
+          .find(|item| Self::compare_hashes(&hash, &item.hash))
         {
-          items.push(Item { name, hash, timestamp });
+          // The tarball is overwritten, so its checksum and timestamp must be updated too.
+          | Some(item) => {
+            item.checksum.clone_from(&checksum);
+            item.timestamp = timestamp;
+          },
+          | None => {
+            items.push(Item {
+              name,
+              hash,
+              timestamp,
+              checksum: checksum.clone(),
+            })
+          },
         }
       })
       .or_insert_with(|| {
@@ -258,96 +625,415 @@ impl Cache {
           name: name.to_string(),
           hash: hash.to_string(),
           timestamp,
+          checksum: checksum.clone(),
         }]
       });
 
     self.manifest.write(&self.root)?;
 
-    let tarballs_dir = self.root.join(CACHE_TARBALLS_DIR);
-    let tarball = tarballs_dir.join(format!("{hash}.tar.gz"));
+    // Make room for the new tarball if the cache grew too big.
+    self.evict(hash)?;
 
-    fs::create_dir_all(&tarballs_dir).map_err(|source| {
+    Ok(())
+  }
+
+  /// Checks whether a tarball for the given source and hash is cached and not expired.
+  pub fn contains(&self, source: &str, hash: &str) -> bool {
+    let entry = base32::encode(BASE32_ALPHABET, source.as_bytes());
+
+    self.manifest.templates.get(&entry).is_some_and(|items| {
+      items
+        .iter()
+        .any(|item| Self::compare_hashes(hash, &item.hash) && !self.is_expired(item))
+    })
+  }
+
+  /// Reads from cache and returns the cached tarball bytes if any. Expired items are treated as
+  /// missing, as well as corrupted ones, i.e. with mismatching checksums. The latter are removed.
+  pub fn read(&mut self, source: &str, hash: &str) -> miette::Result<Option<Vec<u8>>> {
+    let _lock = self.lock()?;
+    let entry = base32::encode(BASE32_ALPHABET, source.as_bytes());
+
+    let item = self.manifest.templates.get(&entry).and_then(|items| {
+      items
+        .iter()
+        .find(|item| Self::compare_hashes(hash, &item.hash) && !self.is_expired(item))
+        .cloned()
+    });
+
+    let Some(item) = item else {
+      return Ok(None);
+    };
+
+    let contents = fs::read(self.get_tarball(&item.hash)).map_err(|source| {
       CacheError::Io {
-        message: format!("Failed to create the '{CACHE_TARBALLS_DIR}' directory."),
+        message: "Failed to read the cached tarball.".to_string(),
         source,
       }
     })?;
 
-    fs::write(tarball, contents).map_err(|source| {
-      CacheError::Io {
-        message: "Failed to write the tarball contents to disk.".to_string(),
-        source,
+    let is_corrupted = item
+      .checksum
+      .as_ref()
+      .is_some_and(|checksum| *checksum != Self::get_checksum(&contents));
+
+    if is_corrupted {
+      let message = format!("? Cached tarball {} is corrupted, removing it", item.hash);
+      info!("{}", message.yellow());
+
+      self.drop_items(HashMap::from([(entry, vec![item])]))?;
+
+      return Ok(None);
+    }
+
+    Ok(Some(contents))
+  }
+
+  /// Computes the SHA-256 checksum of the contents as a hex string.
+  fn get_checksum(contents: &[u8]) -> String {
+    Self::encode_checksum(&Sha256::digest(contents))
+  }
+
+  /// Encodes a computed checksum as a hex string.
+  fn encode_checksum(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+  }
+
+  /// Removes expired items along with their tarballs. Returns the number of removed items.
+  pub fn prune(&mut self) -> miette::Result<usize> {
+    let mut expired: HashMap<Entry, Vec<Item>> = HashMap::new();
+
+    for (key, items) in &self.manifest.templates {
+      for item in items.iter().filter(|item| self.is_expired(item)) {
+        expired
+          .entry(key.to_owned())
+          .or_default()
+          .push(item.clone());
       }
+    }
+
+    self.drop_items(expired)
+  }
+
+  /// Removes the least recently written items along with their tarballs until the total size of
+  /// tarballs fits into the max cache size. The item with the `keep` hash is never removed. Returns
+  /// the number of removed items.
+  pub fn evict(&mut self, keep: &str) -> miette::Result<usize> {
+    let Some(max_size) = self.max_size else {
+      return Ok(0);
+    };
+
+    let mut items = Vec::new();
+    let mut total = 0;
+
+    for (key, entry_items) in &self.manifest.templates {
+      for item in entry_items {
+        let size = fs::metadata(self.get_tarball(&item.hash)).map_or(0, |metadata| metadata.len());
+
+        total += size;
+        items.push((key, item, size));
+      }
+    }
+
+    let mut evicted: HashMap<Entry, Vec<Item>> = HashMap::new();
+
+    for (key, item, size) in items
+      .into_iter()
+      .sorted_by_key(|(_, item, _)| item.timestamp)
+    {
+      if total <= max_size {
+        break;
+      }
+
+      if Self::compare_hashes(keep, &item.hash) {
+        continue;
+      }
+
+      total -= size;
+      evicted
+        .entry(key.to_owned())
+        .or_default()
+        .push(item.clone());
+    }
+
+    self.drop_items(evicted)
+  }
+
+  /// Removes the selected items along with their tarballs. Returns the number of removed items.
+  fn drop_items(&mut self, selection: HashMap<Entry, Vec<Item>>) -> miette::Result<usize> {
+    if selection.is_empty() {
+      return Ok(0);
+    }
+
+    for item in selection.values().flatten() {
+      let tarball = self.get_tarball(&item.hash);
+
+      match fs::remove_file(&tarball) {
+        | Ok(..) => {},
+        | Err(source) if source.kind() == io::ErrorKind::NotFound => {},
+        | Err(source) => {
+          return Err(
+            CacheError::Io {
+              message: format!("Failed to remove the '{}' tarball.", tarball.display()),
+              source,
+            }
+            .into(),
+          );
+        },
+      }
+    }
+
+    self.manifest.remove_entries(&selection);
+    self.manifest.write(&self.root)?;
+
+    Ok(selection.values().map(Vec::len).sum())
+  }
+
+  /// Returns the path to the tarball with the given hash.
+  fn get_tarball(&self, hash: &str) -> PathBuf {
+    self
+      .root
+      .join(CACHE_TARBALLS_DIR)
+      .join(format!("{hash}.tar.gz"))
+  }
+
+  /// Decodes an entry name back into the source string.
+  fn decode_entry(key: &str) -> Result<String, CacheError> {
+    let bytes = base32::decode(BASE32_ALPHABET, key).ok_or_else(|| {
+      CacheError::Diagnostic(miette::miette!(
+        code = "decaff::cache::malformed_entry",
+        help = "Manifest may be malformed, clear the cache and try again.",
+        "Couldn't decode entry: `{key}`."
+      ))
     })?;
 
-    Ok(())
+    String::from_utf8(bytes).map_err(|_| {
+      CacheError::Diagnostic(miette::miette!(
+        code = "decaff::cache::invalid_utf8",
+        help = "Manifest may be malformed, clear the cache and try again.",
+        "Couldn't decode entry due to invalid UTF-8 in the string: `{key}`."
+      ))
+    })
   }
 
-  /// Reads from cache and returns the cached tarball bytes if any.
-  pub fn read(&self, source: &str, hash: &str) -> miette::Result<Option<Vec<u8>>> {
-    let entry = base32::encode(BASE32_ALPHABET, source.as_bytes());
+  /// Collects cache entries sorted by source, with items sorted from newest to oldest. If a filter
+  /// is given, only entries whose source contains it are collected, as well as items matching it
+  /// by ref name or hash.
+  pub fn entries(&self, filter: Option<&str>) -> Result<Vec<ListedEntry>, CacheError> {
+    let selection = match filter {
+      | Some(filter) => self.manifest.select_entries(vec![filter.to_string()]),
+      | None => HashMap::new(),
+    };
 
-    if let Some(items) = self.manifest.templates.get(&entry) {
-      let item = items
+    let mut entries = Vec::new();
+
+    for (key, items) in &self.manifest.templates {
+      let source = Self::decode_entry(key)?;
+
+      let items = match filter {
+        | Some(filter) if !source.contains(filter) => {
+          match selection.get(key) {
+            | Some(items) => items,
+            | None => continue,
+          }
+        },
+        | _ => items,
+      };
+
+      let items = items
         .iter()
-        .find(|item| Self::compare_hashes(hash, &item.hash));
-
-      if let Some(item) = item {
-        let tarball = self
-          .root
-          .join(CACHE_TARBALLS_DIR)
-          .join(format!("{}.tar.gz", item.hash));
-
-        let contents = fs::read(tarball).map_err(|source| {
-          CacheError::Io {
-            message: "Failed to read the cached tarball.".to_string(),
-            source,
+        .sorted_by(|a, b| b.timestamp.cmp(&a.timestamp))
+        .map(|item| {
+          ListedItem {
+            item: item.clone(),
+            size: fs::metadata(self.get_tarball(&item.hash))
+              .ok()
+              .map(|metadata| metadata.len()),
           }
-        })?;
+        })
+        .collect();
+
+      let repository = Self::parse_repository(&source)?;
+
+      entries.push(ListedEntry {
+        source,
+        host: repository.host.to_string(),
+        user: repository.user,
+        repo: repository.repo,
+        items,
+      });
+    }
+
+    entries.sort_by(|a, b| a.source.cmp(&b.source));
+
+    Ok(entries)
+  }
 
-        return Ok(Some(contents));
+  /// Renders cache entries in the given format, optionally only those matching the given source,
+  /// partial source, ref name or hash.
+  pub fn render(&self, filter: Option<&str>, format: ListFormat) -> Result<String, CacheError> {
+    let entries = self.entries(filter)?;
+
+    if format == ListFormat::Json {
+      return serde_json::to_string_pretty(&entries).map_err(|err| {
+        CacheError::Diagnostic(miette::miette!(
+          code = "decaff::cache::json",
+          "Couldn't serialize cache entries: {err}."
+        ))
+      });
+    }
+
+    if entries.is_empty() {
+      return Ok(String::new());
+    }
+
+    let mut lines = Vec::new();
+    let mut total = 0;
+
+    for entry in entries {
+      let host = entry.host.cyan();
+      let name = format!("{}/{}", entry.user, entry.repo).green();
+
+      lines.push(format!("⋅ {host}:{name}"));
+
+      for ListedItem { item, size } in entry.items {
+        if let Some(date) = DateTime::from_timestamp_millis(item.timestamp) {
+          let date = date.format("%d/%m/%Y %H:%M").to_string().dim();
+          let name = item.name.cyan();
+          let hash = item.hash.yellow();
+
+          let size = match size {
+            | Some(size) => {
+              total += size;
+              HumanBytes(size).to_string().dim()
+            },
+            | None => "✗ missing tarball".to_string().red(),
+          };
+
+          lines.push(format!("└─ {date} @ {name} ╌╌ {hash} {size}"));
+        }
       }
     }
 
-    Ok(None)
+    lines.push(format!("~ Total: {}", HumanBytes(total)).dim().to_string());
+
+    Ok(lines.join("\n"))
   }
 
-  /// Lists cache entries.
-  pub fn list(&self) -> Result<(), CacheError> {
-    for (key, items) in &self.manifest.templates {
-      if let Some(bytes) = base32::decode(BASE32_ALPHABET, key) {
-        let entry = String::from_utf8(bytes).map_err(|_| {
-          CacheError::Diagnostic(miette::miette!(
-            code = "decaff::cache::invalid_utf8",
-            help = "Manifest may be malformed, clear the cache and try again.",
-            "Couldn't decode entry due to invalid UTF-8 in the string: `{key}`."
-          ))
-        })?;
-
-        let repo = Self::parse_repository(&entry)?;
-        let host = repo.host.to_string().cyan();
-        let name = format!("{}/{}", repo.user, repo.repo).green();
-
-        println!("⋅ {host}:{name}");
-
-        for item in items.iter().sorted_by(|a, b| b.timestamp.cmp(&a.timestamp)) {
-          if let Some(date) = DateTime::from_timestamp_millis(item.timestamp) {
-            let date = date.format("%d/%m/%Y %H:%M").to_string().dim();
-            let name = item.name.clone().cyan();
-            let hash = item.hash.clone().yellow();
-
-            println!("└─ {date} @ {name} ╌╌ {hash}");
-          }
+  /// Lists cache entries in the given format, optionally only those matching the given source,
+  /// partial source, ref name or hash. JSON is printed regardless of verbosity, since it's meant to
+  /// be consumed by other programs.
+  pub fn list(&self, filter: Option<&str>, format: ListFormat) -> Result<(), CacheError> {
+    let output = self.render(filter, format)?;
+
+    match format {
+      | ListFormat::Json => println!("{output}"),
+      | ListFormat::Human if !output.is_empty() => info!("{output}"),
+      | ListFormat::Human => {},
+    }
+
+    Ok(())
+  }
+
+  /// Removes tarballs that aren't referenced by the manifest, e.g. left behind by interrupted
+  /// writes. Returns the number of removed tarballs and the number of freed bytes.
+  pub fn gc(&mut self) -> miette::Result<(usize, u64)> {
+    let _lock = self.lock()?;
+    let tarballs_dir = self.root.join(CACHE_TARBALLS_DIR);
+
+    if !tarballs_dir.is_dir() {
+      return Ok((0, 0));
+    }
+
+    let referenced: HashSet<&str> = self
+      .manifest
+      .templates
+      .values()
+      .flatten()
+      .map(|item| item.hash.as_str())
+      .collect();
+
+    let read_failed = |source| {
+      CacheError::Io {
+        message: format!("Failed to read the '{CACHE_TARBALLS_DIR}' directory."),
+        source,
+      }
+    };
+
+    let mut removed = 0;
+    let mut freed = 0;
+
+    for entry in fs::read_dir(&tarballs_dir).map_err(read_failed)? {
+      let entry = entry.map_err(read_failed)?;
+      let path = entry.path();
+
+      let Some(hash) = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_suffix(".tar.gz"))
+      else {
+        continue;
+      };
+
+      if referenced.contains(hash) || !path.is_file() {
+        continue;
+      }
+
+      let size = entry.metadata().map_or(0, |metadata| metadata.len());
+
+      fs::remove_file(&path).map_err(|source| {
+        CacheError::Io {
+          message: format!(
+            "Failed to remove the orphaned tarball '{}'.",
+            path.display()
+          ),
+          source,
         }
-      } else {
-        return Err(CacheError::Diagnostic(miette::miette!(
-          code = "decaff::cache::malformed_entry",
-          help = "Manifest may be malformed, clear the cache and try again.",
-          "Couldn't decode entry: `{key}`."
-        )));
+      })?;
+
+      removed += 1;
+      freed += size;
+    }
+
+    Ok((removed, freed))
+  }
+
+  /// Prints selected cache entries along with sizes of their tarballs, without removing anything.
+  fn preview(&self, selection: &HashMap<Entry, Vec<Item>>) -> miette::Result<()> {
+    let mut total = 0;
+
+    for (key, items) in selection.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+      let entry = Self::decode_entry(key)?;
+
+      let repo = Self::parse_repository(&entry)?;
+      let host = repo.host.to_string().cyan();
+      let name = format!("{}/{}", repo.user, repo.repo).green();
+
+      info!("⋅ {host}:{name}");
+
+      for item in items.iter().sorted_by(|a, b| b.timestamp.cmp(&a.timestamp)) {
+        let tarball = self.get_tarball(&item.hash);
+
+        // Missing tarballs are still dropped from the manifest, they just free up nothing.
+        let size = fs::metadata(&tarball).map_or(0, |metadata| metadata.len());
+        total += size;
+
+        let name = item.name.clone().cyan();
+        let hash = item.hash.clone().yellow();
+        let size = HumanBytes(size).to_string().dim();
+
+        info!("└─ {name} ╌╌ {hash} {size}");
       }
     }
 
+    let count = selection.values().map(Vec::len).sum::<usize>();
+    let message = format!(
+      "~ Dry run: would remove {count} item(s) ({})",
+      HumanBytes(total)
+    );
+
+    summary!("{}", message.dim());
+
     Ok(())
   }
 
@@ -356,12 +1042,25 @@ impl Cache {
   /// - entry name, e.g. github:foo/bar -- this will delete all cached entries under that name;
   /// - entry hash, e.g. 4a5a56fd -- this will delete specific cached entry;
   /// - ref name, e.g. feat/some-feature-name -- same as entry hash.
-  pub fn remove(&mut self, needles: Vec<String>) -> miette::Result<()> {
+  ///
+  /// Only entries whose tarballs were actually removed (or were already missing) are dropped from
+  /// the manifest. If any tarball couldn't be removed, an error listing them is returned.
+  ///
+  /// If `dry_run` is set, only prints what would be removed.
+  pub fn remove(&mut self, needles: Vec<String>, dry_run: bool) -> miette::Result<()> {
+    if dry_run {
+      return self.preview(&self.manifest.select_entries(needles));
+    }
+
+    let _lock = self.lock()?;
     let selection = self.manifest.select_entries(needles);
 
+    let mut removed: HashMap<Entry, Vec<Item>> = HashMap::new();
+    let mut failed = Vec::new();
+
     // Actually remove the files and print their names (<hash>.tar.gz).
-    for (entry, items) in &selection {
-      let entry = base32::decode(BASE32_ALPHABET, entry.as_str())
+    for (key, items) in &selection {
+      let entry = base32::decode(BASE32_ALPHABET, key.as_str())
         .and_then(|bytes| String::from_utf8(bytes).ok())
         .unwrap();
 
@@ -369,34 +1068,67 @@ impl Cache {
       let host = repo.host.to_string().cyan();
       let name = format!("{}/{}", repo.user, repo.repo).green();
 
-      println!("⋅ {host}:{name}");
+      info!("⋅ {host}:{name}");
 
       for item in items.iter().sorted_by(|a, b| b.timestamp.cmp(&a.timestamp)) {
-        let tarball = self
-          .root
-          .join(CACHE_TARBALLS_DIR)
-          .join(format!("{}.tar.gz", &item.hash));
+        let tarball = self.get_tarball(&item.hash);
 
         let name = item.name.clone().cyan();
         let hash = item.hash.clone().yellow();
 
-        print!("└─ {name} ╌╌ {hash} ");
+        let removed_file = match fs::remove_file(&tarball) {
+          | Ok(..) => true,
+          | Err(err) => err.kind() == io::ErrorKind::NotFound,
+        };
 
-        match fs::remove_file(&tarball) {
-          | Ok(..) => println!("{}", "✓".green()),
-          | Err(..) => println!("{}", "✗".red()),
+        if !removed_file {
+          info!("└─ {name} ╌╌ {hash} {}", "✗".red());
+          failed.push(tarball);
+          continue;
         }
+
+        info!("└─ {name} ╌╌ {hash} {}", "✓".green());
+
+        removed
+          .entry(key.to_owned())
+          .or_default()
+          .push(item.clone());
       }
     }
 
-    self.manifest.remove_entries(&selection);
+    self.manifest.remove_entries(&removed);
     self.manifest.write(&self.root)?;
 
+    if !failed.is_empty() {
+      let total = selection.values().map(Vec::len).sum::<usize>();
+
+      let list = failed
+        .iter()
+        .map(|tarball| format!("- {}", tarball.display()))
+        .join("\n");
+
+      return Err(
+        CacheError::Diagnostic(miette::miette!(
+          code = "decaff::cache::remove",
+          help = "Check permissions of the listed files and try again.",
+          "Failed to remove {} of {total} cached tarball(s):\n\n{list}",
+          failed.len()
+        ))
+        .into(),
+      );
+    }
+
     Ok(())
   }
 
-  /// Removes all cache entries.
-  pub fn remove_all(&mut self) -> miette::Result<()> {
+  /// Removes all cache entries. If `dry_run` is set, only prints what would be removed.
+  pub fn remove_all(&mut self, dry_run: bool) -> miette::Result<()> {
+    if dry_run {
+      return self.preview(&self.manifest.templates);
+    }
+
+    let _lock = self.lock()?;
+
     fs::remove_dir_all(self.root.join(CACHE_TARBALLS_DIR)).map_err(|source| {
       CacheError::Io {
         message: format!("Failed to clear the '{CACHE_TARBALLS_DIR}' directory."),
@@ -410,3 +1142,640 @@ impl Cache {
     Ok(())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use std::thread;
+
+  use tempfile::TempDir;
+
+  use super::*;
+  use crate::logger::testing::Capture;
+  use crate::logger::Verbosity;
+
+  // Helpers.
+
+  fn item(name: &str, hash: &str) -> Item {
+    Item {
+      name: name.to_string(),
+      hash: hash.to_string(),
+      timestamp: 0,
+      checksum: None,
+    }
+  }
+
+  fn tarball(root: &Path, hash: &str) -> PathBuf {
+    root.join(CACHE_TARBALLS_DIR).join(format!("{hash}.tar.gz"))
+  }
+
+  fn cache(root: &Path, templates: &[(&str, Vec<Item>)]) -> Cache {
+    let mut manifest = Manifest::default();
+
+    for (source, items) in templates {
+      let entry = base32::encode(BASE32_ALPHABET, source.as_bytes());
+      manifest.templates.insert(entry, items.clone());
+    }
+
+    manifest.write(root).unwrap();
+
+    Cache::load(root.to_path_buf()).unwrap()
+  }
+
+  fn fresh(item: Item) -> Item {
+    Item {
+      timestamp: Utc::now().timestamp_millis(),
+      ..item
+    }
+  }
+
+  fn sources(entries: &[ListedEntry]) -> Vec<&str> {
+    entries.iter().map(|entry| entry.source.as_str()).collect()
+  }
+
+  // Tests.
+
+  #[test]
+  fn list_filters_entries() {
+    let temp = TempDir::new().unwrap();
+
+    let cache = cache(
+      temp.path(),
+      &[
+        ("github:foo/bar", vec![item("main", "aaaaaaa")]),
+        ("github:foo/baz", vec![item("dev", "bbbbbbb")]),
+        (
+          "gitlab:qux/bar",
+          vec![item("main", "ccccccc"), item("dev", "ddddddd")],
+        ),
+      ],
+    );
+
+    let all = cache.entries(None).unwrap();
+    assert_eq!(
+      sources(&all),
+      ["github:foo/bar", "github:foo/baz", "gitlab:qux/bar"]
+    );
+
+    let exact = cache.entries(Some("github:foo/bar")).unwrap();
+    assert_eq!(sources(&exact), ["github:foo/bar"]);
+
+    let partial = cache.entries(Some("foo/")).unwrap();
+    assert_eq!(sources(&partial), ["github:foo/bar", "github:foo/baz"]);
+
+    let by_name = cache.entries(Some("dev")).unwrap();
+    assert_eq!(sources(&by_name), ["github:foo/baz", "gitlab:qux/bar"]);
+    assert_eq!(by_name[1].items[0].item, item("dev", "ddddddd"));
+    assert_eq!(by_name[1].items.len(), 1);
+
+    let by_hash = cache.entries(Some("ccc")).unwrap();
+    assert_eq!(by_hash[0].items[0].item, item("main", "ccccccc"));
+
+    assert!(cache.entries(Some("nothing")).unwrap().is_empty());
+  }
+
+  #[test]
+  fn gc_removes_orphaned_tarballs() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let mut cache = cache(root, &[("github:foo/bar", vec![item("main", "aaaaaaa")])]);
+
+    fs::create_dir_all(root.join(CACHE_TARBALLS_DIR)).unwrap();
+    fs::write(tarball(root, "aaaaaaa"), b"referenced").unwrap();
+    fs::write(tarball(root, "bbbbbbb"), b"orphan").unwrap();
+
+    assert_eq!(cache.gc().unwrap(), (1, 6));
+
+    assert!(tarball(root, "aaaaaaa").exists());
+    assert!(!tarball(root, "bbbbbbb").exists());
+  }
+
+  #[test]
+  fn write_replaces_stale_branch_tip() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let mut cache = Cache::load(root.to_path_buf()).unwrap();
+
+    cache
+      .write("github:foo/bar", "main", "aaaaaaa", b"old")
+      .unwrap();
+    cache
+      .write("github:foo/bar", "v1.0.0", "ccccccc", b"tag")
+      .unwrap();
+
+    // Upstream `main` moved to another commit.
+    cache
+      .write("github:foo/bar", "main", "bbbbbbb", b"new")
+      .unwrap();
+
+    assert_eq!(cache.read("github:foo/bar", "aaaaaaa").unwrap(), None);
+    assert!(!tarball(root, "aaaaaaa").exists());
+
+    assert_eq!(
+      cache.read("github:foo/bar", "bbbbbbb").unwrap(),
+      Some(b"new".to_vec())
+    );
+
+    assert_eq!(
+      cache.read("github:foo/bar", "ccccccc").unwrap(),
+      Some(b"tag".to_vec())
+    );
+  }
+
+  #[test]
+  fn store_pending_tarball() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let mut cache = Cache::load(root.to_path_buf()).unwrap();
+
+    let mut pending = cache.pending("aaaaaaa").unwrap();
+
+    for chunk in [b"con".as_slice(), b"ten", b"ts"] {
+      pending.append(chunk);
+    }
+
+    cache
+      .store("github:foo/bar", "main", "aaaaaaa", pending)
+      .unwrap();
+
+    // The checksum is computed along the way, so a read doesn't treat the tarball as corrupted.
+    assert_eq!(
+      cache.read("github:foo/bar", "aaaaaaa").unwrap(),
+      Some(b"contents".to_vec())
+    );
+
+    let files = fs::read_dir(root.join(CACHE_TARBALLS_DIR))
+      .unwrap()
+      .map(|entry| entry.unwrap().file_name())
+      .collect::<Vec<_>>();
+
+    assert_eq!(files, vec!["aaaaaaa.tar.gz"]);
+  }
+
+  #[test]
+  fn drop_pending_tarball() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let cache = Cache::load(root.to_path_buf()).unwrap();
+
+    let mut pending = cache.pending("aaaaaaa").unwrap();
+    pending.append(b"partial");
+
+    drop(pending);
+
+    assert_eq!(
+      fs::read_dir(root.join(CACHE_TARBALLS_DIR)).unwrap().count(),
+      0
+    );
+    assert!(!cache.contains("github:foo/bar", "aaaaaaa"));
+  }
+
+  #[test]
+  fn write_refreshes_overwritten_item() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let entry = base32::encode(BASE32_ALPHABET, b"github:foo/bar");
+
+    let mut cache = cache(root, &[("github:foo/bar", vec![item("main", "aaaaaaa")])]);
+
+    cache
+      .write("github:foo/bar", "main", "aaaaaaa", b"refetched")
+      .unwrap();
+
+    let items = &cache.manifest.templates[&entry];
+
+    assert_eq!(items.len(), 1);
+    assert!(items[0].timestamp > 0);
+    assert_eq!(items[0].checksum, Some(Cache::get_checksum(b"refetched")));
+
+    // The refetched tarball is fresh, so it doesn't expire.
+    let mut cache = cache.ttl(Duration::try_hours(1));
+
+    assert_eq!(
+      cache.read("github:foo/bar", "aaaaaaa").unwrap(),
+      Some(b"refetched".to_vec())
+    );
+  }
+
+  #[test]
+  fn resolve_root_with_override() {
+    let home = Some(PathBuf::from("/home/user"));
+
+    assert_eq!(
+      Cache::resolve_root(Some("/mnt/cache".into()), home.clone()).unwrap(),
+      PathBuf::from("/mnt/cache")
+    );
+
+    assert_eq!(
+      Cache::resolve_root(Some("".into()), home.clone()).unwrap(),
+      PathBuf::from("/home/user").join(CACHE_ROOT)
+    );
+
+    assert!(Cache::resolve_root(None, None).is_err());
+  }
+
+  #[test]
+  fn with_root_creates_directory() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("nested/cache");
+
+    Cache::with_root(root.clone()).unwrap();
+    assert!(root.is_dir());
+
+    // A file in place of the root can't be turned into a directory.
+    let file = temp.path().join("file");
+    fs::write(&file, "").unwrap();
+
+    assert!(Cache::with_root(file.join("cache")).is_err());
+  }
+
+  #[test]
+  fn render_sizes_and_total() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    let cache = cache(
+      root,
+      &[
+        ("github:foo/bar", vec![item("main", "aaaaaaa")]),
+        (
+          "github:foo/baz",
+          vec![item("main", "bbbbbbb"), item("dev", "ccccccc")],
+        ),
+      ],
+    );
+
+    fs::create_dir_all(root.join(CACHE_TARBALLS_DIR)).unwrap();
+    fs::write(tarball(root, "aaaaaaa"), vec![0; 1024]).unwrap();
+    fs::write(tarball(root, "bbbbbbb"), vec![0; 2048]).unwrap();
+
+    let output = cache.render(None, ListFormat::Human).unwrap();
+    let lines = output.lines().collect::<Vec<_>>();
+
+    assert!(lines.iter().any(|line| line.contains("1.00 KiB")));
+    assert!(lines.iter().any(|line| line.contains("missing tarball")));
+    assert!(lines.last().unwrap().contains("Total: 3.00 KiB"));
+
+    let json: serde_json::Value =
+      serde_json::from_str(&cache.render(None, ListFormat::Json).unwrap()).unwrap();
+
+    assert_eq!(json[0]["items"][0]["size"], 1024);
+  }
+
+  #[test]
+  fn render_entries_in_stable_order() {
+    let temp = TempDir::new().unwrap();
+
+    let unsorted = [
+      "gitlab:qux/bar",
+      "github:foo/baz",
+      "bitbucket:foo/bar",
+      "github:foo/bar",
+    ];
+
+    let templates = unsorted
+      .iter()
+      .enumerate()
+      .map(|(index, source)| (*source, vec![item("main", &format!("{index:07}"))]))
+      .collect::<Vec<_>>();
+
+    let cache = cache(temp.path(), &templates);
+
+    // Each load hashes entries with a different seed, so the manifest order differs as well.
+    let reloaded = Cache::load(temp.path().to_path_buf()).unwrap();
+
+    assert_eq!(
+      cache.render(None, ListFormat::Human).unwrap(),
+      reloaded.render(None, ListFormat::Human).unwrap()
+    );
+
+    assert_eq!(
+      sources(&cache.entries(None).unwrap()),
+      [
+        "bitbucket:foo/bar",
+        "github:foo/bar",
+        "github:foo/baz",
+        "gitlab:qux/bar"
+      ]
+    );
+  }
+
+  #[test]
+  fn render_entries_as_json() {
+    let temp = TempDir::new().unwrap();
+    let cache = cache(
+      temp.path(),
+      &[("gitlab:foo/bar", vec![item("main", "aaaaaaa")])],
+    );
+
+    let output = cache.render(None, ListFormat::Json).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+    assert_eq!(json[0]["host"], "gitlab");
+    assert_eq!(json[0]["user"], "foo");
+    assert_eq!(json[0]["repo"], "bar");
+    assert_eq!(json[0]["items"][0]["name"], "main");
+    assert_eq!(json[0]["items"][0]["hash"], "aaaaaaa");
+
+    assert!(cache
+      .render(Some("nothing"), ListFormat::Human)
+      .unwrap()
+      .is_empty());
+  }
+
+  #[test]
+  fn read_treats_expired_items_as_missing() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    let cache = cache(
+      root,
+      &[(
+        "github:foo/bar",
+        vec![
+          item("main", "aaaaaaa"),
+          item("bbbbbbb", "bbbbbbb"),
+          fresh(item("dev", "ccccccc")),
+        ],
+      )],
+    );
+
+    fs::create_dir_all(root.join(CACHE_TARBALLS_DIR)).unwrap();
+
+    for hash in ["aaaaaaa", "bbbbbbb", "ccccccc"] {
+      fs::write(tarball(root, hash), hash).unwrap();
+    }
+
+    let mut cache = cache.ttl(Duration::try_hours(1));
+
+    // Branch cached long ago.
+    assert!(!cache.contains("github:foo/bar", "aaaaaaa"));
+    assert_eq!(cache.read("github:foo/bar", "aaaaaaa").unwrap(), None);
+
+    // Commits never expire.
+    assert!(cache.contains("github:foo/bar", "bbbbbbb"));
+    assert!(cache.read("github:foo/bar", "bbbbbbb").unwrap().is_some());
+
+    // Branch cached recently.
+    assert!(cache.contains("github:foo/bar", "ccccccc"));
+    assert!(cache.read("github:foo/bar", "ccccccc").unwrap().is_some());
+
+    // Without TTL nothing expires.
+    let mut cache = cache.ttl(None);
+
+    assert!(cache.read("github:foo/bar", "aaaaaaa").unwrap().is_some());
+  }
+
+  #[test]
+  fn prune_expired_items() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let entry = base32::encode(BASE32_ALPHABET, b"github:foo/bar");
+
+    let cache = cache(
+      root,
+      &[(
+        "github:foo/bar",
+        vec![item("main", "aaaaaaa"), item("bbbbbbb", "bbbbbbb")],
+      )],
+    );
+
+    fs::create_dir_all(root.join(CACHE_TARBALLS_DIR)).unwrap();
+    fs::write(tarball(root, "aaaaaaa"), b"tarball").unwrap();
+    fs::write(tarball(root, "bbbbbbb"), b"tarball").unwrap();
+
+    let mut cache = cache.ttl(Duration::try_days(7));
+
+    assert_eq!(cache.prune().unwrap(), 1);
+    assert!(!tarball(root, "aaaaaaa").exists());
+    assert!(tarball(root, "bbbbbbb").exists());
+
+    let manifest = Manifest::read(root).unwrap();
+
+    assert_eq!(
+      manifest.templates.get(&entry),
+      Some(&vec![item("bbbbbbb", "bbbbbbb")])
+    );
+  }
+
+  #[test]
+  fn evict_least_recently_written_items() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    let mut cache = Cache::load(root.to_path_buf()).unwrap().max_size(Some(20));
+
+    // Each tarball is 8 bytes, so only two of them fit.
+    cache
+      .write("github:foo/bar", "v1", "aaaaaaa", b"tarball1")
+      .unwrap();
+    cache
+      .write("github:foo/bar", "v2", "bbbbbbb", b"tarball2")
+      .unwrap();
+    cache
+      .write("github:foo/baz", "main", "ccccccc", b"tarball3")
+      .unwrap();
+
+    assert!(!tarball(root, "aaaaaaa").exists());
+    assert!(tarball(root, "bbbbbbb").exists());
+    assert!(tarball(root, "ccccccc").exists());
+
+    assert!(!cache.contains("github:foo/bar", "aaaaaaa"));
+    assert!(cache.contains("github:foo/bar", "bbbbbbb"));
+    assert!(cache.contains("github:foo/baz", "ccccccc"));
+
+    // The item being written is kept even if it doesn't fit on its own.
+    let mut cache = cache.max_size(Some(4));
+
+    cache
+      .write("github:foo/qux", "main", "ddddddd", b"tarball4")
+      .unwrap();
+
+    assert!(!tarball(root, "bbbbbbb").exists());
+    assert!(!tarball(root, "ccccccc").exists());
+    assert!(tarball(root, "ddddddd").exists());
+
+    let manifest = Manifest::read(root).unwrap();
+
+    assert_eq!(manifest.templates.len(), 1);
+  }
+
+  #[test]
+  fn read_tampered_tarball_as_missing() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let entry = base32::encode(BASE32_ALPHABET, b"github:foo/bar");
+
+    let mut cache = Cache::load(root.to_path_buf()).unwrap();
+
+    cache
+      .write("github:foo/bar", "main", "aaaaaaa", b"tarball")
+      .unwrap();
+
+    assert_eq!(
+      cache.read("github:foo/bar", "aaaaaaa").unwrap().as_deref(),
+      Some(b"tarball".as_slice())
+    );
+
+    fs::write(tarball(root, "aaaaaaa"), b"tampered").unwrap();
+
+    assert_eq!(cache.read("github:foo/bar", "aaaaaaa").unwrap(), None);
+    assert!(!tarball(root, "aaaaaaa").exists());
+
+    let manifest = Manifest::read(root).unwrap();
+
+    assert_eq!(manifest.version, MANIFEST_VERSION);
+    assert_eq!(manifest.templates.get(&entry), None);
+  }
+
+  #[test]
+  fn read_legacy_manifest() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let entry = base32::encode(BASE32_ALPHABET, b"github:foo/bar");
+
+    // Manifests prior to version 1 have neither version nor checksums.
+    let manifest = format!(
+      r#"
+      [[templates.{entry}]]
+      name = "main"
+      hash = "aaaaaaa"
+      timestamp = 0
+      "#
+    );
+
+    fs::write(root.join(CACHE_MANIFEST), manifest).unwrap();
+    fs::create_dir_all(root.join(CACHE_TARBALLS_DIR)).unwrap();
+    fs::write(tarball(root, "aaaaaaa"), b"tarball").unwrap();
+
+    let mut cache = Cache::load(root.to_path_buf()).unwrap();
+
+    assert_eq!(cache.manifest.version, 0);
+    assert_eq!(
+      cache.read("github:foo/bar", "aaaaaaa").unwrap().as_deref(),
+      Some(b"tarball".as_slice())
+    );
+  }
+
+  #[test]
+  fn remove_nothing_on_dry_run() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let entry = base32::encode(BASE32_ALPHABET, b"github:foo/bar");
+
+    let mut cache = cache(
+      root,
+      &[(
+        "github:foo/bar",
+        vec![item("main", "aaaaaaa"), item("dev", "bbbbbbb")],
+      )],
+    );
+
+    fs::create_dir_all(root.join(CACHE_TARBALLS_DIR)).unwrap();
+    fs::write(tarball(root, "aaaaaaa"), b"tarball").unwrap();
+    fs::write(tarball(root, "bbbbbbb"), b"tarball").unwrap();
+
+    cache.remove(vec!["main".to_string()], true).unwrap();
+    cache.remove_all(true).unwrap();
+
+    assert!(tarball(root, "aaaaaaa").exists());
+    assert!(tarball(root, "bbbbbbb").exists());
+
+    let manifest = Manifest::read(root).unwrap();
+
+    assert_eq!(
+      manifest.templates.get(&entry),
+      Some(&vec![item("main", "aaaaaaa"), item("dev", "bbbbbbb")])
+    );
+  }
+
+  #[test]
+  fn remove_quietly() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+
+    let mut cache = cache(
+      root,
+      &[(
+        "github:foo/bar",
+        vec![item("main", "aaaaaaa"), item("dev", "bbbbbbb")],
+      )],
+    );
+
+    fs::create_dir_all(root.join(CACHE_TARBALLS_DIR)).unwrap();
+    fs::write(tarball(root, "aaaaaaa"), b"tarball").unwrap();
+
+    let capture = Capture::start(Verbosity::Normal);
+    cache.remove(vec!["dev".to_string()], true).unwrap();
+    cache.remove(vec!["dev".to_string()], false).unwrap();
+
+    assert_eq!(capture.finish().len(), 5);
+
+    let capture = Capture::start(Verbosity::Quiet);
+    cache.remove(vec!["main".to_string()], true).unwrap();
+    cache.remove(vec!["main".to_string()], false).unwrap();
+
+    assert!(capture.finish().is_empty());
+    assert!(!tarball(root, "aaaaaaa").exists());
+  }
+
+  #[test]
+  fn remove_keeps_entries_that_failed_to_delete() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().to_path_buf();
+    let entry = base32::encode(BASE32_ALPHABET, b"github:foo/bar");
+
+    let mut manifest = Manifest::default();
+
+    manifest.templates.insert(
+      entry.clone(),
+      vec![item("main", "aaaaaaa"), item("dev", "bbbbbbb")],
+    );
+
+    manifest.write(&root).unwrap();
+
+    // A directory in place of a tarball can't be removed with `fs::remove_file`.
+    fs::create_dir_all(tarball(&root, "aaaaaaa")).unwrap();
+    fs::write(tarball(&root, "bbbbbbb"), b"tarball").unwrap();
+
+    let mut cache = Cache::load(root.clone()).unwrap();
+    let result = cache.remove(vec!["github:foo/bar".to_string()], false);
+
+    assert!(result.is_err());
+    assert!(!tarball(&root, "bbbbbbb").exists());
+
+    let manifest = Manifest::read(&root).unwrap();
+
+    assert_eq!(
+      manifest.templates.get(&entry),
+      Some(&vec![item("main", "aaaaaaa")])
+    );
+  }
+
+  #[test]
+  fn concurrent_writes_keep_manifest_valid() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().to_path_buf();
+
+    let writers = ["github:foo/bar", "github:foo/baz"].map(|source| {
+      let root = root.clone();
+
+      thread::spawn(move || {
+        for index in 0..20 {
+          let mut cache = Cache::load(root.clone()).unwrap();
+          let hash = format!("{source}{index:02}").replace([':', '/'], "-");
+
+          cache.write(source, &hash, &hash, b"tarball").unwrap();
+        }
+      })
+    });
+
+    for writer in writers {
+      writer.join().unwrap();
+    }
+
+    let manifest = Manifest::read(&root).unwrap();
+
+    assert_eq!(manifest.templates.len(), 2);
+    assert!(manifest.templates.values().all(|items| items.len() == 20));
+  }
+}
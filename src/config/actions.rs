@@ -1,8 +1,12 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use crate::config::prompts::*;
 
 /// Copies a file or directory. Glob-friendly. Overwrites by default.
+///
+/// ```kdl
+/// cp from="src/**" to="dist" exclude="**/*.tmp **/*.log"
+/// ```
 #[derive(Debug)]
 pub struct Copy {
   /// Source(s) to copy.
@@ -11,6 +15,8 @@ pub struct Copy {
   pub to: String,
   /// Whether to overwrite or not. Defaults to `true`.
   pub overwrite: bool,
+  /// Glob patterns of entries to skip, along with their contents.
+  pub exclude: Vec<String>,
 }
 
 /// Moves a file or directory. Glob-friendly. Overwrites by default.
@@ -22,6 +28,8 @@ pub struct Move {
   pub to: String,
   /// Whether to overwrite or not. Defaults to `true`.
   pub overwrite: bool,
+  /// Glob patterns of entries to skip, along with their contents.
+  pub exclude: Vec<String>,
 }
 
 /// Deletes a file or directory. Glob-friendly.
@@ -31,6 +39,90 @@ pub struct Delete {
   pub target: String,
 }
 
+/// Creates a directory, along with any missing parents. Useful for empty directories, which aren't
+/// preserved by archives.
+#[derive(Debug)]
+pub struct MakeDir {
+  /// Directory to create.
+  pub target: String,
+}
+
+/// Creates a symlink at `to` pointing to `from`, both relative to the scaffold root. The link
+/// stores a relative path, so the scaffolded project can be moved around. Falls back to copying if
+/// symlinks aren't supported. Doesn't overwrite by default.
+///
+/// ```kdl
+/// symlink from="shared/.editorconfig" to="app/.editorconfig"
+/// ```
+#[derive(Debug)]
+pub struct Symlink {
+  /// File or directory to link to.
+  pub from: String,
+  /// Where to create the link.
+  pub to: String,
+  /// Whether to replace an existing file or link at `to`. Defaults to `false`.
+  pub overwrite: bool,
+}
+
+/// Downloads a file from a URL. Existing files are overwritten.
+///
+/// ```kdl
+/// download url="https://example.com/LICENSE" to="LICENSE"
+/// ```
+#[derive(Debug)]
+pub struct Download {
+  /// URL to download from.
+  pub url: String,
+  /// Where to write the response body to.
+  pub to: String,
+}
+
+/// Writes a file with contents defined inline. Overwrites by default.
+///
+/// ```kdl
+/// write to=".env" {
+///   content r#"
+///     NAME={repo_name}
+///   "#
+///   inject "repo_name"
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Write {
+  /// Where to write to.
+  pub to: String,
+  /// Contents of the file. Raw strings are trimmed and aligned to the leftmost non-whitespace
+  /// character. Non-empty contents always end with a newline.
+  pub content: String,
+  /// An optional list of placeholders to be injected into the contents.
+  pub injects: Option<HashSet<String>>,
+  /// Whether to overwrite or not. Defaults to `true`.
+  pub overwrite: bool,
+}
+
+/// Renders a template file with prompts' values and writes the result. Overwrites by default.
+///
+/// ```kdl
+/// template from=".template/README.md" to="README.md"
+/// ```
+#[derive(Debug)]
+pub struct Template {
+  /// Template file to render.
+  pub from: String,
+  /// Where to write the rendered file to.
+  pub to: String,
+  /// Engine used for rendering. Defaults to [TemplateEngine::Simple].
+  pub engine: TemplateEngine,
+}
+
+/// Template engines supported by the [Template] action.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum TemplateEngine {
+  /// Substitutes `{{ name }}` placeholders with prompts' values. Undefined names are errors.
+  #[default]
+  Simple,
+}
+
 /// Echoes a message to stdout.
 #[derive(Debug)]
 pub struct Echo {
@@ -72,6 +164,32 @@ pub struct Run {
   ///
   /// All placeholders are processed _before_ running a command.
   pub injects: Option<HashSet<String>>,
+  /// Optional working directory, relative to the scaffold root. Defaults to the root itself.
+  pub cwd: Option<String>,
+  /// Environment variables to set for the command. Prompts' values are always exposed as well,
+  /// as `DECAFF_<NAME>` variables, but explicitly set ones take precedence.
+  ///
+  /// ```kdl
+  /// run "npm publish" {
+  ///   env {
+  ///     NODE_ENV "production"
+  ///   }
+  /// }
+  /// ```
+  pub env: HashMap<String, String>,
+  /// Whether to report a failure as a warning and proceed instead of bailing. Defaults to `false`.
+  pub continue_on_error: bool,
+}
+
+/// Initializes a git repository in the scaffold root. Does nothing if it's already a repository.
+///
+/// ```kdl
+/// git-init initial_branch="main"
+/// ```
+#[derive(Debug)]
+pub struct GitInit {
+  /// Optional name of the initial branch. Defaults to git's own default.
+  pub initial_branch: Option<String>,
 }
 
 /// Prompt actions.
@@ -80,20 +198,114 @@ pub enum Prompt {
   Input(InputPrompt),
   Number(NumberPrompt),
   Select(SelectPrompt),
+  MultiSelect(MultiSelectPrompt),
   Confirm(ConfirmPrompt),
   Editor(EditorPrompt),
+  Password(PasswordPrompt),
+  Path(PathPrompt),
 }
 
-/// Execute given replacements using values provided by prompts. Optionally, only apply
-/// replacements to files matching the provided glob.
+impl Prompt {
+  /// Returns the name the prompt's answer is stored under.
+  pub fn name(&self) -> &str {
+    match self {
+      | Self::Input(prompt) => &prompt.name,
+      | Self::Number(prompt) => &prompt.name,
+      | Self::Select(prompt) => &prompt.name,
+      | Self::MultiSelect(prompt) => &prompt.name,
+      | Self::Confirm(prompt) => &prompt.name,
+      | Self::Editor(prompt) => &prompt.name,
+      | Self::Password(prompt) => &prompt.name,
+      | Self::Path(prompt) => &prompt.name,
+    }
+  }
+
+  /// Returns the condition to ask the prompt: the name of a previous answer, optionally prefixed
+  /// with `!` to negate it. The prompt is skipped, leaving its answer unset, if the answer is
+  /// missing or falsy, i.e. `false`, `0`, an empty string or an empty list.
+  ///
+  /// ```kdl
+  /// input "database_url" when="use_database" {
+  ///   hint "Database URL"
+  /// }
+  /// ```
+  pub fn when(&self) -> Option<&str> {
+    match self {
+      | Self::Input(prompt) => prompt.when.as_deref(),
+      | Self::Number(prompt) => prompt.when.as_deref(),
+      | Self::Select(prompt) => prompt.when.as_deref(),
+      | Self::MultiSelect(prompt) => prompt.when.as_deref(),
+      | Self::Confirm(prompt) => prompt.when.as_deref(),
+      | Self::Editor(prompt) => prompt.when.as_deref(),
+      | Self::Password(prompt) => prompt.when.as_deref(),
+      | Self::Path(prompt) => prompt.when.as_deref(),
+    }
+  }
+}
+
+/// Execute given replacements using values provided by prompts, both in contents and names of files
+/// and directories. Optionally, only apply replacements to files matching the provided glob.
+///
+/// ```kdl
+/// replace in=".template/**" {
+///   "repo_name"
+///   "repo_license" default="MIT"
+///   "author_name" files="LICENSE README.md"
+/// }
+/// ```
 #[derive(Debug)]
 pub struct Replace {
-  /// Replacements to apply.
-  pub replacements: HashSet<String>,
+  /// Replacements to apply, in order of declaration.
+  pub replacements: Vec<Replacement>,
   /// Optional glob to limit files to apply replacements to.
   pub glob: Option<String>,
 }
 
+/// A single replacement of the [Replace] action.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Replacement {
+  /// Name of the value to substitute, i.e. the prompt name.
+  pub name: String,
+  /// Value to use if no prompt provided one.
+  pub default: Option<String>,
+  /// Optional glob patterns to further limit files this replacement applies to.
+  pub files: Option<Vec<String>>,
+}
+
+/// Replaces every match of a pattern inside files matching a glob. Binary files are skipped.
+///
+/// ```kdl
+/// replace-in-file "src/**/*.rs" pattern=r"0\.1\.0" replacement="1.0.0" regex=true
+/// ```
+#[derive(Debug)]
+pub struct ReplaceInFile {
+  /// Glob pattern to match files to apply the replacement to.
+  pub target: String,
+  /// Literal text or a regular expression to look for.
+  pub pattern: String,
+  /// Text to replace matches with. Regular expressions can refer to capture groups, e.g. `$1`.
+  pub replacement: String,
+  /// Whether the pattern is a regular expression. Defaults to `false`.
+  pub regex: bool,
+}
+
+/// Renames files or directories matching a glob in place, i.e. the new name is resolved relative to
+/// the parent directory of each matched entry. Applied right after unpacking, before any actions.
+///
+/// ```kdl
+/// renames {
+///   "gitignore" ".gitignore"
+///   "**/_env" ".env"
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Rename {
+  /// Glob pattern to match entries to rename.
+  pub from: String,
+  /// New name, without path separators.
+  pub to: String,
+}
+
 /// Fallback action for pattern matching ergonomics and reporting purposes.
 #[derive(Debug)]
 pub struct Unknown {
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use kdl::{KdlDocument, KdlValue};
+use miette::Diagnostic;
+use thiserror::Error;
+
+use crate::config::{Number, Value};
+
+#[derive(Debug, Diagnostic, Error)]
+pub enum AnswersError {
+  #[error("{message}")]
+  #[diagnostic(code(decaff::config::answers::io))]
+  Io {
+    message: String,
+    #[source]
+    source: io::Error,
+  },
+  #[error(transparent)]
+  #[diagnostic(transparent)]
+  Kdl(kdl::KdlError),
+  #[error("Failed to parse answers file.")]
+  #[diagnostic(code(decaff::config::answers::json))]
+  Json(#[source] serde_json::Error),
+  #[error("Unsupported value of answer `{name}`.")]
+  #[diagnostic(
+    code(decaff::config::answers::value),
+    help("Answers should be strings, numbers, booleans or lists of strings.")
+  )]
+  Value { name: String },
+}
+
+/// Answers to prompts, keyed by prompt name. Used to run prompts non-interactively.
+///
+/// Answers are loaded either from a JSON file with an object at the top level:
+///
+/// ```json
+/// { "repo_name": "serpent", "repo_port": 3000, "repo_features": ["eslint", "vitest"] }
+/// ```
+///
+/// Or from a KDL file with a node per answer, where multiple arguments form a list:
+///
+/// ```kdl
+/// repo_name "serpent"
+/// repo_port 3000
+/// repo_features "eslint" "vitest"
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Answers {
+  values: HashMap<String, Value>,
+}
+
+impl Answers {
+  /// Loads answers from the given file. Files with the `.json` extension are parsed as JSON, all
+  /// other files are parsed as KDL.
+  pub fn load(path: &Path) -> Result<Self, AnswersError> {
+    let contents = fs::read_to_string(path).map_err(|source| {
+      AnswersError::Io {
+        message: format!("Failed to read answers from '{}'.", path.display()),
+        source,
+      }
+    })?;
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+      | Some("json") => Self::from_json(&contents),
+      | _ => Self::from_kdl(&contents),
+    }
+  }
+
+  /// Gets an answer by prompt name.
+  pub fn get(&self, name: &str) -> Option<&Value> {
+    self.values.get(name)
+  }
+
+  fn from_json(contents: &str) -> Result<Self, AnswersError> {
+    let object: HashMap<String, serde_json::Value> =
+      serde_json::from_str(contents).map_err(AnswersError::Json)?;
+
+    let values = object
+      .into_iter()
+      .map(|(name, value)| {
+        let value = match value {
+          | serde_json::Value::String(string) => Some(Value::String(string)),
+          | serde_json::Value::Bool(boolean) => Some(Value::Bool(boolean)),
+          | serde_json::Value::Number(number) => {
+            number
+              .as_i64()
+              .map(Number::Integer)
+              .or_else(|| number.as_f64().map(Number::Float))
+              .map(Value::Number)
+          },
+          | serde_json::Value::Array(items) => {
+            items
+              .into_iter()
+              .map(|item| item.as_str().map(str::to_string))
+              .collect::<Option<Vec<_>>>()
+              .map(Value::Array)
+          },
+          | _ => None,
+        };
+
+        match value {
+          | Some(value) => Ok((name, value)),
+          | None => Err(AnswersError::Value { name }),
+        }
+      })
+      .collect::<Result<_, _>>()?;
+
+    Ok(Self { values })
+  }
+
+  fn from_kdl(contents: &str) -> Result<Self, AnswersError> {
+    let doc: KdlDocument = contents.parse().map_err(AnswersError::Kdl)?;
+
+    let values = doc
+      .nodes()
+      .iter()
+      .map(|node| {
+        let name = node.name().value().to_string();
+
+        let args = node
+          .entries()
+          .iter()
+          .filter(|entry| entry.name().is_none())
+          .map(|entry| entry.value())
+          .collect::<Vec<_>>();
+
+        let value = match &args[..] {
+          | [value] => scalar(value),
+          | [] => None,
+          | values => {
+            values
+              .iter()
+              .map(|value| value.as_string().map(str::to_string))
+              .collect::<Option<Vec<_>>>()
+              .map(Value::Array)
+          },
+        };
+
+        match value {
+          | Some(value) => Ok((name, value)),
+          | None => Err(AnswersError::Value { name }),
+        }
+      })
+      .collect::<Result<_, _>>()?;
+
+    Ok(Self { values })
+  }
+}
+
+impl<N: Into<String>> FromIterator<(N, Value)> for Answers {
+  fn from_iter<I: IntoIterator<Item = (N, Value)>>(iter: I) -> Self {
+    Self {
+      values: iter
+        .into_iter()
+        .map(|(name, value)| (name.into(), value))
+        .collect(),
+    }
+  }
+}
+
+/// Maps a single KDL value to a [Value].
+fn scalar(value: &KdlValue) -> Option<Value> {
+  if let Some(string) = value.as_string() {
+    Some(Value::String(string.to_string()))
+  } else if let Some(boolean) = value.as_bool() {
+    Some(Value::Bool(boolean))
+  } else if let Some(int) = value.as_i64() {
+    Some(Value::Number(Number::Integer(int)))
+  } else {
+    value
+      .as_f64()
+      .map(|float| Value::Number(Number::Float(float)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use tempfile::TempDir;
+
+  use super::*;
+
+  #[test]
+  fn load_json() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("answers.json");
+
+    fs::write(
+      &path,
+      r#"{ "name": "serpent", "port": 3000, "ratio": 0.5, "commit": true, "features": ["eslint"] }"#,
+    )
+    .unwrap();
+
+    let answers = Answers::load(&path).unwrap();
+
+    assert!(matches!(answers.get("name"), Some(Value::String(name)) if name == "serpent"));
+    assert!(matches!(
+      answers.get("port"),
+      Some(Value::Number(Number::Integer(3000)))
+    ));
+    assert!(matches!(
+      answers.get("ratio"),
+      Some(Value::Number(Number::Float(_)))
+    ));
+    assert!(matches!(answers.get("commit"), Some(Value::Bool(true))));
+    assert!(
+      matches!(answers.get("features"), Some(Value::Array(features)) if features == &["eslint"])
+    );
+    assert!(answers.get("missing").is_none());
+
+    fs::write(&path, r#"{ "nested": { "name": "serpent" } }"#).unwrap();
+
+    assert!(matches!(
+      Answers::load(&path),
+      Err(AnswersError::Value { name }) if name == "nested"
+    ));
+  }
+
+  #[test]
+  fn load_kdl() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("answers.kdl");
+
+    fs::write(
+      &path,
+      r#"
+      name "serpent"
+      port 3000
+      commit false
+      features "eslint" "vitest"
+      "#,
+    )
+    .unwrap();
+
+    let answers = Answers::load(&path).unwrap();
+
+    assert!(matches!(answers.get("name"), Some(Value::String(name)) if name == "serpent"));
+    assert!(matches!(
+      answers.get("port"),
+      Some(Value::Number(Number::Integer(3000)))
+    ));
+    assert!(matches!(answers.get("commit"), Some(Value::Bool(false))));
+    assert!(
+      matches!(answers.get("features"), Some(Value::Array(features)) if features == &["eslint", "vitest"])
+    );
+
+    fs::write(&path, "empty").unwrap();
+
+    assert!(matches!(
+      Answers::load(&path),
+      Err(AnswersError::Value { name }) if name == "empty"
+    ));
+  }
+}
@@ -1,20 +1,29 @@
-use std::collections::HashSet;
-use std::fs;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use kdl::{KdlDocument, KdlNode};
 use miette::{Diagnostic, LabeledSpan, NamedSource, Report};
+use regex::Regex;
 use thiserror::Error;
 
 use crate::config::actions::*;
 use crate::config::prompts::*;
 use crate::config::value::*;
 use crate::config::KdlUtils;
+use crate::path::PathClean;
+use crate::vfs::{Filesystem, RealFs};
 
 const CONFIG_NAME: &str = "decaff.kdl";
 
+/// Environment variable with the config filename, overriding the default.
+const CONFIG_VAR: &str = "DECAFF_CONFIG";
+
+/// Starter config with commented examples, written by `decaff init`.
+pub const STARTER_CONFIG: &str = include_str!("starter.kdl");
+
 /// Helper macro to create a [ConfigError::Diagnostic] in a slightly less verbose way.
 macro_rules! diagnostic {
   ($source:ident = $code:expr, $($key:ident = $value:expr,)* $fmt:literal $($arg:tt)*) => {
@@ -94,6 +103,17 @@ pub enum Actions {
   Empty,
 }
 
+impl Actions {
+  /// Returns all single actions, including those in suites, in order of declaration.
+  pub fn singles(&self) -> Vec<&ActionSingle> {
+    match self {
+      | Actions::Suite(suites) => suites.iter().flat_map(|suite| &suite.actions).collect(),
+      | Actions::Flat(actions) => actions.iter().collect(),
+      | Actions::Empty => Vec::new(),
+    }
+  }
+}
+
 /// A suite of actions that contains a flat list of [ActionSingle].
 #[derive(Debug)]
 pub struct ActionSuite {
@@ -112,15 +132,29 @@ pub enum ActionSingle {
   Move(Move),
   /// Deletes a file or directory. Glob-friendly.
   Delete(Delete),
+  /// Creates a directory, along with any missing parents.
+  MakeDir(MakeDir),
+  /// Creates a symlink to a file or directory.
+  Symlink(Symlink),
+  /// Writes a file with contents defined inline. Overwrites by default.
+  Write(Write),
+  /// Downloads a file from a URL.
+  Download(Download),
+  /// Renders a template file with prompts' values.
+  Template(Template),
   /// Echoes a message to stdout.
   Echo(Echo),
   /// Runs an arbitrary command in the shell.
   Run(Run),
+  /// Initializes a git repository in the scaffold root.
+  GitInit(GitInit),
   /// Executes a prompt asking a declaratively defined "question".
   Prompt(Prompt),
   /// Execute given replacements using values provided by prompts. Optionally, only apply
   /// replacements to files matching the provided glob.
   Replace(Replace),
+  /// Replaces every match of a literal or regex pattern inside files matching a glob.
+  ReplaceInFile(ReplaceInFile),
   /// Fallback action for pattern matching ergonomics and reporting purposes.
   Unknown(Unknown),
 }
@@ -134,8 +168,12 @@ pub struct Config {
   pub source: Arc<NamedSource>,
   /// Config file path.
   pub config: PathBuf,
+  /// Paths of configs imported via `import` nodes, directly or transitively.
+  pub imports: Vec<PathBuf>,
   /// Config options.
   pub options: ConfigOptions,
+  /// Renames to apply before running actions.
+  pub renames: Vec<Rename>,
   /// Actions.
   pub actions: Actions,
 }
@@ -156,13 +194,36 @@ impl Config {
 
     Self {
       config,
+      imports: Vec::new(),
       options: ConfigOptions::default(),
+      renames: Vec::new(),
       actions: Actions::Empty,
       source,
       root,
     }
   }
 
+  /// Sets the name of the config file to read instead of `decaff.kdl`.
+  pub fn name(mut self, name: &str) -> Self {
+    self.config = self.root.join(name);
+    self.source = Arc::new(NamedSource::new(
+      self.config.display().to_string(),
+      String::default(),
+    ));
+    self
+  }
+
+  /// Resolves the config filename: the given name if any, otherwise the one from `DECAFF_CONFIG`,
+  /// falling back to `decaff.kdl`.
+  pub fn resolve_name(name: Option<String>) -> String {
+    let is_set = |name: &String| !name.trim().is_empty();
+
+    name
+      .filter(is_set)
+      .or_else(|| env::var(CONFIG_VAR).ok().filter(is_set))
+      .unwrap_or_else(|| CONFIG_NAME.to_string())
+  }
+
   /// Tries to apply the given overrides to the config options.
   pub fn override_with(&mut self, overrides: ConfigOptionsOverrides) {
     if let Some(delete) = overrides.delete {
@@ -172,10 +233,15 @@ impl Config {
 
   /// Tries to load and parse the config.
   pub fn load(&mut self) -> Result<bool, ConfigError> {
-    if self.exists() {
-      let doc = self.parse()?;
+    self.load_from(&RealFs)
+  }
+
+  /// Tries to load and parse the config from the given filesystem.
+  pub fn load_from(&mut self, fs: &dyn Filesystem) -> Result<bool, ConfigError> {
+    if fs.exists(&self.config) {
+      let doc = self.parse(fs)?;
       self.options = self.get_config_options(&doc)?;
-      self.actions = self.get_config_actions(&doc)?;
+      self.load_document(fs, &doc, &mut vec![self.config.clean()])?;
 
       Ok(true)
     } else {
@@ -183,26 +249,218 @@ impl Config {
     }
   }
 
-  /// Checks if the config exists under `self.root`.
-  fn exists(&self) -> bool {
-    self.config.try_exists().unwrap_or(false)
-  }
+  /// Loads renames and actions from the parsed document, preceded by the ones from configs imported
+  /// via `import "path"` nodes, in order. Import paths are relative to the importing config and
+  /// must stay within the root, and `chain` holds configs currently being imported to detect
+  /// cycles. Options of imported configs are ignored. Suite and prompt names must be unique across
+  /// imported configs, same as within a single one.
+  fn load_document(
+    &mut self,
+    fs: &dyn Filesystem,
+    doc: &KdlDocument,
+    chain: &mut Vec<PathBuf>,
+  ) -> Result<(), ConfigError> {
+    let mut renames = Vec::new();
+    let mut actions = Actions::Empty;
+    let mut origins: HashMap<(&str, String), &KdlNode> = HashMap::new();
+
+    let base = self
+      .config
+      .parent()
+      .map(Path::to_path_buf)
+      .unwrap_or_default();
+
+    for node in doc
+      .nodes()
+      .iter()
+      .filter(|node| node.name().value() == "import")
+    {
+      let import = self.get_arg_string(node)?;
+      let path = base.join(&import).clean();
 
-  /// Reads and parses the config into a [KdlDocument].
-  fn parse(&mut self) -> Result<KdlDocument, ConfigError> {
-    let filename = self.root.join(CONFIG_NAME);
+      if Path::new(&import).is_absolute() || !is_within(&path, &self.root.clean()) {
+        return Err(diagnostic!(
+          source = &self.source,
+          code = "decaff::config::import",
+          labels = vec![LabeledSpan::at(
+            node.span().to_owned(),
+            "this import points outside of the template"
+          )],
+          help = "Use relative paths that stay within the template.",
+          "Imported config is outside of the template: '{import}'."
+        ));
+      }
 
-    let contents = fs::read_to_string(&filename).map_err(|source| {
-      ConfigError::Io {
-        message: "Failed to read the config.".to_string(),
-        source,
+      if chain.contains(&path) {
+        let cycle = chain
+          .iter()
+          .chain([&path])
+          .map(|path| self.display_path(path))
+          .collect::<Vec<_>>()
+          .join(" → ");
+
+        return Err(diagnostic!(
+          source = &self.source,
+          code = "decaff::config::import",
+          labels = vec![LabeledSpan::at(
+            node.span().to_owned(),
+            "this import creates a cycle"
+          )],
+          "Import cycle detected: {cycle}."
+        ));
       }
-    })?;
+
+      if !fs.is_file(&path) {
+        return Err(diagnostic!(
+          source = &self.source,
+          code = "decaff::config::import",
+          labels = vec![LabeledSpan::at(
+            node.span().to_owned(),
+            "this config doesn't exist"
+          )],
+          "Imported config not found: '{}'.",
+          self.display_path(&path)
+        ));
+      }
+
+      let mut imported = Config::new(&self.root);
+      imported.config = path.clone();
+
+      let imported_doc = imported.parse(fs)?;
+
+      chain.push(path.clone());
+      imported.load_document(fs, &imported_doc, chain)?;
+      chain.pop();
+
+      for key in unique_names(&imported.actions) {
+        if let Some(first) = origins.insert(key.clone(), node) {
+          return Err(self.duplicate_name(&key, (first, "first imported here"), node));
+        }
+      }
+
+      self.imports.push(path);
+      self.imports.extend(imported.imports);
+      renames.extend(imported.renames);
+
+      actions =
+        Self::merge_actions(actions, imported.actions).ok_or_else(|| self.mixed_imports(node))?;
+    }
+
+    renames.extend(self.get_config_renames(doc)?);
+
+    let own = self.get_config_actions(doc)?;
+
+    for key in unique_names(&own) {
+      if let Some(first) = origins.get(&key) {
+        let (kind, name) = &key;
+
+        // Point at the redefining node, falling back to the whole actions block.
+        let redefined = doc
+          .get("actions")
+          .and_then(KdlNode::children)
+          .and_then(|children| {
+            children.nodes().iter().find(|node| {
+              (node.name().value() == "suite") == (*kind == "suite")
+                && node.get_string(0).as_deref() == Some(name)
+            })
+          })
+          .or_else(|| doc.get("actions"))
+          .expect("Own actions should exist");
+
+        return Err(self.duplicate_name(&key, (first, "imported here"), redefined));
+      }
+    }
+
+    self.renames = renames;
+    self.actions = Self::merge_actions(actions, own)
+      .ok_or_else(|| self.mixed_imports(doc.get("actions").expect("Own actions should exist")))?;
+
+    Ok(())
+  }
+
+  /// Appends actions to the already collected ones. Returns `None` if one has suites and the other
+  /// has a flat list.
+  fn merge_actions(left: Actions, right: Actions) -> Option<Actions> {
+    match (left, right) {
+      | (Actions::Empty, actions) | (actions, Actions::Empty) => Some(actions),
+      | (Actions::Suite(mut left), Actions::Suite(right)) => {
+        left.extend(right);
+        Some(Actions::Suite(left))
+      },
+      | (Actions::Flat(mut left), Actions::Flat(right)) => {
+        left.extend(right);
+        Some(Actions::Flat(left))
+      },
+      | _ => None,
+    }
+  }
+
+  /// Reports a suite or prompt name defined both in an imported config and elsewhere.
+  fn duplicate_name(
+    &self,
+    (kind, name): &(&str, String),
+    (first, label): (&KdlNode, &str),
+    node: &KdlNode,
+  ) -> ConfigError {
+    diagnostic!(
+      source = &self.source,
+      code = "decaff::config::import",
+      labels = vec![
+        LabeledSpan::at(first.span().to_owned(), label),
+        LabeledSpan::at(node.span().to_owned(), "redefined here"),
+      ],
+      help = if *kind == "suite" {
+        "Suite names must be unique across imported configs."
+      } else {
+        "Prompt names must be unique across imported configs."
+      },
+      "Duplicate {kind} name: `{name}`."
+    )
+  }
+
+  fn mixed_imports(&self, node: &KdlNode) -> ConfigError {
+    diagnostic!(
+      source = &self.source,
+      code = "decaff::config::import",
+      labels = vec![LabeledSpan::at(
+        node.span().to_owned(),
+        "these actions don't match the imported ones"
+      )],
+      help = "All imported configs must use either suites of actions or a flat list of actions.",
+      "Imported configs mix suites and flat lists of actions."
+    )
+  }
+
+  /// Returns the path relative to the root for display purposes.
+  fn display_path(&self, path: &Path) -> String {
+    path
+      .strip_prefix(&self.root)
+      .unwrap_or(path)
+      .display()
+      .to_string()
+  }
+
+  /// Reads and parses the config into a [KdlDocument].
+  fn parse(&mut self, fs: &dyn Filesystem) -> Result<KdlDocument, ConfigError> {
+    let contents = fs
+      .read(&self.config)
+      .and_then(|bytes| {
+        String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+      })
+      .map_err(|source| {
+        ConfigError::Io {
+          message: "Failed to read the config.".to_string(),
+          source,
+        }
+      })?;
 
     let document = contents.parse().map_err(ConfigError::Kdl)?;
 
     // Replace dummy source with actual data.
-    self.source = Arc::new(NamedSource::new(filename.display().to_string(), contents));
+    self.source = Arc::new(NamedSource::new(
+      self.config.display().to_string(),
+      contents,
+    ));
 
     Ok(document)
   }
@@ -249,6 +507,37 @@ impl Config {
     }
   }
 
+  /// Tries to parse renames from the config.
+  fn get_config_renames(&self, doc: &KdlDocument) -> Result<Vec<Rename>, ConfigError> {
+    let mut renames = Vec::new();
+
+    if let Some(children) = doc.get("renames").and_then(KdlNode::children) {
+      for node in children.nodes() {
+        let to = self.get_arg_string(node)?;
+
+        if matches!(to.as_str(), "" | "." | "..") || to.contains(['/', '\\']) {
+          return Err(diagnostic!(
+            source = &self.source,
+            code = "decaff::config::renames",
+            labels = vec![LabeledSpan::at(
+              node.span().to_owned(),
+              "this is not a plain file or directory name"
+            )],
+            help = "Entries are renamed in place, so the new name can't contain path separators.",
+            "Invalid new name: '{to}'."
+          ));
+        }
+
+        renames.push(Rename {
+          from: node.name().value().to_string(),
+          to,
+        });
+      }
+    }
+
+    Ok(renames)
+  }
+
   /// Tries to parse actions from the config.
   fn get_config_actions(&self, doc: &KdlDocument) -> Result<Actions, ConfigError> {
     #[inline]
@@ -270,9 +559,25 @@ impl Config {
         // Check if all nodes are suites.
         if nodes.iter().all(is_suite) {
           let mut suites = Vec::new();
+          let mut names = HashMap::new();
 
           for node in nodes.iter() {
             let suite = self.get_action_suite(node)?;
+
+            if let Some(first) = names.insert(suite.name.clone(), node) {
+              return Err(diagnostic!(
+                source = &self.source,
+                code = "decaff::config::actions",
+                labels = vec![
+                  LabeledSpan::at(first.span().to_owned(), "first defined here"),
+                  LabeledSpan::at(node.span().to_owned(), "redefined here"),
+                ],
+                help = "Suite names must be unique.",
+                "Duplicate suite name: `{}`.",
+                suite.name
+              ));
+            }
+
             suites.push(suite);
           }
 
@@ -282,20 +587,40 @@ impl Config {
         else if nodes.iter().all(is_flat) {
           let mut actions = Vec::new();
 
+          let mut names = HashSet::new();
+
           for node in nodes.iter() {
             let action = self.get_action_single(node)?;
+            self.check_prompt_name(node, &action, &mut names)?;
             actions.push(action);
           }
 
           Ok(Actions::Flat(actions))
         }
-        // Otherwise we have invalid actions block.
+        // Otherwise we have invalid actions block, so point at the first node that doesn't match
+        // the kind of the very first one.
         else {
-          Err(ConfigError::Diagnostic(miette::miette!(
+          let expected = nodes.first().is_some_and(is_suite);
+
+          let offending = nodes
+            .iter()
+            .find(|node| is_suite(node) != expected)
+            .expect("Mixed actions should contain a node of another kind");
+
+          let label = if expected {
+            "expected a `suite`, found a single action"
+          } else {
+            "expected a single action, found a `suite`"
+          };
+
+          Err(diagnostic!(
+            source = &self.source,
             code = "decaff::config::actions",
+            labels = vec![LabeledSpan::at(offending.span().to_owned(), label)],
+            help = "Move single actions into suites, or unwrap suites into a flat list.",
             "You can use either suites of actions or a flat list of single actions. \
              Right now you have a mix of both."
-          )))
+          ))
         }
       });
 
@@ -313,8 +638,11 @@ impl Config {
     let name = self.get_arg_string(node)?;
 
     if let Some(children) = node.children() {
+      let mut names = HashSet::new();
+
       for children in children.nodes() {
         let action = self.get_action_single(children)?;
+        self.check_prompt_name(children, &action, &mut names)?;
         actions.push(action);
       }
     }
@@ -332,6 +660,7 @@ impl Config {
           from: self.get_attr_string(node, "from")?,
           to: self.get_attr_string(node, "to")?,
           overwrite: node.get_bool("overwrite").unwrap_or(true),
+          exclude: self.get_exclude(node),
         })
       },
       | "mv" => {
@@ -339,11 +668,55 @@ impl Config {
           from: self.get_attr_string(node, "from")?,
           to: self.get_attr_string(node, "to")?,
           overwrite: node.get_bool("overwrite").unwrap_or(true),
+          exclude: self.get_exclude(node),
         })
       },
       | "rm" => ActionSingle::Delete(Delete { target: self.get_arg_string(node)? }),
+      | "mkdir" => ActionSingle::MakeDir(MakeDir { target: self.get_arg_string(node)? }),
+      | "symlink" => {
+        ActionSingle::Symlink(Symlink {
+          from: self.get_attr_string(node, "from")?,
+          to: self.get_attr_string(node, "to")?,
+          overwrite: node.get_bool("overwrite").unwrap_or(false),
+        })
+      },
+      | "write" => {
+        let nodes = self.get_children(node, vec!["content"])?;
+
+        let content = nodes.get("content").ok_or_else(|| {
+          diagnostic!(
+            source = &self.source,
+            code = "decaff::config::actions",
+            labels = vec![LabeledSpan::at(
+              node.span().to_owned(),
+              "this node requires the `content` child node"
+            )],
+            "Missing required child node: `content`."
+          )
+        })?;
+
+        ActionSingle::Write(Write {
+          to: self.get_attr_string(node, "to")?,
+          content: self.get_arg_string(content)?,
+          injects: self.get_injects(node),
+          overwrite: node.get_bool("overwrite").unwrap_or(true),
+        })
+      },
+      | "download" => {
+        ActionSingle::Download(Download {
+          url: self.get_attr_string(node, "url")?,
+          to: self.get_attr_string(node, "to")?,
+        })
+      },
+      | "template" => {
+        ActionSingle::Template(Template {
+          from: self.get_attr_string(node, "from")?,
+          to: self.get_attr_string(node, "to")?,
+          engine: self.get_template_engine(node)?,
+        })
+      },
       // Actions for running commands and echoing output.
-      | "echo" => {
+      | "echo" | "print" => {
         ActionSingle::Echo(Echo {
           message: self.get_arg_string(node)?,
           injects: self.get_injects(node),
@@ -355,6 +728,14 @@ impl Config {
           name: node.get_string("name"),
           command: self.get_arg_string(node)?,
           injects: self.get_injects(node),
+          cwd: node.get_string("cwd"),
+          env: self.get_env(node),
+          continue_on_error: node.get_bool("continue_on_error").unwrap_or(false),
+        })
+      },
+      | "git-init" => {
+        ActionSingle::GitInit(GitInit {
+          initial_branch: node.get_string("initial_branch"),
         })
       },
       // Actions for prompts and replacements.
@@ -364,7 +745,13 @@ impl Config {
         ActionSingle::Prompt(Prompt::Input(InputPrompt {
           name: self.get_arg_string(node)?,
           hint: self.get_hint(node, nodes)?,
+          when: node.get_string("when"),
           default: self.get_default_string(nodes),
+          validate: self.get_regex(node, "validate")?,
+          min_length: node.get_usize("min_length"),
+          max_length: node.get_usize("max_length"),
+          transform: self.get_transform(node)?,
+          suggestions: self.get_suggestions(nodes),
         }))
       },
       | "number" => {
@@ -373,7 +760,11 @@ impl Config {
         ActionSingle::Prompt(Prompt::Number(NumberPrompt {
           name: self.get_arg_string(node)?,
           hint: self.get_hint(node, nodes)?,
+          when: node.get_string("when"),
           default: self.get_default_number(nodes),
+          integer: node.get_bool("integer").unwrap_or(false),
+          min: node.get_number("min"),
+          max: node.get_number("max"),
         }))
       },
       | "editor" => {
@@ -382,16 +773,80 @@ impl Config {
         ActionSingle::Prompt(Prompt::Editor(EditorPrompt {
           name: self.get_arg_string(node)?,
           hint: self.get_hint(node, nodes)?,
+          when: node.get_string("when"),
           default: self.get_default_string(nodes),
+          extension: node.get_string("extension").map(|extension| {
+            if extension.starts_with('.') {
+              extension
+            } else {
+              format!(".{extension}")
+            }
+          }),
         }))
       },
       | "select" => {
         let nodes = self.get_children(node, vec!["hint", "options"])?;
 
+        let options = self.get_options(node, nodes)?;
+        let default = self.get_default_string(nodes);
+
+        if let Some(default) = default
+          .as_ref()
+          .filter(|default| !options.contains(default))
+        {
+          let span = nodes.get("default").map_or(node.span(), KdlNode::span);
+
+          return Err(diagnostic!(
+            source = &self.source,
+            code = "decaff::config::actions",
+            labels = vec![LabeledSpan::at(
+              span.to_owned(),
+              "default should be one of the options"
+            )],
+            "Unknown default option: `{default}`."
+          ));
+        }
+
         ActionSingle::Prompt(Prompt::Select(SelectPrompt {
           name: self.get_arg_string(node)?,
           hint: self.get_hint(node, nodes)?,
+          when: node.get_string("when"),
+          options,
+          default,
+          transform: self.get_transform(node)?,
+        }))
+      },
+      | "multiselect" => {
+        let nodes = self.get_children(node, vec!["hint", "options"])?;
+
+        ActionSingle::Prompt(Prompt::MultiSelect(MultiSelectPrompt {
+          name: self.get_arg_string(node)?,
+          hint: self.get_hint(node, nodes)?,
+          when: node.get_string("when"),
           options: self.get_options(node, nodes)?,
+          default: self.get_default_strings(nodes),
+        }))
+      },
+      | "password" => {
+        let nodes = self.get_children(node, vec!["hint"])?;
+
+        ActionSingle::Prompt(Prompt::Password(PasswordPrompt {
+          name: self.get_arg_string(node)?,
+          hint: self.get_hint(node, nodes)?,
+          when: node.get_string("when"),
+          confirm: node.get_bool("confirm").unwrap_or(false),
+        }))
+      },
+      | "path" => {
+        let nodes = self.get_children(node, vec!["hint"])?;
+
+        ActionSingle::Prompt(Prompt::Path(PathPrompt {
+          name: self.get_arg_string(node)?,
+          hint: self.get_hint(node, nodes)?,
+          when: node.get_string("when"),
+          default: self.get_default_string(nodes),
+          must_exist: node.get_bool("must_exist").unwrap_or(false),
+          kind: self.get_path_kind(node)?,
         }))
       },
       | "confirm" => {
@@ -400,6 +855,7 @@ impl Config {
         ActionSingle::Prompt(Prompt::Confirm(ConfirmPrompt {
           name: self.get_arg_string(node)?,
           hint: self.get_hint(node, nodes)?,
+          when: node.get_string("when"),
           default: self.get_default_bool(nodes),
         }))
       },
@@ -410,7 +866,15 @@ impl Config {
             children
               .nodes()
               .iter()
-              .map(|node| node.name().value().to_string())
+              .map(|node| {
+                Replacement {
+                  name: node.name().value().to_string(),
+                  default: node.get_string("default"),
+                  files: node
+                    .get_string("files")
+                    .map(|files| files.split_whitespace().map(str::to_string).collect()),
+                }
+              })
               .collect()
           })
           .unwrap_or_default();
@@ -419,6 +883,21 @@ impl Config {
 
         ActionSingle::Replace(Replace { replacements, glob })
       },
+      | "replace-in-file" => {
+        let pattern = self.get_attr_string(node, "pattern")?;
+        let regex = node.get_bool("regex").unwrap_or(false);
+
+        if regex {
+          self.get_regex(node, "pattern")?;
+        }
+
+        ActionSingle::ReplaceInFile(ReplaceInFile {
+          target: self.get_arg_string(node)?,
+          pattern,
+          replacement: self.get_attr_string(node, "replacement")?,
+          regex,
+        })
+      },
       // Fallback.
       | action => ActionSingle::Unknown(Unknown { name: action.to_string() }),
     };
@@ -426,6 +905,35 @@ impl Config {
     Ok(action)
   }
 
+  /// Checks that a prompt doesn't reuse a name already taken in the same scope, since answers are
+  /// used as replacement values by name and would be silently overwritten otherwise.
+  fn check_prompt_name(
+    &self,
+    node: &KdlNode,
+    action: &ActionSingle,
+    names: &mut HashSet<String>,
+  ) -> Result<(), ConfigError> {
+    let ActionSingle::Prompt(prompt) = action else {
+      return Ok(());
+    };
+
+    if names.insert(prompt.name().to_string()) {
+      return Ok(());
+    }
+
+    Err(diagnostic!(
+      source = &self.source,
+      code = "decaff::config::actions",
+      labels = vec![LabeledSpan::at(
+        node.span().to_owned(),
+        "this prompt redefines the name"
+      )],
+      help = "Prompt answers are used as replacement values, so their names must be unique.",
+      "Duplicate prompt name: `{}`.",
+      prompt.name()
+    ))
+  }
+
   fn get_arg_string(&self, node: &KdlNode) -> Result<String, ConfigError> {
     let start = node.span().offset();
     let end = start + node.name().len();
@@ -457,6 +965,97 @@ impl Config {
     })
   }
 
+  fn get_template_engine(&self, node: &KdlNode) -> Result<TemplateEngine, ConfigError> {
+    match node.get("engine") {
+      | None => Ok(TemplateEngine::default()),
+      | Some(entry) => {
+        match entry.value().as_string() {
+          | Some("simple") => Ok(TemplateEngine::Simple),
+          | _ => {
+            Err(diagnostic!(
+              source = &self.source,
+              code = "decaff::config::actions",
+              labels = vec![LabeledSpan::at(
+                entry.span().to_owned(),
+                "unsupported template engine"
+              )],
+              help = "Supported engines: `simple`.",
+              "Unsupported template engine."
+            ))
+          },
+        }
+      },
+    }
+  }
+
+  fn get_transform(&self, node: &KdlNode) -> Result<Option<Transform>, ConfigError> {
+    let Some(entry) = node.get("transform") else {
+      return Ok(None);
+    };
+
+    match entry.value().as_string() {
+      | Some("slug") => Ok(Some(Transform::Slug)),
+      | Some("upper") => Ok(Some(Transform::Upper)),
+      | Some("lower") => Ok(Some(Transform::Lower)),
+      | Some("kebab") => Ok(Some(Transform::Kebab)),
+      | _ => {
+        Err(diagnostic!(
+          source = &self.source,
+          code = "decaff::config::actions",
+          labels = vec![LabeledSpan::at(
+            entry.span().to_owned(),
+            "unsupported transform"
+          )],
+          help = "Supported transforms: `slug`, `upper`, `lower`, `kebab`.",
+          "Unsupported transform."
+        ))
+      },
+    }
+  }
+
+  fn get_path_kind(&self, node: &KdlNode) -> Result<Option<PathKind>, ConfigError> {
+    let Some(entry) = node.get("kind") else {
+      return Ok(None);
+    };
+
+    match entry.value().as_string() {
+      | Some("file") => Ok(Some(PathKind::File)),
+      | Some("dir") => Ok(Some(PathKind::Dir)),
+      | _ => {
+        Err(diagnostic!(
+          source = &self.source,
+          code = "decaff::config::actions",
+          labels = vec![LabeledSpan::at(
+            entry.span().to_owned(),
+            "unsupported path kind"
+          )],
+          help = "Supported kinds: `file`, `dir`.",
+          "Unsupported path kind."
+        ))
+      },
+    }
+  }
+
+  /// Compiles the regular expression in the given attribute, if any.
+  fn get_regex(&self, node: &KdlNode, key: &str) -> Result<Option<Regex>, ConfigError> {
+    let Some(pattern) = node.get_string(key) else {
+      return Ok(None);
+    };
+
+    Regex::new(&pattern).map(Some).map_err(|err| {
+      diagnostic!(
+        source = &self.source,
+        code = "decaff::config::actions",
+        labels = vec![LabeledSpan::at(
+          node.span().to_owned(),
+          format!("this node has an invalid `{key}`")
+        )],
+        help = err.to_string(),
+        "Invalid regular expression."
+      )
+    })
+  }
+
   fn get_children<'kdl>(
     &self,
     node: &'kdl KdlNode,
@@ -510,6 +1109,42 @@ impl Config {
     })
   }
 
+  /// Collects `exclude` attributes. Each may contain several space-separated patterns, and the
+  /// attribute itself may be repeated.
+  fn get_exclude(&self, node: &KdlNode) -> Vec<String> {
+    node
+      .entries()
+      .iter()
+      .filter(|entry| entry.name().is_some_and(|name| name.value() == "exclude"))
+      .filter_map(|entry| entry.value().as_string())
+      .flat_map(str::split_whitespace)
+      .map(str::to_string)
+      .collect()
+  }
+
+  fn get_env(&self, node: &KdlNode) -> HashMap<String, String> {
+    node
+      .children()
+      .and_then(|children| children.get("env"))
+      .and_then(KdlNode::children)
+      .map(|vars| {
+        vars
+          .nodes()
+          .iter()
+          .filter_map(|var| {
+            let value = var.get(0)?.value();
+            let value = value
+              .as_string()
+              .map(str::to_string)
+              .unwrap_or_else(|| value.to_string());
+
+            Some((var.name().value().to_string(), value))
+          })
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+
   fn get_options(&self, parent: &KdlNode, nodes: &KdlDocument) -> Result<Vec<String>, ConfigError> {
     let options = nodes.get("options").ok_or_else(|| {
       diagnostic!(
@@ -569,6 +1204,31 @@ impl Config {
     nodes.get("default").and_then(|node| node.get_string(0))
   }
 
+  fn get_default_strings(&self, nodes: &KdlDocument) -> Option<Vec<String>> {
+    nodes.get("default").map(|node| {
+      node
+        .entries()
+        .iter()
+        .filter(|entry| entry.name().is_none())
+        .filter_map(|entry| entry.value().as_string().map(str::to_string))
+        .collect()
+    })
+  }
+
+  fn get_suggestions(&self, nodes: &KdlDocument) -> Vec<String> {
+    nodes
+      .get("suggestions")
+      .map(|node| {
+        node
+          .entries()
+          .iter()
+          .filter(|entry| entry.name().is_none())
+          .filter_map(|entry| entry.value().as_string().map(str::to_string))
+          .collect()
+      })
+      .unwrap_or_default()
+  }
+
   fn get_default_bool(&self, nodes: &KdlDocument) -> Option<bool> {
     nodes.get("default").and_then(|node| node.get_bool(0))
   }
@@ -577,3 +1237,1163 @@ impl Config {
     nodes.get("default").and_then(|node| node.get_number(0))
   }
 }
+
+/// Returns names that must be unique across imported configs, keyed by kind: names of suites, or
+/// names of prompts in a flat list. Prompts in suites are scoped to their suite, which is unique
+/// already.
+fn unique_names(actions: &Actions) -> Vec<(&'static str, String)> {
+  match actions {
+    | Actions::Suite(suites) => {
+      suites
+        .iter()
+        .map(|suite| ("suite", suite.name.clone()))
+        .collect()
+    },
+    | Actions::Flat(actions) => {
+      actions
+        .iter()
+        .filter_map(|action| {
+          match action {
+            | ActionSingle::Prompt(prompt) => Some(("prompt", prompt.name().to_string())),
+            | _ => None,
+          }
+        })
+        .collect()
+    },
+    | Actions::Empty => Vec::new(),
+  }
+}
+
+/// Checks if the cleaned `path` is within the cleaned `root`. A relative root of `.` contains all
+/// relative paths that don't start with `..`.
+fn is_within(path: &Path, root: &Path) -> bool {
+  if root == Path::new(".") {
+    !path.is_absolute() && !path.starts_with("..")
+  } else {
+    path.starts_with(root)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs;
+
+  use tempfile::TempDir;
+
+  use super::*;
+
+  // Helpers.
+
+  fn load(contents: &str) -> (TempDir, Result<Config, ConfigError>) {
+    let temp = TempDir::new().unwrap();
+    fs::write(temp.path().join(CONFIG_NAME), contents).unwrap();
+
+    let mut config = Config::new(temp.path());
+    let result = config.load().map(|_| config);
+
+    (temp, result)
+  }
+
+  // Tests.
+
+  #[test]
+  fn load_custom_named_config() {
+    let temp = TempDir::new().unwrap();
+
+    fs::write(
+      temp.path().join("template.kdl"),
+      r#"
+      actions {
+        echo "Hello"
+      }
+      "#,
+    )
+    .unwrap();
+
+    let mut default = Config::new(temp.path());
+    assert!(!default.load().unwrap());
+
+    let mut custom = Config::new(temp.path()).name("template.kdl");
+    assert!(custom.load().unwrap());
+    assert!(matches!(&custom.actions, Actions::Flat(actions) if actions.len() == 1));
+    assert!(custom.source.name().ends_with("template.kdl"));
+
+    assert_eq!(
+      Config::resolve_name(Some("template.kdl".to_string())),
+      "template.kdl"
+    );
+  }
+
+  #[test]
+  fn merge_imported_configs() {
+    let temp = TempDir::new().unwrap();
+
+    fs::create_dir_all(temp.path().join("parts")).unwrap();
+
+    fs::write(
+      temp.path().join(CONFIG_NAME),
+      r#"
+      import "parts/first.kdl"
+      actions {
+        echo "root"
+      }
+      "#,
+    )
+    .unwrap();
+
+    fs::write(
+      temp.path().join("parts/first.kdl"),
+      r#"
+      import "second.kdl"
+      renames {
+        "gitignore" ".gitignore"
+      }
+      actions {
+        echo "first"
+      }
+      "#,
+    )
+    .unwrap();
+
+    fs::write(
+      temp.path().join("parts/second.kdl"),
+      r#"
+      actions {
+        echo "second"
+      }
+      "#,
+    )
+    .unwrap();
+
+    let mut config = Config::new(temp.path());
+    assert!(config.load().unwrap());
+
+    let Actions::Flat(actions) = &config.actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let messages = actions
+      .iter()
+      .map(|action| {
+        match action {
+          | ActionSingle::Echo(echo) => echo.message.as_str(),
+          | _ => panic!("expected echo actions"),
+        }
+      })
+      .collect::<Vec<_>>();
+
+    assert_eq!(messages, vec!["second", "first", "root"]);
+    assert_eq!(config.renames.len(), 1);
+    assert_eq!(
+      config.imports,
+      vec![
+        temp.path().join("parts/first.kdl"),
+        temp.path().join("parts/second.kdl"),
+      ]
+    );
+  }
+
+  #[test]
+  fn reject_import_cycles() {
+    let temp = TempDir::new().unwrap();
+
+    fs::write(temp.path().join(CONFIG_NAME), r#"import "a.kdl""#).unwrap();
+    fs::write(temp.path().join("a.kdl"), r#"import "b.kdl""#).unwrap();
+    fs::write(temp.path().join("b.kdl"), r#"import "a.kdl""#).unwrap();
+
+    let mut config = Config::new(temp.path());
+    let err = config.load().unwrap_err();
+
+    assert_eq!(
+      err.to_string(),
+      "Import cycle detected: decaff.kdl → a.kdl → b.kdl → a.kdl."
+    );
+  }
+
+  #[test]
+  fn reject_imports_outside_of_root() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("template");
+
+    fs::create_dir_all(&root).unwrap();
+    fs::write(temp.path().join("outside.kdl"), "").unwrap();
+
+    for import in [
+      "../outside.kdl".to_string(),
+      "nested/../../outside.kdl".to_string(),
+      temp.path().join("outside.kdl").display().to_string(),
+    ] {
+      fs::write(root.join(CONFIG_NAME), format!("import {import:?}")).unwrap();
+
+      let mut config = Config::new(&root);
+      let err = config.load().unwrap_err();
+
+      assert_eq!(
+        err.to_string(),
+        format!("Imported config is outside of the template: '{import}'.")
+      );
+    }
+  }
+
+  #[test]
+  fn reject_duplicate_names_across_imports() {
+    let cases = [
+      (
+        r#"actions { suite "build" { echo "a"; }; }"#,
+        r#"actions { suite "build" { echo "b"; }; }"#,
+        "Duplicate suite name: `build`.",
+      ),
+      (
+        r#"actions { input "name" { hint "Name"; }; }"#,
+        r#"actions { input "name" { hint "Name"; }; }"#,
+        "Duplicate prompt name: `name`.",
+      ),
+    ];
+
+    for (imported, own, message) in cases {
+      let temp = TempDir::new().unwrap();
+
+      fs::write(temp.path().join("a.kdl"), imported).unwrap();
+      fs::write(temp.path().join("b.kdl"), imported).unwrap();
+
+      // Between two imports, pointing at both import nodes.
+      let contents = "import \"a.kdl\"\nimport \"b.kdl\"\n";
+      fs::write(temp.path().join(CONFIG_NAME), contents).unwrap();
+
+      let err = Config::new(temp.path()).load().unwrap_err();
+      let labels = err.labels().unwrap().collect::<Vec<_>>();
+
+      assert_eq!(err.to_string(), message);
+      assert_eq!(labels[0].offset(), 0);
+      assert_eq!(labels[1].offset(), contents.find("import \"b").unwrap());
+
+      // Between an import and own actions.
+      let contents = format!("import \"a.kdl\"\n{own}\n");
+      fs::write(temp.path().join(CONFIG_NAME), &contents).unwrap();
+
+      let err = Config::new(temp.path()).load().unwrap_err();
+      let labels = err.labels().unwrap().collect::<Vec<_>>();
+
+      assert_eq!(err.to_string(), message);
+      assert_eq!(labels[0].offset(), 0);
+      assert!(labels[1].offset() > contents.find("actions").unwrap());
+    }
+  }
+
+  #[test]
+  fn missing_required_attributes() {
+    let cases = [
+      (r#"cp to="dest""#, "Missing required attribute: `from`."),
+      (r#"cp from="src""#, "Missing required attribute: `to`."),
+      (r#"mv from="src""#, "Missing required attribute: `to`."),
+      ("rm", "Missing required argument."),
+      (r#"run name="build""#, "Missing required argument."),
+    ];
+
+    for (action, message) in cases {
+      let contents = format!("actions {{\n  {action}\n}}\n");
+      let (_temp, config) = load(&contents);
+
+      let Err(err) = config else {
+        panic!("Expected `{action}` to fail");
+      };
+
+      assert_eq!(err.to_string(), message, "{action}");
+
+      // Labels point at the offending node.
+      let offset = contents.find(action).unwrap();
+      let labels = err.labels().unwrap().collect::<Vec<_>>();
+
+      assert_eq!(labels[0].offset(), offset, "{action}");
+    }
+  }
+
+  #[test]
+  fn parse_renames() {
+    let (_temp, config) = load(
+      r#"
+      renames {
+        "gitignore" ".gitignore"
+        "**/_env" ".env"
+      }
+      "#,
+    );
+
+    let renames = config
+      .unwrap()
+      .renames
+      .into_iter()
+      .map(|rename| (rename.from, rename.to))
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      renames,
+      vec![
+        ("gitignore".to_string(), ".gitignore".to_string()),
+        ("**/_env".to_string(), ".env".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn reject_renames_to_other_directories() {
+    for to in ["", ".", "..", "../escaped", "nested/name"] {
+      let (_temp, config) = load(&format!(
+        r#"
+        renames {{
+          "gitignore" "{to}"
+        }}
+        "#
+      ));
+
+      let err = config.unwrap_err();
+
+      assert_eq!(err.to_string(), format!("Invalid new name: '{to}'."));
+    }
+  }
+
+  #[test]
+  fn parse_symlink() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        symlink from="shared/.editorconfig" to="app/.editorconfig"
+        symlink from="shared" to="link" overwrite=true
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("Expected flat actions");
+    };
+
+    let links = actions
+      .iter()
+      .map(|action| {
+        match action {
+          | ActionSingle::Symlink(link) => (link.from.as_str(), link.to.as_str(), link.overwrite),
+          | _ => panic!("Expected symlink action"),
+        }
+      })
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      links,
+      vec![
+        ("shared/.editorconfig", "app/.editorconfig", false),
+        ("shared", "link", true),
+      ]
+    );
+  }
+
+  #[test]
+  fn parse_print_as_echo() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        echo "Hello"
+        print "Run `{{ PM }} install`" trim=false
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Echo(echo), ActionSingle::Echo(print)] = &actions[..] else {
+      panic!("expected two echo actions");
+    };
+
+    assert_eq!(echo.message, "Hello");
+    assert!(echo.trim);
+    assert_eq!(print.message, "Run `{{ PM }} install`");
+    assert!(!print.trim);
+  }
+
+  #[test]
+  fn parse_run_attributes() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        run "npm install" cwd="packages/app"
+        run "ls"
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Run(nested), ActionSingle::Run(root)] = &actions[..] else {
+      panic!("expected two run actions");
+    };
+
+    assert_eq!(nested.cwd.as_deref(), Some("packages/app"));
+    assert_eq!(root.cwd, None);
+    assert!(!root.continue_on_error);
+
+    let (_temp, config) = load(
+      r#"
+      actions {
+        run "npm run lint" continue_on_error=true
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Run(run)] = &actions[..] else {
+      panic!("expected a single run action");
+    };
+
+    assert!(run.continue_on_error);
+  }
+
+  #[test]
+  fn parse_run_env() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        run "npm publish" {
+          inject "PM"
+          env {
+            NODE_ENV "production"
+            RETRIES 3
+          }
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Run(run)] = &actions[..] else {
+      panic!("expected a single run action");
+    };
+
+    assert_eq!(
+      run.env,
+      HashMap::from([
+        ("NODE_ENV".to_string(), "production".to_string()),
+        ("RETRIES".to_string(), "3".to_string()),
+      ])
+    );
+
+    assert_eq!(run.injects, Some(HashSet::from(["PM".to_string()])));
+  }
+
+  #[test]
+  fn parse_git_init() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        git-init
+        git-init initial_branch="main"
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::GitInit(default), ActionSingle::GitInit(main)] = &actions[..] else {
+      panic!("expected two git-init actions");
+    };
+
+    assert_eq!(default.initial_branch, None);
+    assert_eq!(main.initial_branch.as_deref(), Some("main"));
+  }
+
+  #[test]
+  fn parse_exclude() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        cp from="src/**" to="dist" exclude="**/*.tmp **/*.log" exclude="cache"
+        mv from="src/**" to="dist"
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Copy(copy), ActionSingle::Move(mv)] = &actions[..] else {
+      panic!("expected copy and move actions");
+    };
+
+    assert_eq!(copy.exclude, vec!["**/*.tmp", "**/*.log", "cache"]);
+    assert!(mv.exclude.is_empty());
+  }
+
+  #[test]
+  fn parse_multiselect() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        multiselect "features" {
+          hint "Features"
+          options "eslint" "prettier" "vitest"
+          default "eslint" "vitest"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Prompt(Prompt::MultiSelect(prompt))] = &actions[..] else {
+      panic!("expected a single multiselect prompt");
+    };
+
+    assert_eq!(prompt.name, "features");
+    assert_eq!(prompt.hint, "Features");
+    assert_eq!(prompt.options, vec!["eslint", "prettier", "vitest"]);
+    assert_eq!(
+      prompt.default,
+      Some(vec!["eslint".to_string(), "vitest".to_string()])
+    );
+  }
+
+  #[test]
+  fn parse_editor_extension() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        editor "readme" extension="md" {
+          hint "Readme"
+        }
+        editor "config" extension=".toml" {
+          hint "Config"
+        }
+        editor "notes" {
+          hint "Notes"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let extensions = actions
+      .iter()
+      .map(|action| {
+        match action {
+          | ActionSingle::Prompt(Prompt::Editor(prompt)) => prompt.extension.as_deref(),
+          | _ => panic!("expected only editor prompts"),
+        }
+      })
+      .collect::<Vec<_>>();
+
+    assert_eq!(extensions, vec![Some(".md"), Some(".toml"), None]);
+  }
+
+  #[test]
+  fn parse_password() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        password "token" confirm=true {
+          hint "API token"
+        }
+        password "secret" {
+          hint "Secret"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Prompt(Prompt::Password(token)), ActionSingle::Prompt(Prompt::Password(secret))] =
+      &actions[..]
+    else {
+      panic!("expected two password prompts");
+    };
+
+    assert_eq!(token.name, "token");
+    assert_eq!(token.hint, "API token");
+    assert!(token.confirm);
+    assert!(!secret.confirm);
+  }
+
+  #[test]
+  fn parse_prompt_conditions() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        confirm "use_database" {
+          hint "Use database?"
+        }
+        input "database_url" when="use_database" {
+          hint "Database URL"
+        }
+        select "storage" when="!use_database" {
+          hint "Storage"
+          options "fs" "memory"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let conditions = actions
+      .iter()
+      .map(|action| {
+        match action {
+          | ActionSingle::Prompt(prompt) => prompt.when(),
+          | _ => panic!("expected only prompts"),
+        }
+      })
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      conditions,
+      vec![None, Some("use_database"), Some("!use_database")]
+    );
+  }
+
+  #[test]
+  fn parse_number_range() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        number "port" integer=true min=1 max=65535 {
+          hint "Port"
+          default 8080
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Prompt(Prompt::Number(prompt))] = &actions[..] else {
+      panic!("expected a single number prompt");
+    };
+
+    assert!(prompt.integer);
+    assert!(matches!(prompt.min, Some(Number::Integer(1))));
+    assert!(matches!(prompt.max, Some(Number::Integer(65535))));
+    assert!(matches!(prompt.default, Some(Number::Integer(8080))));
+  }
+
+  #[test]
+  fn parse_input_validation() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        input "package" validate=r"^[a-z][a-z0-9-]*$" {
+          hint "Package name"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Prompt(Prompt::Input(prompt))] = &actions[..] else {
+      panic!("expected a single input prompt");
+    };
+
+    assert_eq!(
+      prompt.validate.as_ref().map(Regex::as_str),
+      Some("^[a-z][a-z0-9-]*$")
+    );
+
+    let (_temp, config) = load(
+      r#"
+      actions {
+        input "package" validate="[" {
+          hint "Package name"
+        }
+      }
+      "#,
+    );
+
+    assert!(config.is_err_and(|err| err.to_string().contains("Invalid regular expression")));
+  }
+
+  #[test]
+  fn parse_input_suggestions() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        input "license" {
+          hint "License"
+          suggestions "MIT" "Apache-2.0" "MPL-2.0"
+        }
+        input "name" {
+          hint "Name"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Prompt(Prompt::Input(license)), ActionSingle::Prompt(Prompt::Input(name))] =
+      &actions[..]
+    else {
+      panic!("expected two input prompts");
+    };
+
+    assert_eq!(license.suggestions, vec!["MIT", "Apache-2.0", "MPL-2.0"]);
+    assert!(name.suggestions.is_empty());
+  }
+
+  #[test]
+  fn parse_input_length() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        input "name" min_length=1 max_length=214 {
+          hint "Name"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Prompt(Prompt::Input(prompt))] = &actions[..] else {
+      panic!("expected a single input prompt");
+    };
+
+    assert_eq!(prompt.min_length, Some(1));
+    assert_eq!(prompt.max_length, Some(214));
+  }
+
+  #[test]
+  fn parse_transforms() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        input "name" transform="slug" {
+          hint "Name"
+        }
+        select "case" transform="upper" {
+          hint "Case"
+          options "lower" "upper"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Prompt(Prompt::Input(input)), ActionSingle::Prompt(Prompt::Select(select))] =
+      &actions[..]
+    else {
+      panic!("expected an input and a select prompt");
+    };
+
+    assert_eq!(input.transform, Some(Transform::Slug));
+    assert_eq!(select.transform, Some(Transform::Upper));
+
+    let (_temp, config) = load(
+      r#"
+      actions {
+        input "name" transform="title" {
+          hint "Name"
+        }
+      }
+      "#,
+    );
+
+    assert!(config.is_err_and(|err| err.to_string() == "Unsupported transform."));
+  }
+
+  #[test]
+  fn parse_path() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        path "workspace" must_exist=true kind="dir" {
+          hint "Workspace"
+          default "~/projects"
+        }
+        path "output" {
+          hint "Output"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Prompt(Prompt::Path(workspace)), ActionSingle::Prompt(Prompt::Path(output))] =
+      &actions[..]
+    else {
+      panic!("expected two path prompts");
+    };
+
+    assert_eq!(workspace.default.as_deref(), Some("~/projects"));
+    assert!(workspace.must_exist);
+    assert_eq!(workspace.kind, Some(PathKind::Dir));
+    assert!(!output.must_exist);
+    assert_eq!(output.kind, None);
+
+    let (_temp, config) = load(
+      r#"
+      actions {
+        path "workspace" kind="socket" {
+          hint "Workspace"
+        }
+      }
+      "#,
+    );
+
+    assert!(config.is_err_and(|err| err.to_string() == "Unsupported path kind."));
+  }
+
+  #[test]
+  fn parse_select_default() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        select "pm" {
+          hint "Package manager"
+          options "npm" "pnpm" "yarn"
+          default "pnpm"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Prompt(Prompt::Select(prompt))] = &actions[..] else {
+      panic!("expected a single select prompt");
+    };
+
+    assert_eq!(prompt.default.as_deref(), Some("pnpm"));
+
+    let (_temp, config) = load(
+      r#"
+      actions {
+        select "pm" {
+          hint "Package manager"
+          options "npm" "pnpm"
+          default "bun"
+        }
+      }
+      "#,
+    );
+
+    assert!(config.is_err_and(|err| err.to_string() == "Unknown default option: `bun`."));
+  }
+
+  #[test]
+  fn parse_mkdir() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        mkdir "src/assets"
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::MakeDir(mkdir)] = &actions[..] else {
+      panic!("expected a single mkdir action");
+    };
+
+    assert_eq!(mkdir.target, "src/assets");
+  }
+
+  #[test]
+  fn parse_download() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        download url="https://example.com/LICENSE" to="LICENSE"
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Download(download)] = &actions[..] else {
+      panic!("expected a single download action");
+    };
+
+    assert_eq!(download.url, "https://example.com/LICENSE");
+    assert_eq!(download.to, "LICENSE");
+  }
+
+  #[test]
+  fn parse_template() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        template from=".template/README.md" to="README.md" engine="simple"
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Template(template)] = &actions[..] else {
+      panic!("expected a single template action");
+    };
+
+    assert_eq!(template.from, ".template/README.md");
+    assert_eq!(template.to, "README.md");
+    assert_eq!(template.engine, TemplateEngine::Simple);
+
+    let (_temp, config) = load(
+      r#"
+      actions {
+        template from="a" to="b" engine="tera"
+      }
+      "#,
+    );
+
+    assert!(config.is_err_and(|err| err.to_string().contains("Unsupported template engine")));
+  }
+
+  #[test]
+  fn parse_replacement_attributes() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        replace in="**/*.md" {
+          "repo_name"
+          "repo_license" default="MIT"
+          "author_name" files="LICENSE *.md"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Replace(replace)] = &actions[..] else {
+      panic!("expected a single replace action");
+    };
+
+    assert_eq!(replace.glob.as_deref(), Some("**/*.md"));
+    assert_eq!(
+      replace.replacements,
+      vec![
+        Replacement {
+          name: "repo_name".to_string(),
+          default: None,
+          files: None,
+        },
+        Replacement {
+          name: "repo_license".to_string(),
+          default: Some("MIT".to_string()),
+          files: None,
+        },
+        Replacement {
+          name: "author_name".to_string(),
+          default: None,
+          files: Some(vec!["LICENSE".to_string(), "*.md".to_string()]),
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn parse_replace_in_file() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        replace-in-file "**/*.toml" pattern="0.1.0" replacement="1.0.0"
+        replace-in-file "src/*.rs" pattern=r"v(\d+)" replacement="version $1" regex=true
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::ReplaceInFile(literal), ActionSingle::ReplaceInFile(regex)] = &actions[..]
+    else {
+      panic!("expected two replace-in-file actions");
+    };
+
+    assert_eq!(literal.target, "**/*.toml");
+    assert_eq!(literal.pattern, "0.1.0");
+    assert_eq!(literal.replacement, "1.0.0");
+    assert!(!literal.regex);
+    assert_eq!(regex.pattern, "v(\\d+)");
+    assert!(regex.regex);
+
+    let (_temp, config) = load(
+      r#"
+      actions {
+        replace-in-file "*" pattern="(" replacement="" regex=true
+      }
+      "#,
+    );
+
+    assert!(config.is_err_and(|err| err.to_string().contains("Invalid regular expression")));
+  }
+
+  #[test]
+  fn reject_duplicate_suite_names() {
+    let contents = r#"
+      actions {
+        suite "build" {
+          run "npm run build"
+        }
+        suite "test" {
+          run "npm test"
+        }
+        suite "build" {
+          run "npm run build:prod"
+        }
+      }
+      "#;
+
+    let (_temp, config) = load(contents);
+
+    let ConfigError::Diagnostic(report) = config.unwrap_err() else {
+      panic!("expected a diagnostic");
+    };
+
+    assert_eq!(report.to_string(), "Duplicate suite name: `build`.");
+
+    let offsets = report
+      .labels()
+      .unwrap()
+      .map(|label| label.offset())
+      .collect::<Vec<_>>();
+
+    assert_eq!(
+      offsets,
+      vec![
+        contents.find(r#"suite "build""#).unwrap(),
+        contents.rfind(r#"suite "build""#).unwrap(),
+      ]
+    );
+  }
+
+  #[test]
+  fn reject_mixed_actions() {
+    let contents = r#"
+      actions {
+        suite "first" {
+          echo "Hello"
+        }
+        cp from="a" to="b"
+      }
+      "#;
+
+    let (_temp, config) = load(contents);
+
+    let ConfigError::Diagnostic(report) = config.unwrap_err() else {
+      panic!("expected a diagnostic");
+    };
+
+    assert!(report.to_string().contains("a mix of both"));
+
+    let label = report.labels().unwrap().next().unwrap();
+    let offset = contents.find("cp from").unwrap();
+
+    assert_eq!(label.offset(), offset);
+    assert_eq!(
+      label.label(),
+      Some("expected a `suite`, found a single action")
+    );
+  }
+
+  #[test]
+  fn reject_duplicate_prompt_names() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        suite "first" {
+          input "NAME" {
+            hint "Name"
+          }
+          replace {
+            NAME
+          }
+        }
+        suite "second" {
+          input "NAME" {
+            hint "Name"
+          }
+          confirm "NAME" {
+            hint "Name?"
+          }
+        }
+      }
+      "#,
+    );
+
+    assert!(config.is_err_and(|err| err.to_string().contains("Duplicate prompt name: `NAME`")));
+  }
+
+  #[test]
+  fn parse_write() {
+    let (_temp, config) = load(
+      r#"
+      actions {
+        write to="config.txt" overwrite=false {
+          content "name={NAME}"
+          inject "NAME"
+        }
+      }
+      "#,
+    );
+
+    let Actions::Flat(actions) = config.unwrap().actions else {
+      panic!("expected a flat list of actions");
+    };
+
+    let [ActionSingle::Write(write)] = &actions[..] else {
+      panic!("expected a single write action");
+    };
+
+    assert_eq!(write.to, "config.txt");
+    assert_eq!(write.content, "name={NAME}");
+    assert_eq!(write.injects, Some(HashSet::from(["NAME".to_string()])));
+    assert!(!write.overwrite);
+  }
+}
@@ -1,8 +1,10 @@
+pub use answers::*;
 pub use config::*;
 pub use utils::*;
 pub use value::*;
 
 pub mod actions;
+pub mod answers;
 pub mod prompts;
 pub mod value;
 
@@ -1,3 +1,5 @@
+use regex::Regex;
+
 use crate::config::value::Number;
 
 #[derive(Debug)]
@@ -6,8 +8,20 @@ pub struct InputPrompt {
   pub name: String,
   /// Short description.
   pub hint: String,
+  /// Optional condition to ask the prompt at all, see `Prompt::when`.
+  pub when: Option<String>,
   /// Default value if input is empty.
   pub default: Option<String>,
+  /// Optional regular expression the answer must match.
+  pub validate: Option<Regex>,
+  /// Minimum length of the answer, in characters.
+  pub min_length: Option<usize>,
+  /// Maximum length of the answer, in characters.
+  pub max_length: Option<usize>,
+  /// Optional transform applied to the answer before storing it.
+  pub transform: Option<Transform>,
+  /// Suggestions to autocomplete the answer from. Any other answer is still accepted.
+  pub suggestions: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -16,8 +30,16 @@ pub struct NumberPrompt {
   pub name: String,
   /// Short description.
   pub hint: String,
+  /// Optional condition to ask the prompt at all, see `Prompt::when`.
+  pub when: Option<String>,
   /// Default value if input is empty.
   pub default: Option<Number>,
+  /// Whether only integers are accepted. Defaults to `false`.
+  pub integer: bool,
+  /// Minimum accepted value, inclusive.
+  pub min: Option<Number>,
+  /// Maximum accepted value, inclusive.
+  pub max: Option<Number>,
 }
 
 #[derive(Debug)]
@@ -26,8 +48,42 @@ pub struct SelectPrompt {
   pub name: String,
   /// Short description.
   pub hint: String,
+  /// Optional condition to ask the prompt at all, see `Prompt::when`.
+  pub when: Option<String>,
+  /// List of options.
+  pub options: Vec<String>,
+  /// Option selected by default. Always one of the options.
+  pub default: Option<String>,
+  /// Optional transform applied to the answer before storing it.
+  pub transform: Option<Transform>,
+}
+
+#[derive(Debug)]
+pub struct MultiSelectPrompt {
+  /// Name of the variable that will store the answer.
+  pub name: String,
+  /// Short description.
+  pub hint: String,
+  /// Optional condition to ask the prompt at all, see `Prompt::when`.
+  pub when: Option<String>,
   /// List of options.
   pub options: Vec<String>,
+  /// Options selected by default.
+  pub default: Option<Vec<String>>,
+}
+
+/// Transforms applied to answers of [InputPrompt] and [SelectPrompt].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+  /// URL-safe slug, e.g. `My Project!` becomes `my-project`. Only ASCII letters and digits are
+  /// kept.
+  Slug,
+  /// Uppercase, e.g. `My Project` becomes `MY PROJECT`.
+  Upper,
+  /// Lowercase, e.g. `My Project` becomes `my project`.
+  Lower,
+  /// Kebab case, e.g. `myProject name` becomes `my-project-name`. Splits camel case words too.
+  Kebab,
 }
 
 #[derive(Debug)]
@@ -36,16 +92,60 @@ pub struct ConfirmPrompt {
   pub name: String,
   /// Short description of the prompt.
   pub hint: String,
+  /// Optional condition to ask the prompt at all, see `Prompt::when`.
+  pub when: Option<String>,
   /// Default value.
   pub default: Option<bool>,
 }
 
+#[derive(Debug)]
+pub struct PasswordPrompt {
+  /// Name of the variable that will store the answer.
+  pub name: String,
+  /// Short description.
+  pub hint: String,
+  /// Optional condition to ask the prompt at all, see `Prompt::when`.
+  pub when: Option<String>,
+  /// Whether to ask to type the password again for confirmation. Defaults to `false`.
+  pub confirm: bool,
+}
+
+#[derive(Debug)]
+pub struct PathPrompt {
+  /// Name of the variable that will store the answer.
+  pub name: String,
+  /// Short description.
+  pub hint: String,
+  /// Optional condition to ask the prompt at all, see `Prompt::when`.
+  pub when: Option<String>,
+  /// Default value if input is empty.
+  pub default: Option<String>,
+  /// Whether the path must exist. Defaults to `false`.
+  pub must_exist: bool,
+  /// Kind of entry the path must point to, if it exists. Any kind is accepted by default.
+  pub kind: Option<PathKind>,
+}
+
+/// Kinds of entries accepted by [PathPrompt].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathKind {
+  /// A file.
+  File,
+  /// A directory.
+  Dir,
+}
+
 #[derive(Debug)]
 pub struct EditorPrompt {
   /// Name of the variable that will store the answer.
   pub name: String,
   /// Short description.
   pub hint: String,
+  /// Optional condition to ask the prompt at all, see `Prompt::when`.
+  pub when: Option<String>,
   /// Default value if input is empty.
   pub default: Option<String>,
+  /// Extension of the temporary file opened in the editor, e.g. `.md`, to enable syntax
+  /// highlighting. Defaults to `.txt`.
+  pub extension: Option<String>,
 }
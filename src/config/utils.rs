@@ -9,6 +9,9 @@ pub trait KdlUtils<K> {
   /// Gets an entry by key and tries to map it to a [NumberValue].
   fn get_number(&self, key: K) -> Option<Number>;
 
+  /// Gets an entry by key and tries to map it to a non-negative [usize].
+  fn get_usize(&self, key: K) -> Option<usize>;
+
   /// Gets an entry by key and tries to map it to a [bool].
   fn get_bool(&self, key: K) -> Option<bool>;
 }
@@ -34,6 +37,13 @@ where
     })
   }
 
+  fn get_usize(&self, key: K) -> Option<usize> {
+    self
+      .get(key)
+      .and_then(|entry| entry.value().as_i64())
+      .and_then(|value| usize::try_from(value).ok())
+  }
+
   fn get_bool(&self, key: K) -> Option<bool> {
     self.get(key).and_then(|entry| entry.value().as_bool())
   }
@@ -18,6 +18,16 @@ pub enum Number {
   Float(f64),
 }
 
+impl Number {
+  /// Returns the value as a float, e.g. for comparisons.
+  pub fn as_f64(&self) -> f64 {
+    match self {
+      | Self::Integer(int) => *int as f64,
+      | Self::Float(float) => *float,
+    }
+  }
+}
+
 impl Display for Number {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
@@ -39,7 +49,7 @@ impl FromStr for Number {
 }
 
 /// Replacement value.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Value {
   /// A string value.
   String(String),
@@ -47,6 +57,20 @@ pub enum Value {
   Number(Number),
   /// A boolean value.
   Bool(bool),
+  /// A list of values, e.g. options chosen in a multiselect prompt.
+  Array(Vec<String>),
+}
+
+impl Value {
+  /// Checks whether the value is truthy, i.e. not `false`, `0`, an empty string or an empty list.
+  pub fn is_truthy(&self) -> bool {
+    match self {
+      | Self::String(string) => !string.is_empty(),
+      | Self::Number(number) => number.as_f64() != 0.0,
+      | Self::Bool(boolean) => *boolean,
+      | Self::Array(values) => !values.is_empty(),
+    }
+  }
 }
 
 impl Display for Value {
@@ -55,6 +79,33 @@ impl Display for Value {
       | Self::String(string) => write!(f, "{string}"),
       | Self::Number(number) => write!(f, "{number}"),
       | Self::Bool(boolean) => write!(f, "{boolean}"),
+      | Self::Array(values) => write!(f, "{}", values.join(", ")),
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn truthiness() {
+    assert!(Value::Bool(true).is_truthy());
+    assert!(Value::String("yes".to_string()).is_truthy());
+    assert!(Value::Number(Number::Float(0.5)).is_truthy());
+    assert!(Value::Array(vec!["a".to_string()]).is_truthy());
+
+    assert!(!Value::Bool(false).is_truthy());
+    assert!(!Value::String(String::new()).is_truthy());
+    assert!(!Value::Number(Number::Integer(0)).is_truthy());
+    assert!(!Value::Array(Vec::new()).is_truthy());
+  }
+
+  #[test]
+  fn display_arrays_comma_separated() {
+    let value = Value::Array(vec!["eslint".to_string(), "prettier".to_string()]);
+
+    assert_eq!(value.to_string(), "eslint, prettier");
+    assert_eq!(Value::Array(Vec::new()).to_string(), "");
+  }
+}
@@ -2,11 +2,14 @@
 
 pub(crate) mod actions;
 pub mod app;
+pub mod auth;
 pub(crate) mod cache;
 pub(crate) mod config;
+pub(crate) mod logger;
 pub(crate) mod path;
 pub(crate) mod report;
 pub(crate) mod repository;
 pub(crate) mod spinner;
 pub(crate) mod unpacker;
 pub(crate) mod utils;
+pub(crate) mod vfs;
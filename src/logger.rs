@@ -0,0 +1,200 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Current verbosity, stored as [Verbosity] discriminant.
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+/// Verbosity of the output. Messages are printed if their level is less than or equal to the
+/// current verbosity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+  /// Nothing, errors are reported regardless of verbosity.
+  Quiet = 0,
+  /// Only the final summary.
+  Summary = 1,
+  /// Progress, actions output and warnings.
+  #[default]
+  Normal = 2,
+  /// Additionally URLs, resolved hashes and timings.
+  Verbose = 3,
+}
+
+impl Verbosity {
+  fn from_u8(value: u8) -> Self {
+    match value {
+      | 0 => Verbosity::Quiet,
+      | 1 => Verbosity::Summary,
+      | 2 => Verbosity::Normal,
+      | _ => Verbosity::Verbose,
+    }
+  }
+}
+
+/// Sets the current verbosity.
+pub fn set_verbosity(verbosity: Verbosity) {
+  #[cfg(test)]
+  if testing::set_verbosity(verbosity) {
+    return;
+  }
+
+  VERBOSITY.store(verbosity as u8, Ordering::Relaxed);
+}
+
+/// Returns the current verbosity.
+pub fn verbosity() -> Verbosity {
+  #[cfg(test)]
+  if let Some(verbosity) = testing::verbosity() {
+    return verbosity;
+  }
+
+  Verbosity::from_u8(VERBOSITY.load(Ordering::Relaxed))
+}
+
+/// Returns whether messages of the given level are printed.
+pub fn enabled(level: Verbosity) -> bool {
+  level <= verbosity()
+}
+
+/// Prints the message if its level is enabled. Use the [info] and [summary] macros instead.
+pub fn log(level: Verbosity, message: String) {
+  if !enabled(level) {
+    return;
+  }
+
+  #[cfg(test)]
+  if testing::push(&message) {
+    return;
+  }
+
+  println!("{message}");
+}
+
+/// Prints a progress, action or warning message. Suppressed by `--summary-only` and `--quiet`.
+macro_rules! info {
+  () => {
+    $crate::logger::log($crate::logger::Verbosity::Normal, String::new())
+  };
+  ($($arg:tt)*) => {
+    $crate::logger::log($crate::logger::Verbosity::Normal, format!($($arg)*))
+  };
+}
+
+/// Prints the final summary. Suppressed by `--quiet`.
+macro_rules! summary {
+  ($($arg:tt)*) => {
+    $crate::logger::log($crate::logger::Verbosity::Summary, format!($($arg)*))
+  };
+}
+
+/// Prints details like URLs, resolved hashes and timings. Only printed with `--verbose`.
+macro_rules! verbose {
+  ($($arg:tt)*) => {
+    $crate::logger::log($crate::logger::Verbosity::Verbose, format!($($arg)*))
+  };
+}
+
+pub(crate) use {info, summary, verbose};
+
+#[cfg(test)]
+pub mod testing {
+  use std::cell::RefCell;
+
+  use super::Verbosity;
+
+  thread_local! {
+    /// Verbosity and messages captured on the current thread.
+    static CAPTURED: RefCell<Option<(Verbosity, Vec<String>)>> = const { RefCell::new(None) };
+  }
+
+  /// Captures messages logged on the current thread with the given verbosity, instead of printing
+  /// them, until [Capture::finish] is called.
+  pub struct Capture;
+
+  impl Capture {
+    pub fn start(verbosity: Verbosity) -> Self {
+      CAPTURED.with(|captured| *captured.borrow_mut() = Some((verbosity, Vec::new())));
+      Self
+    }
+
+    /// Stops capturing, returning captured messages.
+    pub fn finish(self) -> Vec<String> {
+      CAPTURED
+        .with(|captured| captured.borrow_mut().take())
+        .map(|(_, messages)| messages)
+        .unwrap_or_default()
+    }
+  }
+
+  /// Sets verbosity of the capture on the current thread, if any. Returns whether it was set.
+  pub(super) fn set_verbosity(verbosity: Verbosity) -> bool {
+    CAPTURED.with(|captured| {
+      captured
+        .borrow_mut()
+        .as_mut()
+        .map(|(current, _)| *current = verbosity)
+        .is_some()
+    })
+  }
+
+  pub(super) fn verbosity() -> Option<Verbosity> {
+    CAPTURED.with(|captured| captured.borrow().as_ref().map(|(verbosity, _)| *verbosity))
+  }
+
+  pub(super) fn push(message: &str) -> bool {
+    CAPTURED.with(|captured| {
+      captured
+        .borrow_mut()
+        .as_mut()
+        .map(|(_, messages)| messages.push(message.to_string()))
+        .is_some()
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::testing::Capture;
+  use super::*;
+
+  #[test]
+  fn summary_only_suppresses_info() {
+    let capture = Capture::start(Verbosity::Summary);
+
+    info!("~ Extracted {} files", 3);
+    info!();
+    summary!("Scaffolded {}", "foo");
+
+    assert_eq!(capture.finish(), vec!["Scaffolded foo".to_string()]);
+
+    let capture = Capture::start(Verbosity::Normal);
+
+    info!("~ Extracted {} files", 3);
+    summary!("Scaffolded {}", "foo");
+
+    assert_eq!(capture.finish().len(), 2);
+  }
+
+  #[test]
+  fn quiet_and_verbose() {
+    let capture = Capture::start(Verbosity::Quiet);
+
+    info!("~ Extracted {} files", 3);
+    summary!("Scaffolded {}", "foo");
+    verbose!("~ Resolved main to {}", "abc123");
+
+    assert!(capture.finish().is_empty());
+
+    let capture = Capture::start(Verbosity::Normal);
+
+    info!("~ Extracted {} files", 3);
+    verbose!("~ Resolved main to {}", "abc123");
+
+    assert_eq!(capture.finish(), vec!["~ Extracted 3 files".to_string()]);
+
+    let capture = Capture::start(Verbosity::Verbose);
+
+    info!("~ Extracted {} files", 3);
+    verbose!("~ Resolved main to {}", "abc123");
+
+    assert_eq!(capture.finish().len(), 2);
+  }
+}
@@ -1,5 +1,7 @@
 pub use clean::*;
+pub use normalize::*;
 pub use traverser::*;
 
 mod clean;
+mod normalize;
 mod traverser;
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use clap::ValueEnum;
+use unicode_normalization::UnicodeNormalization;
+
+/// Unicode normalization policy for generated filenames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum Normalization {
+  /// Keep filenames as-is.
+  #[default]
+  None,
+  /// Normalize filenames to the composed form (NFC).
+  Nfc,
+  /// Normalize filenames to the decomposed form (NFD).
+  Nfd,
+}
+
+impl Normalization {
+  /// Normalizes every normal component of the given path according to the policy.
+  pub fn apply(&self, path: &Path) -> PathBuf {
+    if *self == Normalization::None {
+      return path.to_path_buf();
+    }
+
+    path
+      .components()
+      .map(|component| {
+        match component {
+          | Component::Normal(name) => {
+            let name = name.to_string_lossy();
+
+            match self {
+              | Normalization::Nfc => name.nfc().collect::<String>().into(),
+              | Normalization::Nfd => name.nfd().collect::<String>().into(),
+              | Normalization::None => name.into_owned().into(),
+            }
+          },
+          | component => component.as_os_str().to_os_string(),
+        }
+      })
+      .collect()
+  }
+}
+
+/// Detects paths that would collide on case-insensitive or Unicode-normalizing filesystems (e.g.
+/// on macOS), like `README.md` and `readme.md`, or `café` written in NFC and NFD forms.
+#[derive(Debug, Default)]
+pub struct CollisionDetector {
+  /// Seen paths keyed by their case-folded, NFC-normalized form.
+  seen: HashMap<String, PathBuf>,
+}
+
+impl CollisionDetector {
+  /// Creates a new detector.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Records the path and returns a previously seen path it collides with, if any. Identical paths
+  /// are not considered colliding.
+  pub fn check(&mut self, path: &Path) -> Option<PathBuf> {
+    let key = path
+      .to_string_lossy()
+      .nfc()
+      .flat_map(char::to_lowercase)
+      .collect::<String>();
+
+    match self.seen.get(&key) {
+      | Some(seen) if seen != path => Some(seen.to_owned()),
+      | Some(_) => None,
+      | None => {
+        self.seen.insert(key, path.to_path_buf());
+        None
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const NFC: &str = "caf\u{e9}.md";
+  const NFD: &str = "cafe\u{301}.md";
+
+  #[test]
+  fn detect_collisions() {
+    let mut detector = CollisionDetector::new();
+
+    assert_eq!(detector.check(Path::new("docs/README.md")), None);
+    assert_eq!(detector.check(Path::new("docs/README.md")), None);
+    assert_eq!(
+      detector.check(Path::new("docs/readme.md")),
+      Some(PathBuf::from("docs/README.md"))
+    );
+
+    assert_eq!(detector.check(Path::new(NFC)), None);
+    assert_eq!(detector.check(Path::new(NFD)), Some(PathBuf::from(NFC)));
+
+    assert_eq!(detector.check(Path::new("docs/other.md")), None);
+  }
+
+  #[test]
+  fn normalize_paths() {
+    let nfd = Path::new("docs").join(NFD);
+    let nfc = Path::new("docs").join(NFC);
+
+    assert_eq!(Normalization::Nfc.apply(&nfd), nfc);
+    assert_eq!(Normalization::Nfd.apply(&nfc), nfd);
+    assert_eq!(Normalization::None.apply(&nfd), nfd);
+  }
+}
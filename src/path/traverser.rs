@@ -1,13 +1,15 @@
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
 
-use glob_match::glob_match_with_captures;
+use glob_match::{glob_match, glob_match_with_captures};
 use thiserror::Error;
-use walkdir::{DirEntry, IntoIter as WalkDirIter, WalkDir};
+
+use crate::vfs::{EntryKind, Filesystem, RealFs, Walk};
 
 #[derive(Debug, Error)]
 pub enum TraverseError {
   #[error("Could not read entry while traversing directory.")]
-  InvalidEntry(walkdir::Error),
+  InvalidEntry(io::Error),
 }
 
 #[derive(Debug)]
@@ -16,19 +18,19 @@ pub struct Match {
   pub path: PathBuf,
   /// Captured path relative to the traverser's root.
   pub captured: PathBuf,
-  /// Original entry.
-  pub entry: DirEntry,
+  /// Kind of the entry.
+  pub kind: EntryKind,
 }
 
 impl Match {
   /// Checks if the match is a directory.
   pub fn is_dir(&self) -> bool {
-    self.entry.file_type().is_dir()
+    self.kind == EntryKind::Dir
   }
 
   /// Checks if the match is a file.
   pub fn is_file(&self) -> bool {
-    self.entry.file_type().is_file()
+    self.kind == EntryKind::File
   }
 }
 
@@ -36,8 +38,11 @@ impl Match {
 pub struct TraverseOptions {
   /// Directory to traverse.
   root: PathBuf,
-  /// Pattern to match the path against. If `None`, all paths will match.
-  pattern: Option<String>,
+  /// Patterns to match the path against. Paths matching any of them will match. If empty, all
+  /// paths will match.
+  patterns: Vec<String>,
+  /// Patterns to exclude paths matching them, along with their contents.
+  exclude: Vec<String>,
   /// Whether to ignore directories (not threir contents) when traversing. Defaults to `false`.
   ignore_dirs: bool,
   /// Whether to traverse contents of directories first (depth-first). Defaults to `false`.
@@ -45,27 +50,46 @@ pub struct TraverseOptions {
 }
 
 #[derive(Debug)]
-pub struct Traverser {
+pub struct Traverser<'f> {
   /// Traverser options.
   options: TraverseOptions,
+  /// Filesystem to traverse. Defaults to [RealFs].
+  fs: &'f dyn Filesystem,
 }
 
-impl Traverser {
+impl<'f> Traverser<'f> {
   /// Creates a new (consuming) builder.
   pub fn new<P: Into<PathBuf>>(root: P) -> Self {
     Self {
+      fs: &RealFs,
       options: TraverseOptions {
         root: root.into(),
-        pattern: None,
+        patterns: Vec::new(),
+        exclude: Vec::new(),
         ignore_dirs: false,
         contents_first: false,
       },
     }
   }
 
-  /// Set the pattern to match the path against.
+  /// Add a pattern to match the path against. Can be called multiple times to match any of the
+  /// patterns.
   pub fn pattern(mut self, pattern: &str) -> Self {
-    self.options.pattern = Some(pattern.to_string());
+    self.options.patterns.push(pattern.to_string());
+    self
+  }
+
+  /// Set patterns to exclude paths matching them, along with their contents.
+  pub fn exclude<I, S>(mut self, patterns: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+  {
+    self.options.exclude = patterns
+      .into_iter()
+      .map(|pattern| pattern.as_ref().to_string())
+      .collect();
+
     self
   }
 
@@ -81,32 +105,74 @@ impl Traverser {
     self
   }
 
+  /// Set the filesystem to traverse.
+  pub fn filesystem(mut self, fs: &'f dyn Filesystem) -> Self {
+    self.fs = fs;
+    self
+  }
+
   /// Creates an iterator without consuming the traverser builder.
-  pub fn iter(&self) -> TraverserIterator {
-    let it = WalkDir::new(&self.options.root)
-      .contents_first(self.options.contents_first)
-      .into_iter();
+  pub fn iter(&self) -> TraverserIterator<'_> {
+    let it = self
+      .fs
+      .walk(&self.options.root, self.options.contents_first);
+
+    let root_patterns = self
+      .options
+      .patterns
+      .iter()
+      .map(|pat| self.options.root.join(pat).display().to_string())
+      .collect();
 
-    let root_pattern = self
+    let root_exclude = self
       .options
-      .pattern
-      .as_ref()
-      .map(|pat| self.options.root.join(pat).display().to_string());
+      .exclude
+      .iter()
+      .map(|pat| self.options.root.join(pat).display().to_string())
+      .collect();
 
-    TraverserIterator { it, root_pattern, options: &self.options }
+    TraverserIterator {
+      it,
+      root_patterns,
+      root_exclude,
+      options: &self.options,
+    }
   }
 }
 
 /// Traverser iterator.
 pub struct TraverserIterator<'t> {
-  /// Inner iterator (using [walkdir::IntoIter]) that is used to do actual traversing.
-  it: WalkDirIter,
-  /// Pattern prepended with the root path to avoid conversions on every iteration.
-  root_pattern: Option<String>,
+  /// Inner iterator (using [Filesystem::walk]) that is used to do actual traversing.
+  it: Walk<'t>,
+  /// Patterns prepended with the root path to avoid conversions on every iteration.
+  root_patterns: Vec<String>,
+  /// Exclude patterns prepended with the root path.
+  root_exclude: Vec<String>,
   /// Traverser options.
   options: &'t TraverseOptions,
 }
 
+impl<'t> TraverserIterator<'t> {
+  /// Checks if the path or any of its parents within the root matches an exclude pattern.
+  fn is_excluded(&self, path: &Path) -> bool {
+    if self.root_exclude.is_empty() {
+      return false;
+    }
+
+    path
+      .ancestors()
+      .take_while(|ancestor| *ancestor != self.options.root)
+      .any(|ancestor| {
+        let candidate = ancestor.display().to_string();
+
+        self
+          .root_exclude
+          .iter()
+          .any(|pattern| glob_match(pattern, &candidate))
+      })
+  }
+}
+
 impl<'t> Iterator for TraverserIterator<'t> {
   type Item = Result<Match, TraverseError>;
 
@@ -115,28 +181,35 @@ impl<'t> Iterator for TraverserIterator<'t> {
 
     'skip: loop {
       match item {
-        | Ok(entry) => {
-          let path = entry.path();
-
+        | Ok((path, kind)) => {
           // This ignores only _entry_, while still stepping into the directory.
-          if self.options.ignore_dirs && entry.file_type().is_dir() {
+          if self.options.ignore_dirs && kind == EntryKind::Dir {
+            item = self.it.next()?;
+
+            continue 'skip;
+          }
+
+          if self.is_excluded(&path) {
             item = self.it.next()?;
 
             continue 'skip;
           }
 
-          if let Some(pattern) = &self.root_pattern {
+          // Every entry is walked once, so entries matching several patterns are yielded once, with
+          // captures of the first matching pattern.
+          if !self.root_patterns.is_empty() {
             let candidate = path.display().to_string();
 
-            if let Some(captures) = glob_match_with_captures(pattern, &candidate) {
+            let captures = self
+              .root_patterns
+              .iter()
+              .find_map(|pattern| glob_match_with_captures(pattern, &candidate));
+
+            if let Some(captures) = captures {
               let range = captures.first().cloned().unwrap_or_default();
               let captured = PathBuf::from(&candidate[range.start..]);
 
-              return Some(Ok(Match {
-                path: path.to_path_buf(),
-                captured,
-                entry,
-              }));
+              return Some(Ok(Match { path, captured, kind }));
             }
 
             item = self.it.next()?;
@@ -144,14 +217,89 @@ impl<'t> Iterator for TraverserIterator<'t> {
             continue 'skip;
           }
 
-          return Some(Ok(Match {
-            path: path.to_path_buf(),
-            captured: path.to_path_buf(),
-            entry,
-          }));
+          return Some(Ok(Match { captured: path.clone(), path, kind }));
         },
         | Err(err) => return Some(Err(TraverseError::InvalidEntry(err))),
       }
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::vfs::MemoryFs;
+
+  // Helpers.
+
+  fn fixture() -> MemoryFs {
+    let fs = MemoryFs::new();
+
+    for path in [
+      "root/src/main.rs",
+      "root/src/lib.rs",
+      "root/README.md",
+      "root/Cargo.toml",
+    ] {
+      fs.create_dir_all(Path::new(path).parent().unwrap())
+        .unwrap();
+      fs.write(Path::new(path), b"").unwrap();
+    }
+
+    fs
+  }
+
+  fn matched(traverser: &Traverser) -> Vec<String> {
+    let mut matched = traverser
+      .iter()
+      .map(|matched| {
+        let path = matched.unwrap().path;
+        path.strip_prefix("root").unwrap().display().to_string()
+      })
+      .collect::<Vec<_>>();
+
+    matched.sort();
+    matched
+  }
+
+  // Tests.
+
+  #[test]
+  fn match_any_pattern() {
+    let fs = fixture();
+
+    let traverser = Traverser::new("root")
+      .filesystem(&fs)
+      .ignore_dirs(true)
+      .pattern("src/*.rs")
+      .pattern("*.md");
+
+    assert_eq!(
+      matched(&traverser),
+      vec!["README.md", "src/lib.rs", "src/main.rs"]
+    );
+
+    // Calling `pattern` is additive as well.
+    let traverser = Traverser::new("root")
+      .filesystem(&fs)
+      .ignore_dirs(true)
+      .pattern("*.toml")
+      .pattern("*.md");
+
+    assert_eq!(matched(&traverser), vec!["Cargo.toml", "README.md"]);
+  }
+
+  #[test]
+  fn match_overlapping_patterns_once() {
+    let fs = fixture();
+
+    let traverser = Traverser::new("root")
+      .filesystem(&fs)
+      .ignore_dirs(true)
+      .pattern("**/*.rs")
+      .pattern("src/*")
+      .pattern("src/main.rs");
+
+    assert_eq!(matched(&traverser), vec!["src/lib.rs", "src/main.rs"]);
+  }
+}
@@ -1,16 +1,27 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Display};
-use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+use bytes::Bytes;
+use clap::ValueEnum;
+use crossterm::style::Stylize;
+use futures_util::{stream, Stream, StreamExt};
 use git2::build::CheckoutBuilder;
 use git2::Repository as GitRepository;
+use glob_match::glob_match;
+use itertools::Itertools;
 use miette::{Diagnostic, LabeledSpan, Report};
+use semver::{Version, VersionReq};
+use serde::Deserialize;
 use thiserror::Error;
 
-use crate::path::Traverser;
+use crate::auth::{self, CredentialProvider};
+use crate::logger::{info, verbose};
+use crate::path::{CollisionDetector, Normalization, Traverser};
+use crate::utils::net;
+use crate::vfs::Filesystem;
 
 /// Helper macro to create a [ParseError] in a slightly less verbose way.
 macro_rules! parse_error {
@@ -32,6 +43,9 @@ pub enum RepositoryError {
     #[source]
     source: io::Error,
   },
+  #[error("Subdirectory `{0}` doesn't exist in the repository.")]
+  #[diagnostic(code(decaff::repository::subdir))]
+  SubdirNotFound(PathBuf),
 }
 
 #[derive(Debug, Diagnostic, Error)]
@@ -48,6 +62,16 @@ pub enum FetchError {
   RequestFailedWithCode { code: u16, report: Report },
   #[error("Couldn't get the response body as bytes.")]
   RequestBodyFailed,
+  #[error("Download is incomplete: expected {expected} bytes, but got {actual}.")]
+  IncompleteDownload { expected: u64, actual: u64 },
+  #[error("Release assets are not supported for `{0}`.")]
+  ReleasesUnsupported(RepositoryHost),
+  #[error("Couldn't parse the release `{0}` response.")]
+  ReleaseParseFailed(String),
+  #[error("Release `{0}` has no `.tar.gz` assets.")]
+  ReleaseAssetNotFound(String),
+  #[error(transparent)]
+  Client(#[from] net::ClientError),
 }
 
 #[derive(Debug, Diagnostic, Error)]
@@ -57,6 +81,9 @@ pub enum RemoteError {
   CreateDetachedRemoteFailed { url: Report },
   #[error("Failed to connect the given remote.\n\n{url}")]
   ConnectionFailed { url: Report },
+  #[error("Failed to list refs of the given remote.\n\n{url}")]
+  #[diagnostic(help("Make sure the repository exists and you have access to it."))]
+  ListRefsFailed { url: Report },
 }
 
 #[derive(Debug, Diagnostic, Error)]
@@ -64,6 +91,12 @@ pub enum RemoteError {
 pub enum ReferenceError {
   #[error("Invalid reference: `{0}`.")]
   InvalidSelector(String),
+  #[error("Couldn't pin to `{hash}`: branch `{branch}` doesn't exist.")]
+  PinnedBranchNotFound { branch: String, hash: String },
+  #[error("No refs match the pattern `{0}`.")]
+  NoMatchingRefs(String),
+  #[error("Ambiguous pattern `{pattern}`, matching refs: {matches}.")]
+  AmbiguousPattern { pattern: String, matches: String },
 }
 
 #[derive(Debug, Diagnostic, Error)]
@@ -81,10 +114,12 @@ pub enum CheckoutError {
   SetHeadFailed(String),
   #[error("Failed to detach HEAD to `{0}`.")]
   DetachHeadFailed(String),
+  #[error("Failed to update submodule `{0}`.")]
+  SubmoduleUpdateFailed(String, #[source] git2::Error),
 }
 
 /// Supported hosts. [GitHub][RepositoryHost::GitHub] is the default one.
-#[derive(Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum RepositoryHost {
   #[default]
   GitHub,
@@ -92,15 +127,106 @@ pub enum RepositoryHost {
   BitBucket,
 }
 
+/// Host specification: canonical name, accepted aliases and URL templates.
+///
+/// URL templates support the following placeholders: `{user}`, `{repo}` and `{meta}`.
+#[derive(Debug)]
+pub struct HostSpec {
+  /// Host this specification describes.
+  pub host: RepositoryHost,
+  /// Canonical name of the host. Also used in source strings, e.g. `github:foo/bar`.
+  pub name: &'static str,
+  /// Accepted aliases, including the canonical name.
+  pub aliases: &'static [&'static str],
+  /// Template of the tarball URL.
+  pub tar_url: &'static str,
+  /// Template of the git repository URL.
+  pub git_url: &'static str,
+  /// Template of the release API URL, where `{meta}` is the release tag. `None` if the host
+  /// doesn't support release assets.
+  pub release_url: Option<&'static str>,
+}
+
+impl HostSpec {
+  /// Renders the given URL template.
+  fn render(template: &str, user: &str, repo: &str, meta: &str) -> String {
+    template
+      .replace("{user}", user)
+      .replace("{repo}", repo)
+      .replace("{meta}", meta)
+  }
+}
+
+/// Single source of truth for supported hosts.
+const HOSTS: [HostSpec; 3] = [
+  HostSpec {
+    host: RepositoryHost::GitHub,
+    name: "github",
+    aliases: &["github", "gh"],
+    tar_url: "https://github.com/{user}/{repo}/archive/{meta}.tar.gz",
+    git_url: "https://github.com/{user}/{repo}.git",
+    release_url: Some("https://api.github.com/repos/{user}/{repo}/releases/tags/{meta}"),
+  },
+  HostSpec {
+    host: RepositoryHost::GitLab,
+    name: "gitlab",
+    aliases: &["gitlab", "gl"],
+    tar_url: "https://gitlab.com/{user}/{repo}/-/archive/{meta}/{repo}.tar.gz",
+    git_url: "https://gitlab.com/{user}/{repo}.git",
+    release_url: Some("https://gitlab.com/api/v4/projects/{user}%2F{repo}/releases/{meta}"),
+  },
+  HostSpec {
+    host: RepositoryHost::BitBucket,
+    name: "bitbucket",
+    aliases: &["bitbucket", "bb"],
+    tar_url: "https://bitbucket.org/{user}/{repo}/get/{meta}.tar.gz",
+    git_url: "https://bitbucket.org/{user}/{repo}.git",
+    release_url: None,
+  },
+];
+
+impl RepositoryHost {
+  /// Returns specifications of all supported hosts.
+  pub fn all() -> &'static [HostSpec] {
+    &HOSTS
+  }
+
+  /// Returns the specification of the host.
+  pub fn spec(&self) -> &'static HostSpec {
+    Self::all()
+      .iter()
+      .find(|spec| spec.host == *self)
+      .expect("Every host should have a specification")
+  }
+
+  /// Finds a host by one of its aliases. Case-insensitive.
+  pub fn from_alias(alias: &str) -> Option<Self> {
+    let alias = alias.to_ascii_lowercase();
+
+    Self::all()
+      .iter()
+      .find(|spec| spec.aliases.contains(&alias.as_str()))
+      .map(|spec| spec.host)
+  }
+
+  /// Returns a human-readable list of accepted aliases, e.g. `github/gh, gitlab/gl, or
+  /// bitbucket/bb`.
+  pub fn aliases_hint() -> String {
+    let hosts = Self::all()
+      .iter()
+      .map(|spec| spec.aliases.join("/"))
+      .collect::<Vec<_>>();
+
+    match hosts.split_last() {
+      | Some((last, rest)) if !rest.is_empty() => format!("{}, or {last}", rest.join(", ")),
+      | _ => hosts.join(""),
+    }
+  }
+}
+
 impl Display for RepositoryHost {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    let host = match self {
-      | RepositoryHost::GitHub => "github",
-      | RepositoryHost::GitLab => "gitlab",
-      | RepositoryHost::BitBucket => "bitbucket",
-    };
-
-    write!(f, "{host}")
+    write!(f, "{}", self.spec().name)
   }
 }
 
@@ -118,12 +244,40 @@ impl Default for RepositoryMeta {
   }
 }
 
+impl RepositoryMeta {
+  /// Splits meta in the form of `<branch>@<hash>` into branch name and commit hash. Returns `None`
+  /// if meta isn't pinned, i.e. there's no `@` or the part after it isn't a (short) commit hash.
+  pub fn pinned(&self) -> Option<(&str, &str)> {
+    let (branch, hash) = self.0.rsplit_once('@')?;
+
+    (!branch.is_empty() && is_commit_hash(hash)).then_some((branch, hash))
+  }
+
+  /// Returns the release tag if meta is in the form of `release:<tag>`.
+  pub fn release(&self) -> Option<&str> {
+    self
+      .0
+      .strip_prefix("release:")
+      .filter(|tag| !tag.is_empty())
+  }
+}
+
 impl Display for RepositoryMeta {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     write!(f, "{}", self.0)
   }
 }
 
+/// Which kind of ref to prefer if a selector names both a tag and a branch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum RefPrecedence {
+  /// Prefer the tag, same as git does.
+  #[default]
+  Tag,
+  /// Prefer the branch.
+  Branch,
+}
+
 /// Represents a remote repository. Repositories of this kind need to be downloaded first.
 #[derive(Debug, PartialEq)]
 pub struct RemoteRepository {
@@ -132,6 +286,10 @@ pub struct RemoteRepository {
   pub repo: String,
   pub meta: RepositoryMeta,
   pub refs: HashMap<String, String>,
+  pub tags: HashSet<String>,
+  /// Hash of the ref meta names, if it names both a tag and a branch. Archives are downloaded by
+  /// this hash, since hosts may pick either of them by name.
+  pub resolved: Option<String>,
 }
 
 impl RemoteRepository {
@@ -143,20 +301,35 @@ impl RemoteRepository {
     Ok(Self { meta, ..repo })
   }
 
+  /// Creates new `RemoteRepository` on the given host, ignoring any host prefix in the `target`.
+  pub fn with_host(
+    target: String,
+    meta: Option<String>,
+    host: RepositoryHost,
+  ) -> Result<Self, ParseError> {
+    let repo = Self::parse(&target, Some(host))?;
+    let meta = meta.map_or(repo.meta, RepositoryMeta);
+
+    Ok(Self { meta, ..repo })
+  }
+
   /// Resolves a URL depending on the host and other repository fields.
   pub fn get_tar_url(&self) -> String {
-    let RemoteRepository { host, user, repo, meta, .. } = self;
+    let RemoteRepository { host, user, repo, .. } = self;
 
-    match host {
-      | RepositoryHost::GitHub => {
-        format!("https://github.com/{user}/{repo}/archive/{meta}.tar.gz")
-      },
-      | RepositoryHost::GitLab => {
-        format!("https://gitlab.com/{user}/{repo}/-/archive/{meta}/{repo}.tar.gz")
-      },
-      | RepositoryHost::BitBucket => {
-        format!("https://bitbucket.org/{user}/{repo}/get/{meta}.tar.gz")
-      },
+    HostSpec::render(host.spec().tar_url, user, repo, self.get_archive_ref())
+  }
+
+  /// Returns the ref to download the archive for. For pinned metas (`<branch>@<hash>`) and metas
+  /// naming both a tag and a branch that's the commit hash, otherwise the meta itself.
+  pub fn get_archive_ref(&self) -> &str {
+    if let Some(hash) = &self.resolved {
+      return hash;
+    }
+
+    match self.meta.pinned() {
+      | Some((_, hash)) if !self.refs.contains_key(&self.meta.0) => hash,
+      | _ => &self.meta.0,
     }
   }
 
@@ -164,57 +337,191 @@ impl RemoteRepository {
   pub fn get_git_url(&self) -> String {
     let RemoteRepository { host, user, repo, .. } = self;
 
-    match host {
-      | RepositoryHost::GitHub => format!("https://github.com/{user}/{repo}.git"),
-      | RepositoryHost::GitLab => format!("https://gitlab.com/{user}/{repo}.git"),
-      | RepositoryHost::BitBucket => format!("https://bitbucket.org/{user}/{repo}.git"),
-    }
+    HostSpec::render(host.spec().git_url, user, repo, "")
   }
 
   /// Returns the source string of the repository.
   pub fn get_source(&self) -> String {
-    let host = match self.host {
-      | RepositoryHost::GitHub => "github",
-      | RepositoryHost::GitLab => "gitlab",
-      | RepositoryHost::BitBucket => "bitbucket",
-    };
-
-    let user = &self.user;
-    let repo = &self.repo;
+    let RemoteRepository { host, user, repo, .. } = self;
 
     format!("{host}:{user}/{repo}")
   }
 
-  /// Fetches the refs of the remote repository.
-  pub fn fetch_refs(&mut self) -> Result<(), RemoteError> {
-    let git_url = self.get_git_url();
+  /// Fetches the refs of the remote repository. Annotated tags are stored with the hash of the
+  /// commit they point to rather than the tag object's, so tarball URLs and cache keys reference
+  /// the commit.
+  ///
+  /// If meta names both a tag and a branch, the one given by `precedence` is used, and its hash is
+  /// kept in [RemoteRepository::resolved], so the downloaded archive matches it as well.
+  ///
+  /// Credentials are requested from `credentials` if the remote requires authentication.
+  pub fn fetch_refs(
+    &mut self,
+    precedence: RefPrecedence,
+    credentials: &dyn CredentialProvider,
+  ) -> Result<(), RemoteError> {
+    let url = self.get_git_url();
+
+    verbose!("{}", format!("~ Listing refs of {url}").dim());
+
+    let heads = list_refs(&url, credentials)?;
+
+    self.extend_refs(
+      heads
+        .iter()
+        .map(|(name, oid)| (name.as_str(), oid.to_owned())),
+      precedence,
+    );
 
-    let mut remote = git2::Remote::create_detached(git_url.as_bytes()).map_err(|_| {
-      RemoteError::CreateDetachedRemoteFailed { url: miette::miette!("URL: {git_url}") }
-    })?;
+    Ok(())
+  }
 
-    let connection = remote
-      .connect_auth(git2::Direction::Fetch, None, None)
-      .map_err(|_| RemoteError::ConnectionFailed { url: miette::miette!("URL: {git_url}") })?;
+  /// Collects advertised refs into [RemoteRepository::refs], resolving ambiguous meta.
+  fn extend_refs<'a, I>(&mut self, heads: I, precedence: RefPrecedence)
+  where
+    I: IntoIterator<Item = (&'a str, String)>,
+  {
+    let (refs, tags, ambiguous) = collect_refs(heads, precedence);
+
+    self.refs.extend(refs);
+    self.tags.extend(tags);
+
+    if let Some(hash) = ambiguous
+      .contains(&self.meta.0)
+      .then(|| self.refs.get(&self.meta.0).cloned())
+      .flatten()
+    {
+      let kind = match precedence {
+        | RefPrecedence::Tag => "tag",
+        | RefPrecedence::Branch => "branch",
+      };
 
-    for head in connection.list().unwrap() {
-      let original = head.name();
+      let message = format!(
+        "? `{}` is both a tag and a branch, using the {kind}",
+        self.meta
+      );
+      info!("{}", message.yellow());
 
-      let name = (original == "HEAD")
-        .then_some("HEAD")
-        .or_else(|| original.strip_prefix("refs/heads/"))
-        .or_else(|| original.strip_prefix("refs/tags/"))
-        .map(str::to_string);
+      self.resolved = Some(hash);
+    }
+  }
 
-      if let Some(name) = name {
-        self.refs.insert(name, head.oid().to_string());
-      }
+  /// Resolves special selectors in meta to actual refs. Requires refs to be fetched first, so this
+  /// must be called after [RemoteRepository::fetch_refs] and before resolving the hash.
+  ///
+  /// - `latest` resolves to the highest semver tag that is not a pre-release, or to `HEAD` if there
+  ///   are no such tags.
+  /// - Semver requirements like `^1.2`, `~1.0` or `>=1.0, <2.0` resolve to the highest matching
+  ///   semver tag.
+  /// - Glob patterns like `v1.*` resolve to the highest matching semver tag, or to the only
+  ///   matching ref if none of them are semver tags.
+  ///
+  /// Refs that literally match the selector always take precedence.
+  pub fn resolve_meta(&mut self) -> Result<(), ReferenceError> {
+    let selector = self.meta.to_string();
+
+    if self.refs.contains_key(&selector) {
+      return Ok(());
+    }
+
+    if selector == "latest" {
+      self.meta = match self.find_latest_version(|version| version.pre.is_empty()) {
+        | Some(tag) => RepositoryMeta(tag),
+        | None => {
+          info!(
+            "{}",
+            "? No semver tags found, falling back to HEAD".yellow()
+          );
+          RepositoryMeta::default()
+        },
+      };
+    } else if is_version_req(&selector) {
+      let req = VersionReq::parse(&selector)
+        .map_err(|_| ReferenceError::InvalidSelector(selector.clone()))?;
+
+      let tag = self
+        .find_latest_version(|version| req.matches(version))
+        .ok_or(ReferenceError::InvalidSelector(selector))?;
+
+      self.meta = RepositoryMeta(tag);
+    } else if is_glob(&selector) {
+      self.meta = RepositoryMeta(self.find_matching_ref(&selector)?);
     }
 
     Ok(())
   }
 
-  /// Resolves a given reference to a commit hash.
+  /// Finds the ref matching the glob pattern, preferring the highest semver version. Fails if
+  /// there are no matches, or if the highest match can't be determined, i.e. there are several
+  /// matches without semver versions or with the same highest version (e.g. `v1.0.0` and `1.0.0`).
+  fn find_matching_ref(&self, pattern: &str) -> Result<String, ReferenceError> {
+    let matches = self
+      .refs
+      .keys()
+      .filter(|name| *name != "HEAD" && glob_match(pattern, name))
+      .sorted()
+      .collect::<Vec<_>>();
+
+    let ambiguous = |matches: &[&String]| {
+      ReferenceError::AmbiguousPattern {
+        pattern: pattern.to_string(),
+        matches: matches.iter().map(|name| format!("`{name}`")).join(", "),
+      }
+    };
+
+    let versions = matches
+      .iter()
+      .filter_map(|name| parse_version(name).map(|version| (version, *name)))
+      .collect::<Vec<_>>();
+
+    let Some(highest) = versions.iter().map(|(version, _)| version).max() else {
+      return match matches[..] {
+        | [] => Err(ReferenceError::NoMatchingRefs(pattern.to_string())),
+        | [name] => Ok(name.to_owned()),
+        | _ => Err(ambiguous(&matches)),
+      };
+    };
+
+    match versions
+      .iter()
+      .filter(|(version, _)| version == highest)
+      .map(|(_, name)| *name)
+      .collect::<Vec<_>>()[..]
+    {
+      | [name] => Ok(name.to_owned()),
+      | ref names => Err(ambiguous(names)),
+    }
+  }
+
+  /// Finds the tag with the highest semver version among tags accepted by the given predicate. Tags
+  /// with the same version (e.g. `v1.0.0` and `1.0.0`) are ordered by name.
+  fn find_latest_version<P>(&self, predicate: P) -> Option<String>
+  where
+    P: Fn(&Version) -> bool,
+  {
+    self
+      .tags
+      .iter()
+      .filter_map(|name| parse_version(name).map(|version| (version, name)))
+      .filter(|(version, _)| predicate(version))
+      .max()
+      .map(|(_, name)| name.to_owned())
+  }
+
+  /// Resolves a given reference to a commit hash. Selectors are tried in the following order:
+  ///
+  /// 1. Branch or tag name. Names that are both are resolved when fetching refs, according to the
+  ///    given [RefPrecedence].
+  /// 2. Release (`release:<tag>`), resolved using its tag.
+  /// 3. Branch pinned at a specific commit (`<branch>@<hash>`).
+  /// 4. Full or short (at least 7 characters) commit hash. If it's a prefix of a ref's hash, it's
+  ///    expanded to the full hash, otherwise it's used as is.
+  ///
+  /// If none of these match, the selector is invalid.
+  ///
+  /// Pinned metas (`<branch>@<hash>`) resolve to the given hash, as long as the branch exists. Note
+  /// that we can't check whether the commit is actually reachable from the branch, since we only
+  /// have the advertised refs.
   pub fn resolve_hash(&self) -> Result<String, ReferenceError> {
     let selector = self.meta.to_string();
 
@@ -222,22 +529,42 @@ impl RemoteRepository {
     if let Some(hash) = self.refs.get(&selector) {
       Ok(hash.to_owned())
     }
+    // Or it might be a release, which is resolved using its tag.
+    else if let Some(tag) = self.meta.release() {
+      self
+        .refs
+        .get(tag)
+        .cloned()
+        .ok_or_else(|| ReferenceError::InvalidSelector(tag.to_string()))
+    }
+    // Or it might be a branch pinned at a specific commit.
+    else if let Some((branch, hash)) = self.meta.pinned() {
+      let tip = self.refs.get(branch).ok_or_else(|| {
+        ReferenceError::PinnedBranchNotFound {
+          branch: branch.to_string(),
+          hash: hash.to_string(),
+        }
+      })?;
+
+      // Expand to the full hash if the branch tip is the pinned commit.
+      if tip.starts_with(hash) {
+        Ok(tip.to_owned())
+      } else {
+        Ok(hash.to_string())
+      }
+    }
     // Or it might be a (short) commit hash.
-    else if selector.len() >= 7 {
-      git2::Oid::from_str(&selector)
-        .map(|oid| {
-          let oid = oid.to_string();
-
-          // Try to find a full commit hash.
-          if let Some(full_hash) = self.refs.values().find(|hash| hash.starts_with(&oid)) {
-            full_hash.to_owned()
-          }
-          // At this point this is most likely a commit that's not a tip of any branch.
-          else {
-            selector.clone()
-          }
-        })
-        .map_err(|_| ReferenceError::InvalidSelector(selector))
+    else if is_commit_hash(&selector) {
+      let selector = selector.to_ascii_lowercase();
+
+      // Try to find a full commit hash.
+      if let Some(full_hash) = self.refs.values().find(|hash| hash.starts_with(&selector)) {
+        Ok(full_hash.to_owned())
+      }
+      // At this point this is most likely a commit that's not a tip of any branch.
+      else {
+        Ok(selector)
+      }
     }
     // Otherwise this is not a valid ref.
     else {
@@ -245,39 +572,337 @@ impl RemoteRepository {
     }
   }
 
-  /// Fetches the tarball using the resolved URL, and reads it into a vector of bytes.
-  pub async fn fetch(&self) -> Result<Vec<u8>, FetchError> {
-    let url = self.get_tar_url();
+  /// Returns the key to cache the tarball resolved to `hash` under. Release assets differ from the
+  /// source archive of the same commit, so they are keyed by the tag as well. Such keys never start
+  /// with a hash, so they don't match (short) hashes of source archives.
+  pub fn cache_key(&self, hash: &str) -> String {
+    match self.meta.release() {
+      | Some(tag) => {
+        let tag = tag
+          .chars()
+          .map(|char| {
+            if char.is_ascii_alphanumeric() || matches!(char, '.' | '-' | '_') {
+              char
+            } else {
+              '_'
+            }
+          })
+          .collect::<String>();
+
+        format!("release-{tag}-{hash}")
+      },
+      | None => hash.to_string(),
+    }
+  }
 
-    let response = reqwest::get(&url).await.map_err(|err| {
-      err.status().map_or(FetchError::RequestFailed, |status| {
-        FetchError::RequestFailedWithCode {
-          code: status.as_u16(),
-          report: miette::miette!("\n\nURL: {}", url.clone()),
-        }
-      })
-    })?;
+  /// Fetches the tarball using the resolved URL, authenticating with `credentials`.
+  pub async fn fetch(&self, credentials: &dyn CredentialProvider) -> Result<Vec<u8>, FetchError> {
+    let url = self.resolve_download_url(credentials).await?;
 
-    let status = response.status();
+    download(&url, credentials).await
+  }
 
-    if !status.is_success() {
-      let code = status.as_u16();
+  /// Fetches the tarball using the resolved URL, returning the response body as a stream of chunks.
+  pub async fn fetch_stream(
+    &self,
+    credentials: &dyn CredentialProvider,
+  ) -> Result<impl Stream<Item = Result<Bytes, FetchError>>, FetchError> {
+    let url = self.resolve_download_url(credentials).await?;
 
-      let report = if code == 404 {
-        miette::miette!("The requested branch, tag or commit was not found.\n\nURL: {url}")
-      } else {
-        miette::miette!("\n\nURL: {url}")
-      };
+    download_stream(&url, credentials).await
+  }
+
+  /// Resolves the URL to download the tarball from. For releases (`release:<tag>`), that's the
+  /// release asset's URL instead of the source archive's.
+  async fn resolve_download_url(
+    &self,
+    credentials: &dyn CredentialProvider,
+  ) -> Result<String, FetchError> {
+    if let Some(tag) = self.meta.release() {
+      let RemoteRepository { host, user, repo, .. } = self;
+
+      let template = host
+        .spec()
+        .release_url
+        .ok_or(FetchError::ReleasesUnsupported(*host))?;
+
+      let url = HostSpec::render(template, user, repo, tag);
+
+      fetch_release_asset(*host, &url, tag, credentials).await
+    } else {
+      Ok(self.get_tar_url())
+    }
+  }
+}
+
+/// Lists refs advertised by the remote at the given `git_url` as pairs of ref names and hashes.
+fn list_refs(
+  git_url: &str,
+  credentials: &dyn CredentialProvider,
+) -> Result<Vec<(String, String)>, RemoteError> {
+  let mut remote = git2::Remote::create_detached(git_url.as_bytes()).map_err(|_| {
+    RemoteError::CreateDetachedRemoteFailed { url: miette::miette!("URL: {git_url}") }
+  })?;
+
+  // Let libgit2 pick up the proxy configuration, including the proxy environment variables.
+  let mut proxy = git2::ProxyOptions::new();
+  proxy.auto();
+
+  let connection = remote
+    .connect_auth(
+      git2::Direction::Fetch,
+      Some(auth::git_callbacks(credentials)),
+      Some(proxy),
+    )
+    .map_err(|_| RemoteError::ConnectionFailed { url: miette::miette!("URL: {git_url}") })?;
+
+  let heads = connection
+    .list()
+    .map_err(|_| RemoteError::ListRefsFailed { url: miette::miette!("URL: {git_url}") })?
+    .iter()
+    .map(|head| (head.name().to_string(), head.oid().to_string()))
+    .collect();
+
+  Ok(heads)
+}
 
-      return Err(FetchError::RequestFailedWithCode { code, report });
+/// Collects advertised refs into a map of ref names (branches and tags without prefixes) and
+/// hashes, along with the names of tags. Names of both a tag and a branch map to the hash of the
+/// ref preferred by `precedence`, and are returned separately as well.
+///
+/// For annotated tags, the advertised `refs/tags/<name>` points to the tag object rather than the
+/// commit, so the peeled `refs/tags/<name>^{}` entry is preferred when present.
+fn collect_refs<'a, I>(
+  heads: I,
+  precedence: RefPrecedence,
+) -> (HashMap<String, String>, HashSet<String>, HashSet<String>)
+where
+  I: IntoIterator<Item = (&'a str, String)>,
+{
+  let mut branches = HashMap::new();
+  let mut tags = HashMap::new();
+  let mut peeled = HashSet::new();
+
+  for (original, oid) in heads {
+    let peeled_tag = original
+      .strip_prefix("refs/tags/")
+      .and_then(|tag| tag.strip_suffix("^{}"));
+
+    if let Some(tag) = peeled_tag {
+      peeled.insert(tag.to_string());
+      tags.insert(tag.to_string(), oid);
+
+      continue;
     }
 
-    response
-      .bytes()
-      .await
-      .map(|bytes| bytes.to_vec())
-      .map_err(|_| FetchError::RequestBodyFailed)
+    if let Some(tag) = original.strip_prefix("refs/tags/") {
+      if !peeled.contains(tag) {
+        tags.insert(tag.to_string(), oid);
+      }
+    } else if let Some(branch) = original.strip_prefix("refs/heads/") {
+      branches.insert(branch.to_string(), oid);
+    } else if original == "HEAD" {
+      branches.insert(original.to_string(), oid);
+    }
   }
+
+  let ambiguous = tags
+    .keys()
+    .filter(|tag| branches.contains_key(*tag))
+    .cloned()
+    .collect();
+
+  let names = tags.keys().cloned().collect();
+
+  let refs = match precedence {
+    | RefPrecedence::Tag => branches.into_iter().chain(tags).collect(),
+    | RefPrecedence::Branch => tags.into_iter().chain(branches).collect(),
+  };
+
+  (refs, names, ambiguous)
+}
+
+/// Checks if the selector looks like a full or short (at least 7 characters) commit hash.
+fn is_commit_hash(selector: &str) -> bool {
+  (7..=40).contains(&selector.len()) && selector.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+/// Checks if the selector looks like a semver requirement rather than a ref name.
+fn is_version_req(selector: &str) -> bool {
+  selector.starts_with(['^', '~', '>', '<', '=', '*']) || selector.contains(',')
+}
+
+/// Checks if the selector looks like a glob pattern rather than a ref name.
+fn is_glob(selector: &str) -> bool {
+  selector.contains(['*', '?', '[', '{'])
+}
+
+/// Parses a ref name as a semver version, allowing an optional `v` prefix.
+fn parse_version(name: &str) -> Option<Version> {
+  let version = name.strip_prefix(['v', 'V']).unwrap_or(name);
+
+  Version::parse(version).ok()
+}
+
+/// GitHub release, only the parts we need.
+#[derive(Debug, Deserialize)]
+struct GitHubRelease {
+  assets: Vec<GitHubAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitHubAsset {
+  name: String,
+  browser_download_url: String,
+}
+
+/// GitLab release, only the parts we need.
+#[derive(Debug, Deserialize)]
+struct GitLabRelease {
+  assets: GitLabAssets,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabAssets {
+  links: Vec<GitLabLink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabLink {
+  name: String,
+  url: String,
+}
+
+/// Queries the release API and returns the URL of the release's tarball asset. An asset named
+/// `template.tar.gz` is preferred, otherwise the first `.tar.gz` asset is used.
+async fn fetch_release_asset(
+  host: RepositoryHost,
+  url: &str,
+  tag: &str,
+  credentials: &dyn CredentialProvider,
+) -> Result<String, FetchError> {
+  let bytes = download(url, credentials).await?;
+  let parse_error = |_| FetchError::ReleaseParseFailed(tag.to_string());
+
+  let assets: Vec<(String, String)> = match host {
+    | RepositoryHost::GitHub => {
+      let release: GitHubRelease = serde_json::from_slice(&bytes).map_err(parse_error)?;
+
+      release
+        .assets
+        .into_iter()
+        .map(|asset| (asset.name, asset.browser_download_url))
+        .collect()
+    },
+    | RepositoryHost::GitLab => {
+      let release: GitLabRelease = serde_json::from_slice(&bytes).map_err(parse_error)?;
+
+      release
+        .assets
+        .links
+        .into_iter()
+        .map(|link| (link.name, link.url))
+        .collect()
+    },
+    | RepositoryHost::BitBucket => return Err(FetchError::ReleasesUnsupported(host)),
+  };
+
+  assets
+    .iter()
+    .find(|(name, _)| name == "template.tar.gz")
+    .or_else(|| assets.iter().find(|(name, _)| name.ends_with(".tar.gz")))
+    .map(|(_, url)| url.to_owned())
+    .ok_or_else(|| FetchError::ReleaseAssetNotFound(tag.to_string()))
+}
+
+/// Downloads the given URL and reads the response body into a vector of bytes.
+async fn download(url: &str, credentials: &dyn CredentialProvider) -> Result<Vec<u8>, FetchError> {
+  let mut stream = Box::pin(download_stream(url, credentials).await?);
+  let mut bytes = Vec::new();
+
+  while let Some(chunk) = stream.next().await {
+    bytes.extend_from_slice(&chunk?);
+  }
+
+  Ok(bytes)
+}
+
+/// Downloads the given URL, returning the response body as a stream of chunks. If the response
+/// advertises `Content-Length`, the number of received bytes is checked against it.
+async fn download_stream(
+  url: &str,
+  credentials: &dyn CredentialProvider,
+) -> Result<impl Stream<Item = Result<Bytes, FetchError>>, FetchError> {
+  verbose!("{}", format!("~ Downloading {url}").dim());
+
+  let client = net::client()?;
+  let mut request = client.get(url);
+
+  // Bearer tokens are marked as sensitive, so they don't show up in debug output.
+  if let Some(credentials) = reqwest::Url::parse(url).ok().and_then(|url| {
+    url
+      .host_str()
+      .and_then(|host| credentials.credentials(host))
+  }) {
+    request = request.bearer_auth(credentials.secret.expose());
+  }
+
+  let response = request.send().await.map_err(|err| {
+    err.status().map_or(FetchError::RequestFailed, |status| {
+      FetchError::RequestFailedWithCode {
+        code: status.as_u16(),
+        report: miette::miette!("\n\nURL: {url}"),
+      }
+    })
+  })?;
+
+  let status = response.status();
+
+  if !status.is_success() {
+    let code = status.as_u16();
+
+    let report = if code == 404 {
+      miette::miette!("The requested branch, tag or commit was not found.\n\nURL: {url}")
+    } else {
+      miette::miette!("\n\nURL: {url}")
+    };
+
+    return Err(FetchError::RequestFailedWithCode { code, report });
+  }
+
+  let expected = response.content_length();
+
+  let chunks = stream::unfold(Some((response, 0)), move |state| {
+    async move {
+      let (mut response, received) = state?;
+
+      match response.chunk().await {
+        | Ok(Some(chunk)) => {
+          let received = received + chunk.len() as u64;
+          Some((Ok(chunk), Some((response, received))))
+        },
+        // Body is complete, but might be shorter than advertised.
+        | Ok(None) => {
+          expected
+            .filter(|expected| *expected != received)
+            .map(|expected| {
+              let err = FetchError::IncompleteDownload { expected, actual: received };
+              (Err(err), None)
+            })
+        },
+        // Body got cut off.
+        | Err(_) => {
+          let err = expected.map_or(FetchError::RequestBodyFailed, |expected| {
+            FetchError::IncompleteDownload { expected, actual: received }
+          });
+
+          Some((Err(err), None))
+        },
+      }
+    }
+  });
+
+  Ok(chunks)
 }
 
 impl FromStr for RemoteRepository {
@@ -285,6 +910,14 @@ impl FromStr for RemoteRepository {
 
   /// Parses a `&str` into a `RemoteRepository`.
   fn from_str(input: &str) -> Result<Self, Self::Err> {
+    RemoteRepository::parse(input, None)
+  }
+}
+
+impl RemoteRepository {
+  /// Parses a `&str` into a `RemoteRepository`. If the `forced_host` is given, host prefixes are
+  /// not recognized and the input is treated as `user/repo` literally.
+  fn parse(input: &str, forced_host: Option<RepositoryHost>) -> Result<Self, ParseError> {
     #[inline(always)]
     fn is_valid_user(ch: char) -> bool {
       ch.is_ascii_alphanumeric() || ch == '_' || ch == '-'
@@ -298,28 +931,31 @@ impl FromStr for RemoteRepository {
     let source = input.trim();
 
     // Parse host if present or use default otherwise.
-    let (host, (input, offset)) = if let Some((host, rest)) = source.split_once(':') {
+    // Host prefix can't contain slashes or hashes, otherwise the colon belongs to the ref.
+    let prefix = source
+      .split_once(':')
+      .filter(|(host, _)| forced_host.is_none() && !host.contains(['/', '#']));
+
+    let (host, (input, offset)) = if let Some((host, rest)) = prefix {
       let host = host.to_ascii_lowercase();
       let next_offset = host.len() + 1;
 
-      match host.as_str() {
-        | "github" | "gh" => (RepositoryHost::GitHub, (rest, next_offset)),
-        | "gitlab" | "gl" => (RepositoryHost::GitLab, (rest, next_offset)),
-        | "bitbucket" | "bb" => (RepositoryHost::BitBucket, (rest, next_offset)),
-        | _ => {
+      match RepositoryHost::from_alias(&host) {
+        | Some(host) => (host, (rest, next_offset)),
+        | None => {
           return Err(parse_error!(
             source = source.to_string(),
             code = "decaff::repository::parse",
             labels = vec![LabeledSpan::at(
               (0, host.len()),
-              "must be one of: github/gh, gitlab/gl, or bitbucket/bb"
+              format!("must be one of: {}", RepositoryHost::aliases_hint())
             )],
             "Invalid host: `{host}`."
           ));
         },
       }
     } else {
-      (RepositoryHost::default(), (source, 0))
+      (forced_host.unwrap_or_default(), (source, 0))
     };
 
     // Parse user name.
@@ -384,8 +1020,18 @@ impl FromStr for RemoteRepository {
       });
 
     let refs = HashMap::default();
-
-    Ok(RemoteRepository { host, user, repo, meta, refs })
+    let tags = HashSet::default();
+    let resolved = None;
+
+    Ok(RemoteRepository {
+      host,
+      user,
+      repo,
+      meta,
+      refs,
+      tags,
+      resolved,
+    })
   }
 }
 
@@ -409,18 +1055,41 @@ impl LocalRepository {
     }
   }
 
-  /// Copies the repository into the `destination` directory.
-  pub fn copy(&self, destination: &Path) -> Result<(), RepositoryError> {
+  /// Checks whether the source is a git repository rather than a plain directory.
+  pub fn is_git(&self) -> bool {
+    GitRepository::open(&self.source).is_ok()
+  }
+
+  /// Copies the repository into the `destination` directory on the given filesystem, normalizing
+  /// filenames according to the given [Normalization] policy. The source is always read from disk.
+  pub fn copy_into(
+    &self,
+    fs: &dyn Filesystem,
+    destination: &Path,
+    normalization: Normalization,
+  ) -> Result<(), RepositoryError> {
     let traverser = Traverser::new(self.source.to_owned())
       .pattern("**/*")
       .ignore_dirs(true)
       .contents_first(true);
 
+    let mut detector = CollisionDetector::new();
+
     for matched in traverser.iter().flatten() {
-      let target = destination.join(&matched.captured);
+      let target = destination.join(normalization.apply(&matched.captured));
+
+      if let Some(seen) = detector.check(&target) {
+        let message = format!(
+          "? Colliding filenames: {} and {}",
+          seen.display(),
+          target.display()
+        );
+
+        info!("{}", message.yellow());
+      }
 
       if let Some(parent) = target.parent() {
-        fs::create_dir_all(parent).map_err(|source| {
+        fs.create_dir_all(parent).map_err(|source| {
           RepositoryError::Io {
             message: format!(
               "Failed to create directory structure for '{}'.",
@@ -430,7 +1099,13 @@ impl LocalRepository {
           }
         })?;
 
-        fs::copy(&matched.path, &target).map_err(|source| {
+        // Copying natively preserves permissions.
+        let copied = match fs.local_path(&target) {
+          | Some(target) => std::fs::copy(&matched.path, target).map(|_| ()),
+          | None => std::fs::read(&matched.path).and_then(|contents| fs.write(&target, &contents)),
+        };
+
+        copied.map_err(|source| {
           RepositoryError::Io {
             message: format!(
               "Failed to copy from '{}' to '{}'.",
@@ -446,8 +1121,56 @@ impl LocalRepository {
     Ok(())
   }
 
-  /// Checks out the repository located at the `destination`.
-  pub fn checkout(&self, destination: &Path) -> Result<(), CheckoutError> {
+  /// Replaces the repository copied into the `destination` directory on the given filesystem with
+  /// its `subdir`.
+  pub fn extract_subdir_in(
+    &self,
+    fs: &dyn Filesystem,
+    destination: &Path,
+    subdir: &Path,
+  ) -> Result<(), RepositoryError> {
+    if !fs.is_dir(&destination.join(subdir)) {
+      return Err(RepositoryError::SubdirNotFound(subdir.to_path_buf()));
+    }
+
+    // Move the whole copy aside first, since the subdirectory is nested inside the destination.
+    let mut staging = destination.as_os_str().to_owned();
+    staging.push(".decaff");
+
+    let staging = PathBuf::from(staging);
+
+    fs.rename(destination, &staging).map_err(|source| {
+      RepositoryError::Io {
+        message: format!("Failed to move '{}' aside.", destination.display()),
+        source,
+      }
+    })?;
+
+    fs.rename(&staging.join(subdir), destination)
+      .map_err(|source| {
+        RepositoryError::Io {
+          message: format!(
+            "Failed to move '{}' to '{}'.",
+            subdir.display(),
+            destination.display()
+          ),
+          source,
+        }
+      })?;
+
+    fs.remove_dir_all(&staging).map_err(|source| {
+      RepositoryError::Io {
+        message: format!("Failed to remove '{}'.", staging.display()),
+        source,
+      }
+    })?;
+
+    Ok(())
+  }
+
+  /// Checks out the repository located at the `destination`. Returns the hash of the checked out
+  /// commit.
+  pub fn checkout(&self, destination: &Path) -> Result<String, CheckoutError> {
     let meta = self.meta.to_string();
     let head = "HEAD".to_string();
 
@@ -466,7 +1189,7 @@ impl LocalRepository {
         .and_then(|reference| reference.name().map(str::to_string))
         .unwrap_or(head)
     } else {
-      head
+      meta
     };
 
     // Try to find (parse revision) the desired reference: branch, tag or commit. They are encoded
@@ -511,13 +1234,110 @@ impl LocalRepository {
       },
     }
 
-    Ok(())
+    // Annotated tags point to tag objects, so peel them to get the commit hash.
+    let hash = object
+      .peel_to_commit()
+      .map_or_else(|_| object.id(), |commit| commit.id());
+
+    Ok(hash.to_string())
+  }
+
+  /// Initializes and updates submodules of the repository located at the `destination`,
+  /// recursively. Returns paths of the updated submodules relative to the `destination`.
+  pub fn update_submodules(
+    &self,
+    destination: &Path,
+    credentials: &dyn CredentialProvider,
+  ) -> Result<Vec<PathBuf>, CheckoutError> {
+    let repository = GitRepository::open(destination).map_err(CheckoutError::OpenFailed)?;
+    update_submodules(&repository, Path::new(""), credentials)
+  }
+}
+
+/// Initializes and updates submodules of the given repository, recursively. Paths of submodules are
+/// returned prefixed with the given `prefix`.
+fn update_submodules(
+  repository: &GitRepository,
+  prefix: &Path,
+  credentials: &dyn CredentialProvider,
+) -> Result<Vec<PathBuf>, CheckoutError> {
+  let mut updated = Vec::new();
+
+  let submodules = repository
+    .submodules()
+    .map_err(|err| CheckoutError::SubmoduleUpdateFailed(prefix.display().to_string(), err))?;
+
+  for mut submodule in submodules {
+    let path = prefix.join(submodule.path());
+    let failed = |err| CheckoutError::SubmoduleUpdateFailed(path.display().to_string(), err);
+
+    // Submodules are fetched the same way as refs of remote repositories, i.e. picking up proxies
+    // and credentials.
+    let mut proxy = git2::ProxyOptions::new();
+    proxy.auto();
+
+    let mut fetch = git2::FetchOptions::new();
+    fetch.proxy_options(proxy);
+    fetch.remote_callbacks(auth::git_callbacks(credentials));
+
+    let mut options = git2::SubmoduleUpdateOptions::new();
+    options.fetch(fetch);
+
+    submodule.update(true, Some(&mut options)).map_err(failed)?;
+
+    let nested = submodule.open().map_err(failed)?;
+
+    updated.push(path.clone());
+    updated.extend(update_submodules(&nested, &path, credentials)?);
   }
+
+  Ok(updated)
 }
 
 #[cfg(test)]
 mod tests {
+  use std::fs;
+
+  use tempfile::TempDir;
+
   use super::*;
+  use crate::auth::{Anonymous, Credentials};
+  use crate::utils::testing;
+  use crate::vfs::RealFs;
+
+  // Helpers.
+
+  /// Commits a single file with the given contents on top of HEAD.
+  fn commit(repository: &GitRepository, contents: &str) -> git2::Oid {
+    let root = repository.workdir().unwrap();
+    let signature = git2::Signature::now("decaff", "decaff@example.com").unwrap();
+
+    fs::write(root.join("version.txt"), contents).unwrap();
+
+    let mut index = repository.index().unwrap();
+    index.add_path(Path::new("version.txt")).unwrap();
+    index.write().unwrap();
+
+    let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+    let parent = repository
+      .head()
+      .ok()
+      .and_then(|head| head.peel_to_commit().ok());
+    let parents = parent.iter().collect::<Vec<_>>();
+
+    repository
+      .commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        contents,
+        &tree,
+        &parents,
+      )
+      .unwrap()
+  }
+
+  // Tests.
 
   #[test]
   fn parse_remote_default() {
@@ -528,7 +1348,9 @@ mod tests {
         user: "foo".to_string(),
         repo: "bar".to_string(),
         meta: RepositoryMeta::default(),
-        refs: HashMap::default()
+        refs: HashMap::default(),
+        tags: HashSet::default(),
+        resolved: None
       })
     );
   }
@@ -582,6 +1404,10 @@ mod tests {
       ("foo/bar", RepositoryMeta::default()),
       ("foo/bar#foo", RepositoryMeta("foo".to_string())),
       ("foo/bar#4a5a56fd", RepositoryMeta("4a5a56fd".to_string())),
+      (
+        "foo/bar#release:v1.2.0",
+        RepositoryMeta("release:v1.2.0".to_string()),
+      ),
       (
         "foo/bar#feat/some-feature-name",
         RepositoryMeta("feat/some-feature-name".to_string()),
@@ -596,6 +1422,8 @@ mod tests {
           user: "foo".to_string(),
           repo: "bar".to_string(),
           refs: HashMap::default(),
+          tags: HashSet::default(),
+          resolved: None,
           meta,
         })
       );
@@ -621,7 +1449,9 @@ mod tests {
           user: "foo".to_string(),
           repo: "bar".to_string(),
           meta: RepositoryMeta::default(),
-          refs: HashMap::default()
+          refs: HashMap::default(),
+          tags: HashSet::default(),
+          resolved: None
         })
       );
     }
@@ -636,7 +1466,9 @@ mod tests {
         user: "foo".to_string(),
         repo: "bar".to_string(),
         meta: RepositoryMeta::default(),
-        refs: HashMap::default()
+        refs: HashMap::default(),
+        tags: HashSet::default(),
+        resolved: None
       })
     );
   }
@@ -660,9 +1492,681 @@ mod tests {
           user: user.to_string(),
           repo: repo.to_string(),
           meta: RepositoryMeta::default(),
-          refs: HashMap::default()
+          refs: HashMap::default(),
+          tags: HashSet::default(),
+          resolved: None
         })
       );
     }
   }
+
+  #[tokio::test]
+  async fn download_detects_truncated_body() {
+    let url = testing::serve_once(
+      "HTTP/1.1 200 OK\r\nContent-Length: 100\r\nConnection: close\r\n\r\ntruncated",
+    );
+
+    assert!(matches!(
+      download(&url, &Anonymous).await,
+      Err(FetchError::IncompleteDownload { expected: 100, actual: 9 })
+    ));
+  }
+
+  #[tokio::test]
+  async fn download_accepts_complete_body() {
+    let url = testing::serve_once("HTTP/1.1 200 OK\r\nContent-Length: 8\r\n\r\ncomplete");
+
+    assert_eq!(
+      download(&url, &Anonymous).await.unwrap(),
+      b"complete".to_vec()
+    );
+  }
+
+  #[tokio::test]
+  async fn download_uses_provided_credentials() {
+    #[derive(Debug)]
+    struct Token;
+
+    impl CredentialProvider for Token {
+      fn credentials(&self, host: &str) -> Option<Credentials> {
+        (host == "127.0.0.1").then(|| Credentials::new("decaff", "s3cr3t"))
+      }
+    }
+
+    let (url, request) =
+      testing::serve_once_capturing("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok");
+
+    assert_eq!(download(&url, &Token).await.unwrap(), b"ok".to_vec());
+
+    let request = request.recv().unwrap().to_lowercase();
+
+    assert!(request.contains("authorization: bearer s3cr3t"));
+  }
+
+  #[test]
+  fn parse_pinned_meta() {
+    let cases = [
+      ("main@4a5a56fd", Some(("main", "4a5a56fd"))),
+      (
+        "feat/some-feature@4a5a56fd",
+        Some(("feat/some-feature", "4a5a56fd")),
+      ),
+      ("main", None),
+      ("main@", None),
+      ("@4a5a56fd", None),
+      ("main@4a5a5", None),
+      ("main@not-a-hash", None),
+      ("@scope/pkg@1.0.0", None),
+    ];
+
+    for (input, expected) in cases {
+      assert_eq!(RepositoryMeta(input.to_string()).pinned(), expected);
+    }
+  }
+
+  #[test]
+  fn resolve_pinned_meta() {
+    let tip = "4a5a56fd0c9e3b1a3e6f2c1d9b8a7f6e5d4c3b2a".to_string();
+    let pinned = "1b2c3d4e5f";
+
+    let mut repository =
+      RemoteRepository::new("foo/bar".to_string(), Some(format!("main@{pinned}"))).unwrap();
+
+    repository.refs.insert("main".to_string(), tip.clone());
+
+    assert_eq!(repository.resolve_hash().unwrap(), pinned);
+    assert_eq!(repository.get_archive_ref(), pinned);
+
+    repository.meta = RepositoryMeta("main@4a5a56fd".to_string());
+    assert_eq!(repository.resolve_hash().unwrap(), tip);
+
+    repository.meta = RepositoryMeta(format!("dev@{pinned}"));
+
+    assert!(matches!(
+      repository.resolve_hash(),
+      Err(ReferenceError::PinnedBranchNotFound { .. })
+    ));
+  }
+
+  #[test]
+  fn force_host() {
+    let repo = RemoteRepository::with_host("gitlab/foo".to_string(), None, RepositoryHost::GitHub);
+
+    assert_eq!(
+      repo.map_err(|report| report.to_string()),
+      Ok(RemoteRepository {
+        host: RepositoryHost::GitHub,
+        user: "gitlab".to_string(),
+        repo: "foo".to_string(),
+        meta: RepositoryMeta::default(),
+        refs: HashMap::default(),
+        tags: HashSet::default(),
+        resolved: None
+      })
+    );
+
+    let repo =
+      RemoteRepository::with_host("github/foo".to_string(), None, RepositoryHost::GitLab).unwrap();
+
+    assert_eq!(repo.host, RepositoryHost::GitLab);
+    assert_eq!(repo.user, "github");
+
+    // Host prefixes are not recognized at all.
+    assert!(RemoteRepository::with_host(
+      "gitlab:foo/bar".to_string(),
+      None,
+      RepositoryHost::GitHub
+    )
+    .is_err());
+  }
+
+  #[test]
+  fn host_aliases_roundtrip() {
+    for spec in RepositoryHost::all() {
+      assert_eq!(spec.host.to_string(), spec.name);
+      assert!(spec.aliases.contains(&spec.name));
+
+      for alias in spec.aliases {
+        assert_eq!(RepositoryHost::from_alias(alias), Some(spec.host));
+        assert_eq!(
+          RemoteRepository::from_str(&format!("{alias}:foo/bar"))
+            .map(|repository| repository.host)
+            .map_err(|report| report.to_string()),
+          Ok(spec.host)
+        );
+      }
+    }
+
+    assert_eq!(
+      RepositoryHost::aliases_hint(),
+      "github/gh, gitlab/gl, or bitbucket/bb"
+    );
+  }
+
+  #[tokio::test]
+  async fn release_asset_resolution() {
+    let body = r#"{
+      "assets": [
+        { "name": "checksums.txt", "browser_download_url": "https://example.com/checksums.txt" },
+        { "name": "extra.tar.gz", "browser_download_url": "https://example.com/extra.tar.gz" },
+        { "name": "template.tar.gz", "browser_download_url": "https://example.com/template.tar.gz" }
+      ]
+    }"#;
+
+    let url = testing::serve_once(format!(
+      "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+      body.len()
+    ));
+
+    assert_eq!(
+      fetch_release_asset(RepositoryHost::GitHub, &url, "v1.2.0", &Anonymous)
+        .await
+        .unwrap(),
+      "https://example.com/template.tar.gz"
+    );
+  }
+
+  #[tokio::test]
+  async fn release_asset_missing() {
+    let body =
+      r#"{ "assets": { "links": [{ "name": "notes.md", "url": "https://example.com" }] } }"#;
+
+    let url = testing::serve_once(format!(
+      "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+      body.len()
+    ));
+
+    assert!(matches!(
+      fetch_release_asset(RepositoryHost::GitLab, &url, "v1.2.0", &Anonymous).await,
+      Err(FetchError::ReleaseAssetNotFound(tag)) if tag == "v1.2.0"
+    ));
+  }
+
+  #[test]
+  fn resolve_latest_version() {
+    let mut repository =
+      RemoteRepository::new("foo/bar".to_string(), Some("latest".to_string())).unwrap();
+
+    // Pre-releases and branches named like versions are not considered.
+    let heads = [
+      ("HEAD", "0000000"),
+      ("refs/tags/v1.0.0", "1111111"),
+      ("refs/tags/v1.2.0", "2222222"),
+      ("refs/tags/v1.3.0-rc.1", "3333333"),
+      ("refs/heads/v2.0.0", "4444444"),
+      ("refs/heads/nightly", "5555555"),
+    ];
+
+    repository.extend_refs(
+      heads.map(|(name, oid)| (name, oid.to_string())),
+      RefPrecedence::default(),
+    );
+
+    repository.resolve_meta().unwrap();
+
+    assert_eq!(repository.meta, RepositoryMeta("v1.2.0".to_string()));
+    assert_eq!(repository.resolve_hash().unwrap(), "2222222");
+  }
+
+  #[test]
+  fn resolve_latest_version_with_equal_versions() {
+    let heads = [
+      ("refs/tags/1.0.0", "1111111"),
+      ("refs/tags/v1.0.0", "2222222"),
+    ];
+
+    // Tags of the same version are resolved the same way regardless of their order.
+    for heads in [heads, [heads[1], heads[0]]] {
+      let mut repository =
+        RemoteRepository::new("foo/bar".to_string(), Some("latest".to_string())).unwrap();
+
+      repository.extend_refs(
+        heads.map(|(name, oid)| (name, oid.to_string())),
+        RefPrecedence::default(),
+      );
+
+      repository.resolve_meta().unwrap();
+
+      assert_eq!(repository.meta, RepositoryMeta("v1.0.0".to_string()));
+    }
+  }
+
+  #[test]
+  fn resolve_latest_without_versions() {
+    let mut repository =
+      RemoteRepository::new("foo/bar".to_string(), Some("latest".to_string())).unwrap();
+
+    repository.extend_refs(
+      [
+        ("refs/heads/nightly", "3333333".to_string()),
+        ("refs/tags/v1.0.0-beta.1", "4444444".to_string()),
+      ],
+      RefPrecedence::default(),
+    );
+
+    repository.resolve_meta().unwrap();
+
+    assert_eq!(repository.meta, RepositoryMeta::default());
+  }
+
+  #[test]
+  fn resolve_version_requirements() {
+    let cases = [
+      ("^1.2", Some("v1.4.2")),
+      ("~1.2", Some("v1.2.9")),
+      ("~1.0", Some("1.0.5")),
+      (">=1.0, <2.0", Some("v1.4.2")),
+      (">=2.0", Some("v2.0.0")),
+      ("^3", None),
+      ("^foo", None),
+    ];
+
+    for (selector, expected) in cases {
+      let mut repository =
+        RemoteRepository::new("foo/bar".to_string(), Some(selector.to_string())).unwrap();
+
+      for name in ["v0.9.0", "1.0.5", "v1.2.9", "v1.4.2", "v2.0.0", "main"] {
+        repository
+          .refs
+          .insert(name.to_string(), format!("{name}-hash"));
+
+        if name != "main" {
+          repository.tags.insert(name.to_string());
+        }
+      }
+
+      match expected {
+        | Some(tag) => {
+          repository.resolve_meta().unwrap();
+          assert_eq!(repository.meta, RepositoryMeta(tag.to_string()));
+        },
+        | None => {
+          assert!(matches!(
+            repository.resolve_meta(),
+            Err(ReferenceError::InvalidSelector(_))
+          ));
+        },
+      }
+    }
+  }
+
+  #[test]
+  fn resolve_glob_patterns() {
+    let refs = [
+      "v1.2.0",
+      "v1.9.3",
+      "v1.10.0",
+      "v1.10.0-rc.1",
+      "v2.0.0",
+      "release-a",
+      "release-b",
+      "feature/one",
+      "main",
+    ];
+
+    let resolve = |selector: &str| {
+      let mut repository =
+        RemoteRepository::new("foo/bar".to_string(), Some(selector.to_string())).unwrap();
+
+      for name in refs {
+        repository
+          .refs
+          .insert(name.to_string(), format!("{name}-hash"));
+      }
+
+      repository.resolve_meta().map(|_| repository.meta.0)
+    };
+
+    assert_eq!(resolve("v1.*").unwrap(), "v1.10.0");
+    assert_eq!(resolve("v*").unwrap(), "v2.0.0");
+    assert_eq!(resolve("v1.9.?").unwrap(), "v1.9.3");
+    assert_eq!(resolve("feature/*").unwrap(), "feature/one");
+
+    assert!(matches!(
+      resolve("v3.*"),
+      Err(ReferenceError::NoMatchingRefs(..))
+    ));
+
+    let Err(ReferenceError::AmbiguousPattern { matches, .. }) = resolve("release-*") else {
+      panic!("expected an ambiguous pattern");
+    };
+
+    assert_eq!(matches, "`release-a`, `release-b`");
+  }
+
+  #[test]
+  fn resolve_ambiguous_glob_versions() {
+    let mut repository =
+      RemoteRepository::new("foo/bar".to_string(), Some("[vV]1.0.0".to_string())).unwrap();
+
+    for name in ["v1.0.0", "V1.0.0"] {
+      repository
+        .refs
+        .insert(name.to_string(), format!("{name}-hash"));
+    }
+
+    assert!(matches!(
+      repository.resolve_meta(),
+      Err(ReferenceError::AmbiguousPattern { .. })
+    ));
+  }
+
+  #[test]
+  fn checkout_requested_tag() {
+    let temp = TempDir::new().unwrap();
+    let repository = GitRepository::init(temp.path()).unwrap();
+
+    let tagged = commit(&repository, "1.2.0");
+    let object = repository.find_object(tagged, None).unwrap();
+    repository
+      .tag_lightweight("v1.2.0", &object, false)
+      .unwrap();
+
+    commit(&repository, "2.0.0");
+
+    let local = LocalRepository::new(
+      temp.path().display().to_string(),
+      Some("v1.2.0".to_string()),
+    );
+
+    local.checkout(temp.path()).unwrap();
+
+    assert_eq!(
+      fs::read_to_string(temp.path().join("version.txt")).unwrap(),
+      "1.2.0"
+    );
+  }
+
+  #[test]
+  fn scaffold_local_plain_directory() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("source");
+    let destination = temp.path().join("destination");
+
+    fs::create_dir_all(source.join("src")).unwrap();
+    fs::write(source.join("src/main.rs"), "fn main() {}").unwrap();
+
+    let local = LocalRepository::new(source.display().to_string(), None);
+
+    assert!(!local.is_git());
+
+    local
+      .copy_into(&RealFs, &destination, Normalization::None)
+      .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(destination.join("src/main.rs")).unwrap(),
+      "fn main() {}"
+    );
+  }
+
+  #[test]
+  fn scaffold_local_git_repository() {
+    let temp = TempDir::new().unwrap();
+    let source = temp.path().join("source");
+    let destination = temp.path().join("destination");
+
+    let repository = GitRepository::init(&source).unwrap();
+
+    commit(&repository, "1.0.0");
+    fs::write(source.join("version.txt"), "dirty").unwrap();
+
+    let local = LocalRepository::new(source.display().to_string(), None);
+
+    assert!(local.is_git());
+
+    local
+      .copy_into(&RealFs, &destination, Normalization::None)
+      .unwrap();
+    local.checkout(&destination).unwrap();
+
+    // Checking out discards uncommitted changes copied along with the repository.
+    assert_eq!(
+      fs::read_to_string(destination.join("version.txt")).unwrap(),
+      "1.0.0"
+    );
+  }
+
+  #[test]
+  fn update_local_submodules() {
+    let temp = TempDir::new().unwrap();
+
+    // Repository to be used as a submodule.
+    let library = GitRepository::init(temp.path().join("library")).unwrap();
+    commit(&library, "library");
+
+    // Repository with the submodule added.
+    let template = GitRepository::init(temp.path().join("template")).unwrap();
+    let url = format!("file://{}", temp.path().join("library").display());
+
+    let mut submodule = template
+      .submodule(&url, Path::new("vendor/library"), true)
+      .unwrap();
+
+    submodule.clone(None).unwrap();
+    submodule.add_finalize().unwrap();
+    commit(&template, "template");
+
+    // Clones don't initialize submodules, so the source doesn't have the submodule's contents.
+    let source = temp.path().join("source");
+    GitRepository::clone(
+      &format!("file://{}", temp.path().join("template").display()),
+      &source,
+    )
+    .unwrap();
+
+    assert!(!source.join("vendor/library/version.txt").exists());
+
+    let destination = temp.path().join("destination");
+    let local = LocalRepository::new(source.display().to_string(), None);
+
+    local
+      .copy_into(&RealFs, &destination, Normalization::None)
+      .unwrap();
+    local.checkout(&destination).unwrap();
+
+    let updated = local.update_submodules(&destination, &Anonymous).unwrap();
+
+    assert_eq!(updated, [PathBuf::from("vendor/library")]);
+    assert_eq!(
+      fs::read_to_string(destination.join("vendor/library/version.txt")).unwrap(),
+      "library"
+    );
+  }
+
+  #[test]
+  fn extract_local_subdir() {
+    let temp = TempDir::new().unwrap();
+    let destination = temp.path().join("app");
+
+    fs::create_dir_all(destination.join("templates/web/src")).unwrap();
+    fs::create_dir_all(destination.join("templates/api")).unwrap();
+    fs::write(destination.join("README.md"), "# monorepo").unwrap();
+    fs::write(destination.join("templates/web/src/app.js"), "app()").unwrap();
+    fs::write(destination.join("templates/api/main.rs"), "fn main() {}").unwrap();
+
+    let local = LocalRepository::new("monorepo".to_string(), None);
+
+    assert!(matches!(
+      local.extract_subdir_in(&RealFs, &destination, Path::new("templates/cli")),
+      Err(RepositoryError::SubdirNotFound(..))
+    ));
+
+    local
+      .extract_subdir_in(&RealFs, &destination, Path::new("templates/web"))
+      .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(destination.join("src/app.js")).unwrap(),
+      "app()"
+    );
+
+    assert!(!destination.join("README.md").exists());
+    assert!(!destination.join("templates").exists());
+    assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+  }
+
+  #[test]
+  fn resolve_annotated_tag_to_commit() {
+    let temp = TempDir::new().unwrap();
+    let repository = GitRepository::init(temp.path()).unwrap();
+    let signature = git2::Signature::now("decaff", "decaff@example.com").unwrap();
+
+    let tagged = commit(&repository, "1.0.0");
+    let object = repository.find_object(tagged, None).unwrap();
+    let tag = repository
+      .tag("v1.0.0", &object, &signature, "Release 1.0.0", false)
+      .unwrap();
+
+    commit(&repository, "2.0.0");
+
+    let url = format!("file://{}", temp.path().display());
+    let heads = list_refs(&url, &Anonymous).unwrap();
+
+    let mut remote =
+      RemoteRepository::new("foo/bar".to_string(), Some("v1.0.0".to_string())).unwrap();
+    remote.extend_refs(
+      heads
+        .iter()
+        .map(|(name, oid)| (name.as_str(), oid.to_owned())),
+      RefPrecedence::default(),
+    );
+
+    assert_ne!(tag, tagged);
+    assert_eq!(remote.resolve_hash().unwrap(), tagged.to_string());
+  }
+
+  #[test]
+  fn list_refs_of_missing_repository() {
+    let temp = TempDir::new().unwrap();
+    let url = format!("file://{}", temp.path().join("missing").display());
+
+    assert!(matches!(
+      list_refs(&url, &Anonymous),
+      Err(RemoteError::ConnectionFailed { .. } | RemoteError::ListRefsFailed { .. })
+    ));
+  }
+
+  #[test]
+  fn collect_refs_peels_annotated_tags() {
+    let heads = [
+      ("HEAD", "1111111"),
+      ("refs/heads/main", "1111111"),
+      ("refs/tags/v1", "2222222"),
+      ("refs/tags/v1^{}", "1111111"),
+      ("refs/tags/v2^{}", "3333333"),
+      ("refs/tags/v2", "4444444"),
+      ("refs/tags/lightweight", "5555555"),
+      ("refs/pull/1/head", "6666666"),
+    ];
+
+    let (refs, tags, ambiguous) = collect_refs(
+      heads.map(|(name, oid)| (name, oid.to_string())),
+      RefPrecedence::default(),
+    );
+
+    let expected = HashMap::from(
+      [
+        ("HEAD", "1111111"),
+        ("main", "1111111"),
+        ("v1", "1111111"),
+        ("v2", "3333333"),
+        ("lightweight", "5555555"),
+      ]
+      .map(|(name, oid)| (name.to_string(), oid.to_string())),
+    );
+
+    assert_eq!(refs, expected);
+    assert_eq!(
+      tags,
+      HashSet::from(["v1", "v2", "lightweight"].map(String::from))
+    );
+    assert!(ambiguous.is_empty());
+  }
+
+  #[test]
+  fn collect_refs_with_precedence() {
+    let heads = [
+      ("refs/heads/stable", "1111111"),
+      ("refs/heads/main", "2222222"),
+      ("refs/tags/stable", "3333333"),
+    ];
+
+    let collect =
+      |precedence| collect_refs(heads.map(|(name, oid)| (name, oid.to_string())), precedence);
+
+    let (refs, _, ambiguous) = collect(RefPrecedence::Tag);
+    assert_eq!(refs["stable"], "3333333");
+    assert_eq!(refs["main"], "2222222");
+    assert_eq!(ambiguous, HashSet::from(["stable".to_string()]));
+
+    let (refs, ..) = collect(RefPrecedence::Branch);
+    assert_eq!(refs["stable"], "1111111");
+  }
+
+  #[test]
+  fn resolve_hash_decision_tree() {
+    let main = "1111111aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    let tag = "2222222bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+    let branch = "3333333ccccccccccccccccccccccccccccccccc";
+
+    let heads = [
+      ("HEAD", main),
+      ("refs/heads/main", main),
+      ("refs/heads/stable", branch),
+      ("refs/tags/stable", tag),
+      ("refs/tags/v1.0.0", tag),
+    ];
+
+    let remote = |meta: &str, precedence| {
+      let mut remote =
+        RemoteRepository::new("foo/bar".to_string(), Some(meta.to_string())).unwrap();
+      remote.extend_refs(heads.map(|(name, oid)| (name, oid.to_string())), precedence);
+      remote
+    };
+
+    let resolve = |meta: &str| remote(meta, RefPrecedence::Tag).resolve_hash();
+
+    // 1. Branch or tag name.
+    assert_eq!(resolve("main").unwrap(), main);
+    assert_eq!(resolve("v1.0.0").unwrap(), tag);
+
+    // 1. Ambiguous name, resolved to the preferred ref's hash while keeping the meta as is.
+    let preferred = remote("stable", RefPrecedence::Tag);
+    assert_eq!(preferred.meta.0, "stable");
+    assert_eq!(preferred.resolve_hash().unwrap(), tag);
+    assert_eq!(preferred.get_archive_ref(), tag);
+
+    let preferred = remote("stable", RefPrecedence::Branch);
+    assert_eq!(preferred.resolve_hash().unwrap(), branch);
+    assert_eq!(preferred.get_archive_ref(), branch);
+
+    // 2. Release.
+    assert_eq!(resolve("release:v1.0.0").unwrap(), tag);
+
+    assert!(matches!(
+      resolve("release:v2.0.0"),
+      Err(ReferenceError::InvalidSelector(..))
+    ));
+
+    // 3. Pinned branch.
+    assert_eq!(resolve("main@1111111").unwrap(), main);
+
+    // 4. Full or short commit hash, expanded if it's a tip of a ref.
+    assert_eq!(resolve("2222222").unwrap(), tag);
+    assert_eq!(resolve(main).unwrap(), main);
+    assert_eq!(resolve("4444444").unwrap(), "4444444");
+    assert_eq!(resolve("ABCDEF0").unwrap(), "abcdef0");
+
+    // Nothing matched.
+    assert!(matches!(
+      resolve("missing"),
+      Err(ReferenceError::InvalidSelector(..))
+    ));
+
+    assert!(matches!(
+      resolve("abc"),
+      Err(ReferenceError::InvalidSelector(..))
+    ));
+  }
 }
@@ -2,16 +2,23 @@ use std::time::Duration;
 
 use indicatif::{ProgressBar, ProgressStyle};
 
+use crate::logger::{self, Verbosity};
+
 /// Small wrapper around the `indicatif` spinner.
 pub struct Spinner {
   spinner: ProgressBar,
 }
 
 impl Spinner {
-  /// Creates a new spinner.
+  /// Creates a new spinner. The spinner is hidden if progress output is suppressed.
   pub fn new() -> Self {
     let style = ProgressStyle::default_spinner().tick_chars("⠋⠙⠚⠒⠂⠂⠒⠲⠴⠦⠖⠒⠐⠐⠒⠓⠋·");
-    let spinner = ProgressBar::new_spinner();
+
+    let spinner = if logger::enabled(Verbosity::Normal) {
+      ProgressBar::new_spinner()
+    } else {
+      ProgressBar::hidden()
+    };
 
     spinner.set_style(style);
     spinner.enable_steady_tick(Duration::from_millis(80));
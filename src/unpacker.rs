@@ -1,11 +1,24 @@
+use std::fmt::{self, Display};
 use std::fs;
-use std::io;
-use std::path::{Path, PathBuf};
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
-use flate2::bufread::GzDecoder;
+use bytes::Bytes;
+use crossterm::style::Stylize;
+use flate2::read::GzDecoder;
+use futures_util::{Stream, StreamExt};
+use glob_match::glob_match;
 use miette::Diagnostic;
 use tar::Archive;
 use thiserror::Error;
+use tokio::sync::mpsc::{self, Receiver};
+use tokio::task;
+use zip::ZipArchive;
+
+use crate::logger::info;
+use crate::path::{CollisionDetector, Normalization, PathClean};
+use crate::vfs::Filesystem;
 
 #[cfg(target_os = "windows")]
 const USE_XATTRS: bool = false;
@@ -19,6 +32,12 @@ const USE_PERMISSIONS: bool = false;
 #[cfg(not(target_os = "windows"))]
 const USE_PERMISSIONS: bool = true;
 
+#[cfg(target_os = "windows")]
+const USE_SYMLINKS: bool = false;
+
+#[cfg(not(target_os = "windows"))]
+const USE_SYMLINKS: bool = true;
+
 #[derive(Debug, Diagnostic, Error)]
 pub enum UnpackError {
   #[error("{message}")]
@@ -28,79 +47,1373 @@ pub enum UnpackError {
     #[source]
     source: io::Error,
   },
+  #[error("Refusing to unpack `{0}`: it points outside of the destination.")]
+  #[diagnostic(
+    code(decaff::unpack::unsafe_entry),
+    help("The archive may be malformed or malicious.")
+  )]
+  UnsafeEntry(PathBuf),
+  #[error("Subdirectory `{0}` doesn't exist in the archive.")]
+  #[diagnostic(code(decaff::unpack::subdir))]
+  SubdirNotFound(PathBuf),
+}
+
+/// Number of chunks to buffer while unpacking from a stream.
+const STREAM_BUFFER: usize = 16;
+
+/// Number of leading path components to strip from entries of archives served by hosts, which wrap
+/// everything in a top-level directory like `repo-<sha>/`.
+const HOST_ARCHIVE_DEPTH: usize = 1;
+
+/// Summary of what was unpacked.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UnpackStats {
+  /// Number of unpacked files, including symlinks.
+  pub files: usize,
+  /// Number of unpacked directories. Directories created implicitly are not counted.
+  pub dirs: usize,
+  /// Total size of unpacked files in bytes.
+  pub bytes: u64,
+}
+
+impl Display for UnpackStats {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let UnpackStats { files, bytes, .. } = self;
+    let suffix = if *files == 1 { "" } else { "s" };
+
+    write!(f, "{files} file{suffix} ({bytes} bytes)")
+  }
+}
+
+/// Options controlling how entries are unpacked.
+#[derive(Clone, Debug)]
+pub struct UnpackOptions {
+  /// Number of leading path components to strip from each entry, like `tar --strip-components`.
+  /// Defaults to the depth of archives served by hosts.
+  pub strip_components: usize,
+  /// Unicode normalization policy for unpacked filenames.
+  pub normalization: Normalization,
+  /// Subdirectory of the archive to unpack. If set, only entries under it are unpacked, relative
+  /// to it.
+  pub subdir: Option<PathBuf>,
+  /// Glob patterns of entries to unpack. If empty, all entries are unpacked. Patterns are matched
+  /// against paths relative to the destination, and against their parent directories as well.
+  pub include: Vec<String>,
+  /// Glob patterns of entries to skip, matched the same way as `include`. Takes precedence over
+  /// `include`.
+  pub exclude: Vec<String>,
+}
+
+impl Default for UnpackOptions {
+  fn default() -> Self {
+    Self {
+      strip_components: HOST_ARCHIVE_DEPTH,
+      normalization: Normalization::default(),
+      subdir: None,
+      include: Vec::new(),
+      exclude: Vec::new(),
+    }
+  }
 }
 
 pub struct Unpacker {
-  bytes: Vec<u8>,
+  reader: Box<dyn Read>,
+  options: UnpackOptions,
 }
 
 impl Unpacker {
   pub fn new(bytes: Vec<u8>) -> Self {
-    Self { bytes }
+    Self::from_reader(io::Cursor::new(bytes))
+  }
+
+  /// Creates an unpacker consuming the archive incrementally from the given reader, without
+  /// materializing the whole archive in memory.
+  pub fn from_reader<R: Read + 'static>(reader: R) -> Self {
+    Self {
+      reader: Box::new(reader),
+      options: UnpackOptions::default(),
+    }
+  }
+
+  /// Sets the options to unpack with.
+  pub fn options(mut self, options: UnpackOptions) -> Self {
+    self.options = options;
+    self
+  }
+
+  /// Unpacks the archive (a gzipped tarball or a zip) to the given [Path] on the given filesystem.
+  /// Filesystems not backed by the disk only get directories and regular files, without
+  /// permissions.
+  pub fn unpack_into(self, fs: &dyn Filesystem, path: &Path) -> Result<UnpackStats, UnpackError> {
+    unpack_archive(self.reader, fs, path, &self.options)
+  }
+
+  /// Unpacks the archive to the given [Path] on the given filesystem while reading it from the
+  /// stream of chunks, e.g. an HTTP response body, without buffering the whole archive in memory.
+  /// Zip archives are the exception, since they can only be unpacked once fully read.
+  pub async fn unpack_stream_into<S, E>(
+    stream: S,
+    fs: Arc<dyn Filesystem>,
+    path: &Path,
+    options: UnpackOptions,
+  ) -> Result<UnpackStats, UnpackError>
+  where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+  {
+    let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+    let destination = path.to_path_buf();
+
+    // Decompression and unpacking are blocking, so they run on a separate thread, fed by the chunks
+    // received from the stream.
+    let mut handle = task::spawn_blocking(move || {
+      Unpacker::from_reader(ChannelReader::new(receiver))
+        .options(options)
+        .unpack_into(fs.as_ref(), &destination)
+    });
+
+    let join = |result: Result<Result<UnpackStats, UnpackError>, task::JoinError>| {
+      result.map_err(|err| {
+        UnpackError::Io {
+          message: "Couldn't finish unpacking the archive.".to_string(),
+          source: io::Error::other(err),
+        }
+      })?
+    };
+
+    let mut stream = Box::pin(stream);
+    let mut failure = None;
+    let mut unpacked = None;
+
+    while let Some(chunk) = stream.next().await {
+      match chunk {
+        // Once unpacking is done, the rest of the stream is drained, so consumers of the stream
+        // observe the whole body, e.g. to cache it or to check its length.
+        | Ok(..) if unpacked.is_some() => {},
+        | Ok(chunk) => {
+          // The receiving side is gone either because unpacking failed, or because the archive
+          // ended before the stream did, e.g. with trailing padding or the gzip trailer.
+          if sender.send(chunk).await.is_err() {
+            unpacked = Some(join((&mut handle).await)?);
+          }
+        },
+        | Err(err) => {
+          failure = Some(io::Error::other(err));
+          break;
+        },
+      }
+    }
+
+    // Signal the end of the stream.
+    drop(sender);
+
+    let unpacked = match unpacked {
+      | Some(unpacked) => unpacked,
+      | None => join(handle.await)?,
+    };
+
+    match failure {
+      | Some(source) => {
+        Err(UnpackError::Io {
+          message: "Couldn't read the archive stream.".to_string(),
+          source,
+        })
+      },
+      | None => Ok(unpacked),
+    }
+  }
+}
+
+impl From<Vec<u8>> for Unpacker {
+  fn from(bytes: Vec<u8>) -> Self {
+    Unpacker::new(bytes)
+  }
+}
+
+/// Leading bytes of zip archives: a local file header or, for empty archives, the end of central
+/// directory record.
+const ZIP_MAGIC: [&[u8]; 2] = [b"PK\x03\x04", b"PK\x05\x06"];
+
+/// Archive formats supported by the [Unpacker].
+#[derive(Debug, PartialEq)]
+enum ArchiveFormat {
+  /// Gzipped tarball.
+  TarGz,
+  /// Zip archive.
+  Zip,
+}
+
+impl ArchiveFormat {
+  /// Detects the format from the leading (magic) bytes of an archive. Defaults to gzipped tarballs.
+  fn detect(magic: &[u8]) -> Self {
+    if ZIP_MAGIC.iter().any(|zip| magic.starts_with(zip)) {
+      Self::Zip
+    } else {
+      Self::TarGz
+    }
   }
+}
 
-  /// Unpacks the tar archive to the given [Path].
-  pub fn unpack_to(&self, path: &Path) -> Result<Vec<PathBuf>, UnpackError> {
-    let mut archive = Archive::new(GzDecoder::new(&self.bytes[..]));
-    let mut written_paths = Vec::new();
+/// Unpacks the archive read from the `reader` to the given [Path] according to the given
+/// [UnpackOptions]. The archive format is detected from its magic bytes.
+fn unpack_archive<R: Read>(
+  mut reader: R,
+  fs: &dyn Filesystem,
+  path: &Path,
+  options: &UnpackOptions,
+) -> Result<UnpackStats, UnpackError> {
+  let mut magic = Vec::new();
 
-    // Get iterator over the entries.
-    let raw_entries = archive.entries().map_err(|source| {
+  (&mut reader)
+    .take(ZIP_MAGIC[0].len() as u64)
+    .read_to_end(&mut magic)
+    .map_err(|source| {
       UnpackError::Io {
-        message: "Couldn't get entries from the tarball.".to_string(),
+        message: "Couldn't read the archive.".to_string(),
         source,
       }
     })?;
 
-    // Create output structure (if necessary).
-    fs::create_dir_all(path).map_err(|source| {
+  let format = ArchiveFormat::detect(&magic);
+  let mut reader = io::Cursor::new(magic).chain(reader);
+  let mut destination = Destination::new(fs, path, options)?;
+
+  match format {
+    | ArchiveFormat::TarGz => unpack_tar(reader, &mut destination)?,
+    // Zip archives keep their central directory at the end, so they have to be read in full.
+    | ArchiveFormat::Zip => {
+      let mut bytes = Vec::new();
+
+      reader.read_to_end(&mut bytes).map_err(|source| {
+        UnpackError::Io {
+          message: "Couldn't read the archive.".to_string(),
+          source,
+        }
+      })?;
+
+      unpack_zip(io::Cursor::new(bytes), &mut destination)?
+    },
+  }
+
+  destination.finish()
+}
+
+/// Unpacks the gzipped tar archive read from the `reader` to the given [Destination].
+fn unpack_tar<R: Read>(reader: R, destination: &mut Destination) -> Result<(), UnpackError> {
+  let mut archive = Archive::new(GzDecoder::new(reader));
+
+  // Get iterator over the entries.
+  let raw_entries = archive.entries().map_err(|source| {
+    UnpackError::Io {
+      message: "Couldn't get entries from the tarball.".to_string(),
+      source,
+    }
+  })?;
+
+  for entry in raw_entries {
+    let mut entry = entry.map_err(|source| {
       UnpackError::Io {
-        message: "Couldn't create the output structure.".to_string(),
+        message: "Couldn't read the entry.".to_string(),
+        source,
+      }
+    })?;
+
+    let entry_path = entry.path().map_err(|source| {
+      UnpackError::Io {
+        message: "Couldn't get the entry's path.".to_string(),
+        source,
+      }
+    })?;
+
+    let Some(fixed_path) = destination.prepare(&entry_path)? else {
+      continue;
+    };
+
+    let kind = entry.header().entry_type();
+
+    // Hard links are never created natively, since tar would link the raw target as-is.
+    if kind.is_hard_link() {
+      let link = entry.link_name().map_err(|source| {
+        UnpackError::Io {
+          message: "Couldn't get the hard link's target.".to_string(),
+          source,
+        }
+      })?;
+
+      destination.hard_link(&fixed_path, &link.unwrap_or_default())?;
+
+      continue;
+    }
+
+    if kind.is_symlink() {
+      let link = entry.link_name().map_err(|source| {
+        UnpackError::Io {
+          message: "Couldn't get the symlink's target.".to_string(),
+          source,
+        }
+      })?;
+
+      let link = link.unwrap_or_default();
+
+      destination.check_link(&fixed_path, &link)?;
+
+      if !USE_SYMLINKS || !destination.is_local() {
+        warn_skipped_symlink(&fixed_path);
+        continue;
+      }
+    }
+
+    if !destination.is_local() {
+      if kind.is_dir() {
+        destination.create_dir(&fixed_path)?;
+      } else if kind.is_file() {
+        destination.write(&fixed_path, &mut entry)?;
+      }
+
+      continue;
+    }
+
+    let is_dir = kind.is_dir();
+    let bytes = entry.size();
+
+    entry.set_preserve_permissions(USE_PERMISSIONS);
+    entry.set_unpack_xattrs(USE_XATTRS);
+
+    entry.unpack(&fixed_path).map_err(|source| {
+      UnpackError::Io {
+        message: "Couldn't unpack the entry.".to_string(),
         source,
       }
     })?;
 
-    for mut entry in raw_entries.flatten() {
-      let entry_path = entry.path().map_err(|source| {
+    destination.record(is_dir, bytes);
+  }
+
+  Ok(())
+}
+
+/// Unpacks the zip archive read from the `reader` to the given [Destination].
+fn unpack_zip<R: Read + Seek>(reader: R, destination: &mut Destination) -> Result<(), UnpackError> {
+  let mut archive = ZipArchive::new(reader).map_err(|err| {
+    UnpackError::Io {
+      message: "Couldn't read the zip archive.".to_string(),
+      source: io::Error::other(err),
+    }
+  })?;
+
+  for index in 0..archive.len() {
+    let mut entry = archive.by_index(index).map_err(|err| {
+      UnpackError::Io {
+        message: "Couldn't read the entry.".to_string(),
+        source: io::Error::other(err),
+      }
+    })?;
+
+    let entry_path = entry
+      .name()
+      .map(|name| PathBuf::from(name.as_ref()))
+      .map_err(|err| {
         UnpackError::Io {
           message: "Couldn't get the entry's path.".to_string(),
+          source: io::Error::other(err),
+        }
+      })?;
+
+    let Some(fixed_path) = destination.prepare(&entry_path)? else {
+      continue;
+    };
+
+    // Directories may come in any order relative to their contents, and may be missing entirely.
+    if entry.is_dir() {
+      destination.create_dir(&fixed_path)?;
+      continue;
+    }
+
+    if entry.is_symlink() {
+      let mut link = String::new();
+
+      entry.read_to_string(&mut link).map_err(|source| {
+        UnpackError::Io {
+          message: "Couldn't get the symlink's target.".to_string(),
           source,
         }
       })?;
 
-      let fixed_path = fix_entry_path(&entry_path, path);
+      destination.check_link(&fixed_path, Path::new(&link))?;
 
-      entry.set_preserve_permissions(USE_PERMISSIONS);
-      entry.set_unpack_xattrs(USE_XATTRS);
+      if !USE_SYMLINKS || !destination.is_local() {
+        warn_skipped_symlink(&fixed_path);
+        continue;
+      }
 
-      entry.unpack(&fixed_path).map_err(|source| {
+      #[cfg(unix)]
+      std::os::unix::fs::symlink(&link, &fixed_path).map_err(|source| {
         UnpackError::Io {
           message: "Couldn't unpack the entry.".to_string(),
           source,
         }
       })?;
 
-      written_paths.push(fixed_path);
+      destination.record(false, 0);
+
+      continue;
     }
 
-    // Deduplicate, because it **will** contain duplicates.
-    written_paths.dedup();
+    if !destination.is_local() {
+      destination.write(&fixed_path, &mut entry)?;
+      continue;
+    }
+
+    let mut file = fs::File::create(&fixed_path).map_err(|source| {
+      UnpackError::Io {
+        message: "Couldn't unpack the entry.".to_string(),
+        source,
+      }
+    })?;
+
+    let bytes = io::copy(&mut entry, &mut file).map_err(|source| {
+      UnpackError::Io {
+        message: "Couldn't unpack the entry.".to_string(),
+        source,
+      }
+    })?;
+
+    #[cfg(unix)]
+    if let (true, Some(mode)) = (USE_PERMISSIONS, entry.unix_mode()) {
+      use std::os::unix::fs::PermissionsExt;
+
+      file
+        .set_permissions(fs::Permissions::from_mode(mode))
+        .map_err(|source| {
+          UnpackError::Io {
+            message: "Couldn't set permissions of the entry.".to_string(),
+            source,
+          }
+        })?;
+    }
 
-    Ok(written_paths)
+    destination.record(false, bytes);
   }
+
+  Ok(())
 }
 
-impl From<Vec<u8>> for Unpacker {
-  fn from(bytes: Vec<u8>) -> Self {
-    Unpacker::new(bytes)
+/// Destination of unpacked entries, mapping entry paths to target paths regardless of the archive
+/// format.
+struct Destination<'a> {
+  /// Filesystem to unpack to.
+  fs: &'a dyn Filesystem,
+  /// Destination directory.
+  path: &'a Path,
+  /// Canonicalized destination directory.
+  root: PathBuf,
+  /// Options to unpack with.
+  options: &'a UnpackOptions,
+  /// Detector of colliding filenames.
+  detector: CollisionDetector,
+  /// Stats of unpacked entries.
+  stats: UnpackStats,
+}
+
+impl<'a> Destination<'a> {
+  /// Creates the destination directory (if necessary).
+  fn new(
+    fs: &'a dyn Filesystem,
+    path: &'a Path,
+    options: &'a UnpackOptions,
+  ) -> Result<Self, UnpackError> {
+    // Create output structure (if necessary).
+    fs.create_dir_all(path).map_err(|source| {
+      UnpackError::Io {
+        message: "Couldn't create the output structure.".to_string(),
+        source,
+      }
+    })?;
+
+    let root = fs.canonicalize(path).map_err(|source| {
+      UnpackError::Io {
+        message: "Couldn't resolve the output directory.".to_string(),
+        source,
+      }
+    })?;
+
+    Ok(Self {
+      fs,
+      path,
+      root,
+      options,
+      detector: CollisionDetector::new(),
+      stats: UnpackStats::default(),
+    })
+  }
+
+  /// Resolves the target path for the entry and creates its parent directories. Returns `None` if
+  /// the entry should be skipped.
+  fn prepare(&mut self, entry_path: &Path) -> Result<Option<PathBuf>, UnpackError> {
+    // Absolute paths and `..` components could be used to write outside of the destination.
+    if !is_safe_entry_path(entry_path) {
+      return Err(UnpackError::UnsafeEntry(entry_path.to_path_buf()));
+    }
+
+    let Some(relative_path) = self.relative_path(entry_path) else {
+      return Ok(None);
+    };
+
+    let entry_path = self.options.normalization.apply(entry_path);
+
+    // Filtered out entries never touch the disk.
+    let is_included =
+      self.options.include.is_empty() || matches_any(&self.options.include, &relative_path);
+
+    if !is_included || matches_any(&self.options.exclude, &relative_path) {
+      return Ok(None);
+    }
+
+    let fixed_path = self.path.join(relative_path);
+
+    // Entries colliding on case-insensitive or normalizing filesystems would silently overwrite
+    // each other there, so warn about them.
+    if let Some(seen) = self.detector.check(&fixed_path) {
+      let message = format!(
+        "? Colliding filenames: {} and {}",
+        seen.display(),
+        fixed_path.display()
+      );
+
+      info!("{}", message.yellow());
+    }
+
+    // Archives don't necessarily contain entries for all directories.
+    if let Some(parent) = fixed_path.parent() {
+      self.fs.create_dir_all(parent).map_err(|source| {
+        UnpackError::Io {
+          message: "Couldn't create the output structure.".to_string(),
+          source,
+        }
+      })?;
+
+      // Previously unpacked symlinks could still redirect the entry outside of the destination.
+      let is_contained = self
+        .fs
+        .canonicalize(parent)
+        .is_ok_and(|parent| parent.starts_with(&self.root));
+
+      if !is_contained {
+        return Err(UnpackError::UnsafeEntry(entry_path));
+      }
+    }
+
+    Ok(Some(fixed_path))
+  }
+
+  /// Maps the entry path to a path relative to the destination, by normalizing, stripping leading
+  /// components and the subdirectory. Returns `None` for entries that are stripped away entirely or
+  /// lie outside the subdirectory.
+  fn relative_path(&self, entry_path: &Path) -> Option<PathBuf> {
+    let entry_path = self.options.normalization.apply(entry_path);
+    let relative_path = strip_entry_path(&entry_path, self.options.strip_components);
+
+    let relative_path = match &self.options.subdir {
+      | Some(subdir) => relative_path.strip_prefix(subdir).ok()?.to_path_buf(),
+      | None => relative_path,
+    };
+
+    // Entries that are fully stripped away, like the top-level directory itself, are skipped.
+    if relative_path.as_os_str().is_empty() {
+      None
+    } else {
+      Some(relative_path)
+    }
+  }
+
+  /// Unpacks the hard link at `fixed_path` as a copy of the previously unpacked entry at `link`.
+  /// Link targets are paths within the archive, so they are resolved like entry paths, and refused
+  /// if they escape the destination.
+  fn hard_link(&mut self, fixed_path: &Path, link: &Path) -> Result<(), UnpackError> {
+    let unsafe_entry = || UnpackError::UnsafeEntry(fixed_path.to_path_buf());
+
+    if !is_safe_entry_path(link) {
+      return Err(unsafe_entry());
+    }
+
+    let target = self
+      .relative_path(link)
+      .map(|relative_path| self.path.join(relative_path))
+      .ok_or_else(unsafe_entry)?;
+
+    // Previously unpacked symlinks could still redirect the target outside of the destination.
+    let is_contained = self
+      .fs
+      .canonicalize(&target)
+      .is_ok_and(|target| target.starts_with(&self.root));
+
+    if !is_contained {
+      return Err(unsafe_entry());
+    }
+
+    let contents = self.fs.read(&target).map_err(|source| {
+      UnpackError::Io {
+        message: format!("Couldn't read the hard link's target '{}'.", link.display()),
+        source,
+      }
+    })?;
+
+    self.write(fixed_path, &mut contents.as_slice())
+  }
+
+  /// Checks that the symlink at `fixed_path` pointing to `link` doesn't point outside of the
+  /// destination.
+  fn check_link(&self, fixed_path: &Path, link: &Path) -> Result<(), UnpackError> {
+    let is_contained = fixed_path
+      .parent()
+      .and_then(|parent| self.fs.canonicalize(parent).ok())
+      .is_some_and(|parent| {
+        !link.is_absolute() && parent.join(link).clean().starts_with(&self.root)
+      });
+
+    if is_contained {
+      Ok(())
+    } else {
+      Err(UnpackError::UnsafeEntry(fixed_path.to_path_buf()))
+    }
+  }
+
+  /// Checks if the destination is on disk, so entries can be unpacked natively.
+  fn is_local(&self) -> bool {
+    self.fs.local_path(self.path).is_some()
+  }
+
+  /// Creates the directory entry at `fixed_path`.
+  fn create_dir(&mut self, fixed_path: &Path) -> Result<(), UnpackError> {
+    self.fs.create_dir_all(fixed_path).map_err(|source| {
+      UnpackError::Io {
+        message: "Couldn't create the output structure.".to_string(),
+        source,
+      }
+    })?;
+
+    self.record(true, 0);
+
+    Ok(())
+  }
+
+  /// Writes contents of the file entry read from the `reader` to `fixed_path`.
+  fn write(&mut self, fixed_path: &Path, reader: &mut dyn Read) -> Result<(), UnpackError> {
+    let mut contents = Vec::new();
+
+    reader.read_to_end(&mut contents).map_err(|source| {
+      UnpackError::Io {
+        message: "Couldn't read the entry.".to_string(),
+        source,
+      }
+    })?;
+
+    self.fs.write(fixed_path, &contents).map_err(|source| {
+      UnpackError::Io {
+        message: "Couldn't unpack the entry.".to_string(),
+        source,
+      }
+    })?;
+
+    self.record(false, contents.len() as u64);
+
+    Ok(())
+  }
+
+  /// Records an unpacked entry.
+  fn record(&mut self, is_dir: bool, bytes: u64) {
+    if is_dir {
+      self.stats.dirs += 1;
+    } else {
+      self.stats.files += 1;
+      self.stats.bytes += bytes;
+    }
+  }
+
+  /// Returns the stats of unpacked entries.
+  fn finish(self) -> Result<UnpackStats, UnpackError> {
+    let UnpackStats { files, dirs, .. } = self.stats;
+
+    if let (Some(subdir), 0) = (&self.options.subdir, files + dirs) {
+      return Err(UnpackError::SubdirNotFound(subdir.to_owned()));
+    }
+
+    Ok(self.stats)
+  }
+}
+
+/// Blocking [Read] adapter over the receiving half of a channel of chunks.
+struct ChannelReader {
+  receiver: Receiver<Bytes>,
+  chunk: Bytes,
+}
+
+impl ChannelReader {
+  fn new(receiver: Receiver<Bytes>) -> Self {
+    Self { receiver, chunk: Bytes::new() }
+  }
+}
+
+impl Read for ChannelReader {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    while self.chunk.is_empty() {
+      match self.receiver.blocking_recv() {
+        | Some(chunk) => self.chunk = chunk,
+        | None => return Ok(0),
+      }
+    }
+
+    let size = buf.len().min(self.chunk.len());
+    let chunk = self.chunk.split_to(size);
+
+    buf[..size].copy_from_slice(&chunk);
+
+    Ok(size)
   }
 }
 
-/// Produces a "fixed" path for an entry.
+/// Warns that a symlink entry was skipped, since symlinks aren't supported on this platform.
+fn warn_skipped_symlink(fixed_path: &Path) {
+  let message = format!("? Skipped symlink: {}", fixed_path.display());
+  info!("{}", message.yellow());
+}
+
+/// Checks that an entry path is relative and doesn't contain `..` components.
 #[inline(always)]
-fn fix_entry_path(entry_path: &Path, dest_path: &Path) -> PathBuf {
-  dest_path
+fn is_safe_entry_path(entry_path: &Path) -> bool {
+  entry_path
     .components()
-    .chain(entry_path.components().skip(1))
-    .fold(PathBuf::new(), |acc, next| acc.join(next))
+    .all(|component| matches!(component, Component::Normal(..) | Component::CurDir))
+}
+
+/// Checks if any of the glob patterns matches the entry path or any of its parent directories. To
+/// make `dir/**` exclude the directory itself as well, such patterns also match `dir`.
+fn matches_any(patterns: &[String], entry_path: &Path) -> bool {
+  entry_path
+    .ancestors()
+    .filter(|path| !path.as_os_str().is_empty())
+    .map(|path| path.display().to_string())
+    .any(|candidate| {
+      patterns.iter().any(|pattern| {
+        glob_match(pattern, &candidate)
+          || pattern
+            .strip_suffix("/**")
+            .is_some_and(|pattern| glob_match(pattern, &candidate))
+      })
+    })
+}
+
+/// Strips the given number of leading components from an entry path.
+#[inline(always)]
+fn strip_entry_path(entry_path: &Path, components: usize) -> PathBuf {
+  entry_path.components().skip(components).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Write;
+
+  use flate2::write::GzEncoder;
+  use flate2::Compression;
+  use futures_util::stream;
+  use tar::{Builder, EntryType, Header};
+  use tempfile::TempDir;
+  use zip::write::SimpleFileOptions;
+  use zip::ZipWriter;
+
+  use super::*;
+  use crate::vfs::{MemoryFs, RealFs};
+
+  // Helpers.
+
+  /// Builds a gzipped tarball with the given files, nested under a top-level directory like the
+  /// archives served by hosts.
+  fn tarball(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let files = files
+      .iter()
+      .map(|(path, contents)| (format!("repo-sha/{path}"), *contents))
+      .collect::<Vec<_>>();
+
+    raw_tarball(&files)
+  }
+
+  /// Builds a gzipped tarball with the given files as-is.
+  fn raw_tarball(files: &[(String, &[u8])]) -> Vec<u8> {
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    for (path, contents) in files {
+      let mut header = Header::new_gnu();
+
+      header.set_size(contents.len() as u64);
+      header.set_mode(0o644);
+      header.set_cksum();
+
+      builder.append_data(&mut header, path, *contents).unwrap();
+    }
+
+    builder.into_inner().unwrap().finish().unwrap()
+  }
+
+  /// Builds a gzipped tarball with a single entry with the given raw path, bypassing the path
+  /// validation done by [Builder].
+  fn crafted_tarball(path: &str, kind: EntryType, link: Option<&str>, contents: &[u8]) -> Vec<u8> {
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    let mut header = Header::new_old();
+
+    header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+
+    if let Some(link) = link {
+      header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+    }
+
+    header.set_entry_type(kind);
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+
+    builder.append(&header, contents).unwrap();
+
+    builder.into_inner().unwrap().finish().unwrap()
+  }
+
+  // Tests.
+
+  #[tokio::test]
+  async fn unpack_from_stream() {
+    let temp = TempDir::new().unwrap();
+    let bytes = tarball(&[("README.md", b"# readme"), ("src/main.rs", b"fn main() {}")]);
+
+    // Feed the archive in small chunks to make sure chunks boundaries don't matter.
+    let chunks = bytes
+      .chunks(7)
+      .map(|chunk| Ok::<_, io::Error>(Bytes::copy_from_slice(chunk)))
+      .collect::<Vec<_>>();
+
+    Unpacker::unpack_stream_into(
+      stream::iter(chunks),
+      Arc::new(RealFs),
+      temp.path(),
+      UnpackOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(temp.path().join("README.md")).unwrap(),
+      "# readme"
+    );
+
+    assert_eq!(
+      fs::read_to_string(temp.path().join("src/main.rs")).unwrap(),
+      "fn main() {}"
+    );
+  }
+
+  #[tokio::test]
+  async fn unpack_from_stream_drains_it() {
+    let temp = TempDir::new().unwrap();
+
+    // Trailing data after the end of the archive is never read by the unpacker, but still has to
+    // pass through the stream, e.g. to be cached.
+    let mut builder = Builder::new(Vec::new());
+    let mut header = Header::new_gnu();
+
+    header.set_size(8);
+    header.set_mode(0o644);
+    header.set_cksum();
+
+    builder
+      .append_data(&mut header, "repo-sha/README.md", &b"# readme"[..])
+      .unwrap();
+
+    let mut archive = builder.into_inner().unwrap();
+    let mut seed = 42u32;
+
+    archive.extend((0..4096).map(|_| {
+      seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+      (seed >> 16) as u8
+    }));
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&archive).unwrap();
+
+    let bytes = encoder.finish().unwrap();
+    let mut received = Vec::new();
+
+    let chunks = bytes
+      .chunks(1)
+      .map(|chunk| Ok::<_, io::Error>(Bytes::copy_from_slice(chunk)))
+      .collect::<Vec<_>>();
+
+    let stream = stream::iter(chunks).inspect(|chunk| {
+      if let Ok(chunk) = chunk {
+        received.extend_from_slice(chunk);
+      }
+    });
+
+    let stats = Unpacker::unpack_stream_into(
+      stream,
+      Arc::new(RealFs),
+      temp.path(),
+      UnpackOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stats.files, 1);
+    assert_eq!(received, bytes);
+  }
+
+  #[tokio::test]
+  async fn unpack_from_failing_stream() {
+    let temp = TempDir::new().unwrap();
+    let bytes = tarball(&[("README.md", b"# readme")]);
+
+    let chunks = vec![
+      Ok(Bytes::copy_from_slice(&bytes[..10])),
+      Err(io::Error::other("connection reset")),
+    ];
+
+    assert!(Unpacker::unpack_stream_into(
+      stream::iter(chunks),
+      Arc::new(RealFs),
+      temp.path(),
+      UnpackOptions::default()
+    )
+    .await
+    .is_err());
+  }
+
+  #[test]
+  fn unpack_with_normalization() {
+    let temp = TempDir::new().unwrap();
+    let bytes = tarball(&[("cafe\u{301}.md", b"# nfd")]);
+
+    Unpacker::new(bytes)
+      .options(UnpackOptions {
+        normalization: Normalization::Nfc,
+        ..UnpackOptions::default()
+      })
+      .unpack_into(&RealFs, temp.path())
+      .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(temp.path().join("caf\u{e9}.md")).unwrap(),
+      "# nfd"
+    );
+  }
+
+  #[test]
+  fn unpack_subdir() {
+    let temp = TempDir::new().unwrap();
+
+    let bytes = tarball(&[
+      ("README.md", b"# monorepo"),
+      ("templates/web/index.html", b"<html></html>"),
+      ("templates/web/src/app.js", b"app()"),
+      ("templates/api/main.rs", b"fn main() {}"),
+    ]);
+
+    Unpacker::new(bytes)
+      .options(UnpackOptions {
+        subdir: Some(PathBuf::from("templates/web")),
+        ..UnpackOptions::default()
+      })
+      .unpack_into(&RealFs, temp.path())
+      .unwrap();
+
+    let mut entries = walkdir::WalkDir::new(temp.path())
+      .into_iter()
+      .flatten()
+      .filter(|entry| entry.file_type().is_file())
+      .map(|entry| {
+        entry
+          .path()
+          .strip_prefix(temp.path())
+          .unwrap()
+          .to_path_buf()
+      })
+      .collect::<Vec<_>>();
+
+    entries.sort();
+
+    assert_eq!(
+      entries,
+      [PathBuf::from("index.html"), PathBuf::from("src/app.js")]
+    );
+  }
+
+  #[test]
+  fn unpack_with_filters() {
+    let temp = TempDir::new().unwrap();
+
+    let bytes = tarball(&[
+      ("README.md", b"# template"),
+      (".github/workflows/ci.yml", b"on: push"),
+      ("src/main.rs", b"fn main() {}"),
+      ("src/lib.rs", b""),
+      ("docs/guide.md", b"# guide"),
+    ]);
+
+    Unpacker::new(bytes)
+      .options(UnpackOptions {
+        include: vec!["**/*.md".to_string(), "src".to_string()],
+        exclude: vec![".github/**".to_string(), "src/lib.rs".to_string()],
+        ..UnpackOptions::default()
+      })
+      .unpack_into(&RealFs, temp.path())
+      .unwrap();
+
+    assert!(temp.path().join("README.md").is_file());
+    assert!(temp.path().join("docs/guide.md").is_file());
+    assert!(temp.path().join("src/main.rs").is_file());
+    assert!(!temp.path().join("src/lib.rs").exists());
+    assert!(!temp.path().join(".github").exists());
+  }
+
+  #[test]
+  fn unpack_missing_subdir() {
+    let temp = TempDir::new().unwrap();
+    let bytes = tarball(&[("README.md", b"# monorepo")]);
+
+    let result = Unpacker::new(bytes)
+      .options(UnpackOptions {
+        subdir: Some(PathBuf::from("templates/web")),
+        ..UnpackOptions::default()
+      })
+      .unpack_into(&RealFs, temp.path());
+
+    assert!(matches!(result, Err(UnpackError::SubdirNotFound(..))));
+  }
+
+  #[test]
+  fn unpack_with_strip_components() {
+    let bytes = raw_tarball(&[
+      ("outer/inner/README.md".to_string(), b"# readme"),
+      ("outer/inner/src/main.rs".to_string(), b"fn main() {}"),
+      ("outer/LICENSE".to_string(), b"MIT"),
+    ]);
+
+    let default = TempDir::new().unwrap();
+
+    Unpacker::new(bytes.clone())
+      .unpack_into(&RealFs, default.path())
+      .unwrap();
+
+    assert!(default.path().join("inner/README.md").is_file());
+    assert!(default.path().join("LICENSE").is_file());
+
+    let stripped = TempDir::new().unwrap();
+
+    Unpacker::new(bytes)
+      .options(UnpackOptions {
+        strip_components: 2,
+        ..UnpackOptions::default()
+      })
+      .unpack_into(&RealFs, stripped.path())
+      .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(stripped.path().join("README.md")).unwrap(),
+      "# readme"
+    );
+
+    assert!(stripped.path().join("src/main.rs").is_file());
+    assert!(!stripped.path().join("LICENSE").exists());
+    assert!(!stripped.path().join("inner").exists());
+  }
+
+  #[test]
+  fn refuse_entries_escaping_destination() {
+    let temp = TempDir::new().unwrap();
+    let destination = temp.path().join("destination");
+
+    for path in [
+      "repo-sha/../../evil",
+      "/repo-sha/evil",
+      "repo-sha/nested/../../../evil",
+    ] {
+      let bytes = crafted_tarball(path, EntryType::Regular, None, b"evil");
+      let result = Unpacker::new(bytes).unpack_into(&RealFs, &destination);
+
+      assert!(
+        matches!(result, Err(UnpackError::UnsafeEntry(..))),
+        "{path} should be refused"
+      );
+    }
+
+    assert!(!temp.path().join("evil").exists());
+  }
+
+  #[test]
+  fn refuse_hard_links_escaping_destination() {
+    let temp = TempDir::new().unwrap();
+    let outside = temp.path().join("outside");
+    let destination = temp.path().join("destination");
+
+    fs::write(&outside, "secret").unwrap();
+
+    for link in [
+      outside.to_str().unwrap(),
+      "../outside",
+      "repo-sha/../../outside",
+    ] {
+      let bytes = crafted_tarball("repo-sha/evil", EntryType::Link, Some(link), b"");
+      let result = Unpacker::new(bytes).unpack_into(&RealFs, &destination);
+
+      assert!(
+        matches!(result, Err(UnpackError::UnsafeEntry(..))),
+        "{link} should be refused"
+      );
+    }
+
+    assert!(!destination.join("evil").exists());
+  }
+
+  #[test]
+  fn unpack_hard_links_as_copies() {
+    let temp = TempDir::new().unwrap();
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    let mut header = Header::new_gnu();
+    header.set_size(2);
+    header.set_mode(0o644);
+    header.set_cksum();
+
+    builder
+      .append_data(&mut header, "repo-sha/v2/VERSION", &b"v2"[..])
+      .unwrap();
+
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::Link);
+    header.set_size(0);
+
+    builder
+      .append_link(&mut header, "repo-sha/VERSION", "repo-sha/v2/VERSION")
+      .unwrap();
+
+    let bytes = builder.into_inner().unwrap().finish().unwrap();
+    let stats = Unpacker::new(bytes)
+      .unpack_into(&RealFs, temp.path())
+      .unwrap();
+
+    assert_eq!(stats.files, 2);
+    assert_eq!(
+      fs::read_to_string(temp.path().join("VERSION")).unwrap(),
+      "v2"
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn refuse_entries_escaping_through_symlinks() {
+    let temp = TempDir::new().unwrap();
+    let outside = temp.path().join("outside");
+    let destination = temp.path().join("destination");
+
+    fs::create_dir_all(&outside).unwrap();
+    fs::create_dir_all(&destination).unwrap();
+
+    // Symlinks pointing outside are refused when unpacked...
+    for link in [
+      outside.to_str().unwrap(),
+      "../outside",
+      "nested/../../outside",
+    ] {
+      let bytes = crafted_tarball("repo-sha/link", EntryType::Symlink, Some(link), b"");
+      let result = Unpacker::new(bytes).unpack_into(&RealFs, &destination);
+
+      assert!(
+        matches!(result, Err(UnpackError::UnsafeEntry(..))),
+        "{link} should be refused"
+      );
+    }
+
+    // ...but existing ones shouldn't be followed either.
+    std::os::unix::fs::symlink(&outside, destination.join("link")).unwrap();
+
+    let bytes = crafted_tarball("repo-sha/link/evil", EntryType::Regular, None, b"evil");
+    let result = Unpacker::new(bytes).unpack_into(&RealFs, &destination);
+
+    assert!(matches!(result, Err(UnpackError::UnsafeEntry(..))));
+    assert!(!outside.join("evil").exists());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn unpack_relative_symlinks() {
+    let temp = TempDir::new().unwrap();
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    let mut header = Header::new_gnu();
+    header.set_size(2);
+    header.set_mode(0o644);
+    header.set_cksum();
+
+    builder
+      .append_data(&mut header, "repo-sha/v2/VERSION", &b"v2"[..])
+      .unwrap();
+
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::Symlink);
+    header.set_size(0);
+
+    builder
+      .append_link(&mut header, "repo-sha/latest", "v2")
+      .unwrap();
+
+    let bytes = builder.into_inner().unwrap().finish().unwrap();
+    let stats = Unpacker::new(bytes)
+      .unpack_into(&RealFs, temp.path())
+      .unwrap();
+
+    let latest = temp.path().join("latest");
+
+    assert_eq!(stats.files, 2);
+    assert!(latest.is_symlink());
+    assert_eq!(fs::read_link(&latest).unwrap(), PathBuf::from("v2"));
+    assert_eq!(fs::read_to_string(latest.join("VERSION")).unwrap(), "v2");
+  }
+
+  #[test]
+  fn unpack_into_memory() {
+    let fs = MemoryFs::new();
+
+    let bytes = tarball(&[
+      ("README.md", b"# template"),
+      ("src/main.rs", b"fn main() {}"),
+    ]);
+
+    let stats = Unpacker::new(bytes)
+      .unpack_into(&fs, Path::new("project"))
+      .unwrap();
+
+    assert_eq!(stats.files, 2);
+    assert_eq!(
+      fs.read(Path::new("project/README.md")).unwrap(),
+      b"# template"
+    );
+    assert_eq!(
+      fs.read(Path::new("project/src/main.rs")).unwrap(),
+      b"fn main() {}"
+    );
+  }
+
+  #[test]
+  fn unpack_zip() {
+    let temp = TempDir::new().unwrap();
+    let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().unix_permissions(0o644);
+
+    // Contents deliberately come before their directory entries.
+    writer.start_file("repo-sha/src/main.rs", options).unwrap();
+    writer.write_all(b"fn main() {}").unwrap();
+    writer.add_directory("repo-sha/", options).unwrap();
+    writer.add_directory("repo-sha/src/", options).unwrap();
+    writer.add_directory("repo-sha/empty/", options).unwrap();
+    writer.start_file("repo-sha/README.md", options).unwrap();
+    writer.write_all(b"# readme").unwrap();
+
+    let bytes = writer.finish().unwrap().into_inner();
+
+    Unpacker::new(bytes)
+      .unpack_into(&RealFs, temp.path())
+      .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(temp.path().join("README.md")).unwrap(),
+      "# readme"
+    );
+
+    assert_eq!(
+      fs::read_to_string(temp.path().join("src/main.rs")).unwrap(),
+      "fn main() {}"
+    );
+
+    assert!(temp.path().join("empty").is_dir());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn unpack_preserves_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = TempDir::new().unwrap();
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    for (path, mode) in [("repo-sha/setup.sh", 0o755), ("repo-sha/README.md", 0o644)] {
+      let mut header = Header::new_gnu();
+
+      header.set_size(0);
+      header.set_mode(mode);
+      header.set_cksum();
+
+      builder.append_data(&mut header, path, io::empty()).unwrap();
+    }
+
+    let bytes = builder.into_inner().unwrap().finish().unwrap();
+
+    Unpacker::new(bytes)
+      .unpack_into(&RealFs, temp.path())
+      .unwrap();
+
+    let mode = |path: &str| {
+      fs::metadata(temp.path().join(path))
+        .unwrap()
+        .permissions()
+        .mode()
+        & 0o777
+    };
+
+    assert_eq!(mode("setup.sh"), 0o755);
+    assert_eq!(mode("README.md"), 0o644);
+  }
+
+  #[test]
+  fn unpack_from_reader() {
+    let temp = TempDir::new().unwrap();
+    let bytes = tarball(&[("README.md", b"# readme")]);
+
+    Unpacker::from_reader(io::Cursor::new(bytes))
+      .unpack_into(&RealFs, temp.path())
+      .unwrap();
+
+    assert_eq!(
+      fs::read_to_string(temp.path().join("README.md")).unwrap(),
+      "# readme"
+    );
+  }
+
+  #[test]
+  fn unpack_stats() {
+    let temp = TempDir::new().unwrap();
+
+    let bytes = raw_tarball(&[
+      ("repo-sha/README.md".to_string(), b"# readme"),
+      ("repo-sha/src/main.rs".to_string(), b"fn main() {}"),
+      ("repo-sha/src/lib.rs".to_string(), b""),
+    ]);
+
+    let stats = Unpacker::new(bytes)
+      .unpack_into(&RealFs, temp.path())
+      .unwrap();
+
+    assert_eq!(stats, UnpackStats { files: 3, dirs: 0, bytes: 20 });
+
+    let temp = TempDir::new().unwrap();
+    let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+
+    writer.add_directory("repo-sha/", options).unwrap();
+    writer.add_directory("repo-sha/src/", options).unwrap();
+    writer.start_file("repo-sha/src/main.rs", options).unwrap();
+    writer.write_all(b"fn main() {}").unwrap();
+
+    let bytes = writer.finish().unwrap().into_inner();
+    let stats = Unpacker::new(bytes)
+      .unpack_into(&RealFs, temp.path())
+      .unwrap();
+
+    assert_eq!(stats, UnpackStats { files: 1, dirs: 1, bytes: 12 });
+  }
 }
@@ -1,2 +1,6 @@
 pub mod net;
+pub mod open;
 pub mod prompts;
+
+#[cfg(test)]
+pub mod testing;
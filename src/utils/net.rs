@@ -1,10 +1,170 @@
+use std::env;
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream};
 use std::time::Duration;
 
+use miette::Diagnostic;
+use reqwest::{Client, NoProxy, Proxy};
+use thiserror::Error;
+
+/// Environment variables to read proxy URLs from, grouped by the scheme they apply to. Within a
+/// group, the first variable that is set takes precedence.
+const PROXY_VARS: [(ProxyScheme, [&str; 2]); 3] = [
+  (ProxyScheme::Https, ["HTTPS_PROXY", "https_proxy"]),
+  (ProxyScheme::Http, ["HTTP_PROXY", "http_proxy"]),
+  (ProxyScheme::All, ["ALL_PROXY", "all_proxy"]),
+];
+
+#[derive(Debug, Diagnostic, Error)]
+#[diagnostic(code(decaff::net::client))]
+pub enum ClientError {
+  #[error("Invalid proxy URL in `{var}`: '{url}'.")]
+  InvalidProxy {
+    var: &'static str,
+    url: String,
+    #[source]
+    source: reqwest::Error,
+  },
+  #[error("Failed to build the HTTP client.")]
+  BuildFailed(#[source] reqwest::Error),
+}
+
+/// Requests a proxy applies to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProxyScheme {
+  Https,
+  Http,
+  All,
+}
+
+/// Proxy URL resolved from an environment variable.
+#[derive(Debug, PartialEq)]
+pub struct ProxyVar {
+  pub scheme: ProxyScheme,
+  pub var: &'static str,
+  pub url: String,
+}
+
 #[allow(dead_code)]
 pub fn is_online() -> bool {
   let ip = Ipv4Addr::new(1, 1, 1, 1);
   let address = SocketAddr::V4(SocketAddrV4::new(ip, 80));
 
-  TcpStream::connect_timeout(&address, Duration::from_secs(5)).map_or(false, |_| true)
+  TcpStream::connect_timeout(&address, Duration::from_secs(5)).is_ok()
+}
+
+/// Resolves proxy URLs for each scheme from the standard proxy variables using the given lookup
+/// function. Empty variables are ignored.
+pub fn resolve_proxies<F>(lookup: F) -> Vec<ProxyVar>
+where
+  F: Fn(&str) -> Option<String>,
+{
+  PROXY_VARS
+    .iter()
+    .filter_map(|(scheme, vars)| {
+      vars.iter().find_map(|var| {
+        lookup(var)
+          .filter(|url| !url.trim().is_empty())
+          .map(|url| ProxyVar { scheme: *scheme, var, url })
+      })
+    })
+    .collect()
+}
+
+/// Builds an HTTP client that respects `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY`.
+pub fn client() -> Result<Client, ClientError> {
+  build_client(
+    resolve_proxies(|name| env::var(name).ok()),
+    NoProxy::from_env(),
+  )
+}
+
+/// Builds an HTTP client with the given proxies and exclusions. Scheme-specific proxies take
+/// precedence over `ALL_PROXY`.
+fn build_client(proxies: Vec<ProxyVar>, no_proxy: Option<NoProxy>) -> Result<Client, ClientError> {
+  // Some APIs (e.g. GitHub's) reject requests without a user agent.
+  let mut builder = Client::builder().user_agent(concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION")
+  ));
+
+  for ProxyVar { scheme, var, url } in proxies {
+    let proxy = match scheme {
+      | ProxyScheme::Https => Proxy::https(&url),
+      | ProxyScheme::Http => Proxy::http(&url),
+      | ProxyScheme::All => Proxy::all(&url),
+    };
+
+    let proxy = proxy.map_err(|source| ClientError::InvalidProxy { var, url, source })?;
+
+    builder = builder.proxy(proxy.no_proxy(no_proxy.clone()));
+  }
+
+  builder.build().map_err(ClientError::BuildFailed)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use super::*;
+
+  #[test]
+  fn resolve_proxies_per_scheme() {
+    let vars = HashMap::from([
+      ("HTTP_PROXY", "http://http.proxy:8080"),
+      ("http_proxy", "http://ignored.proxy:8080"),
+      ("HTTPS_PROXY", ""),
+      ("https_proxy", "http://https.proxy:8080"),
+      ("ALL_PROXY", "http://all.proxy:8080"),
+    ]);
+
+    let lookup = |name: &str| vars.get(name).map(|value| value.to_string());
+
+    let proxy = |scheme, var, url: &str| ProxyVar { scheme, var, url: url.to_string() };
+
+    assert_eq!(
+      resolve_proxies(lookup),
+      vec![
+        proxy(ProxyScheme::Https, "https_proxy", "http://https.proxy:8080"),
+        proxy(ProxyScheme::Http, "HTTP_PROXY", "http://http.proxy:8080"),
+        proxy(ProxyScheme::All, "ALL_PROXY", "http://all.proxy:8080"),
+      ]
+    );
+
+    assert_eq!(resolve_proxies(|_| None), Vec::new());
+  }
+
+  #[test]
+  fn client_uses_proxy() {
+    let client = build_client(
+      vec![ProxyVar {
+        scheme: ProxyScheme::Https,
+        var: "HTTPS_PROXY",
+        url: "http://proxy.internal:3128".to_string(),
+      }],
+      NoProxy::from_string("localhost"),
+    )
+    .unwrap();
+
+    assert!(format!("{client:?}").contains("proxy.internal:3128"));
+  }
+
+  #[test]
+  fn client_rejects_invalid_proxy() {
+    let err = build_client(
+      vec![ProxyVar {
+        scheme: ProxyScheme::All,
+        var: "ALL_PROXY",
+        url: "http://[invalid".to_string(),
+      }],
+      None,
+    )
+    .unwrap_err();
+
+    assert_eq!(
+      err.to_string(),
+      "Invalid proxy URL in `ALL_PROXY`: 'http://[invalid'."
+    );
+  }
 }
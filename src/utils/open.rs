@@ -0,0 +1,112 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::process::Command;
+
+use crossterm::style::Stylize;
+
+use crate::logger::info;
+
+/// Environment variables to read the editor command from, in order of precedence.
+const EDITOR_VARS: [&str; 2] = ["VISUAL", "EDITOR"];
+
+/// Resolves the command (program and arguments) to open the `target` with. URLs are opened with
+/// the platform's opener, anything else is treated as a path and opened in the editor taken from
+/// `$VISUAL` or `$EDITOR` using the given lookup function.
+pub fn resolve_command<F>(target: &str, lookup: F) -> Option<(String, Vec<String>)>
+where
+  F: Fn(&str) -> Option<String>,
+{
+  if target.starts_with("http://") || target.starts_with("https://") {
+    let (program, args): (&str, &[&str]) = if cfg!(target_os = "macos") {
+      ("open", &[])
+    } else if cfg!(target_os = "windows") {
+      ("cmd", &["/C", "start", ""])
+    } else {
+      ("xdg-open", &[])
+    };
+
+    let args = args
+      .iter()
+      .map(|arg| arg.to_string())
+      .chain([target.to_string()])
+      .collect();
+
+    return Some((program.to_string(), args));
+  }
+
+  let editor = EDITOR_VARS
+    .iter()
+    .filter_map(|name| lookup(name))
+    .find(|editor| !editor.trim().is_empty())?;
+
+  // Editors are often configured with flags, e.g. `code --wait`.
+  let mut parts = editor.split_whitespace().map(str::to_string);
+  let program = parts.next()?;
+  let args = parts.chain([target.to_string()]).collect();
+
+  Some((program, args))
+}
+
+/// Opens the `target` (a URL, or the scaffolded project at `destination` if `None`) after
+/// scaffolding. Never fails: if opening isn't possible, a warning is printed instead.
+pub fn open(target: Option<&str>, destination: &Path) {
+  if !io::stdout().is_terminal() || env::var_os("CI").is_some() {
+    info!("{}", "~ Not running interactively, skipping opening".dim());
+    return;
+  }
+
+  let destination = destination.display().to_string();
+  let target = target.unwrap_or(&destination);
+
+  let Some((program, args)) = resolve_command(target, |name| env::var(name).ok()) else {
+    info!(
+      "{}",
+      "? Couldn't open: neither $VISUAL nor $EDITOR is set".yellow()
+    );
+    return;
+  };
+
+  match Command::new(&program).args(&args).status() {
+    | Ok(status) if status.success() => {},
+    | Ok(status) => {
+      info!(
+        "{}",
+        format!("? Couldn't open `{target}`: {program} exited with {status}").yellow()
+      );
+    },
+    | Err(err) => {
+      info!("{}", format!("? Couldn't open `{target}`: {err}").yellow());
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use super::*;
+
+  #[test]
+  fn resolve_editor_command() {
+    let vars = HashMap::from([("VISUAL", ""), ("EDITOR", "code --wait")]);
+    let lookup = |name: &str| vars.get(name).map(|value| value.to_string());
+
+    assert_eq!(
+      resolve_command("projects/app", lookup),
+      Some((
+        "code".to_string(),
+        vec!["--wait".to_string(), "projects/app".to_string()]
+      ))
+    );
+
+    assert_eq!(resolve_command("projects/app", |_| None), None);
+  }
+
+  #[test]
+  fn resolve_url_command() {
+    let (_, args) = resolve_command("https://example.com/docs", |_| None).unwrap();
+
+    assert_eq!(args.last().unwrap(), "https://example.com/docs");
+  }
+}
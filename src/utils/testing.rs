@@ -0,0 +1,48 @@
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Spawns a tiny HTTP server that answers a single request with the given raw response and returns
+/// its URL.
+pub fn serve_once<R>(response: R) -> String
+where
+  R: Into<Vec<u8>>,
+{
+  serve_once_capturing(response).0
+}
+
+/// Same as [serve_once], but also returns a receiver of the raw request headers, e.g. to check what
+/// the client actually sent.
+pub fn serve_once_capturing<R>(response: R) -> (String, Receiver<String>)
+where
+  R: Into<Vec<u8>>,
+{
+  let response = response.into();
+  let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind the test server");
+  let address = listener
+    .local_addr()
+    .expect("Failed to get the test server address");
+
+  let (sender, receiver) = mpsc::channel();
+
+  thread::spawn(move || {
+    if let Ok((mut stream, _)) = listener.accept() {
+      let mut request = Vec::new();
+      let mut buffer = [0; 1024];
+
+      // Read until the end of the request headers.
+      while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        match stream.read(&mut buffer) {
+          | Ok(0) | Err(_) => break,
+          | Ok(read) => request.extend_from_slice(&buffer[..read]),
+        }
+      }
+
+      let _ = sender.send(String::from_utf8_lossy(&request).into_owned());
+      let _ = stream.write_all(&response);
+    }
+  });
+
+  (format!("http://{address}"), receiver)
+}
@@ -0,0 +1,73 @@
+use std::fmt::Debug;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Kind of a filesystem entry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EntryKind {
+  File,
+  Dir,
+  Symlink,
+}
+
+/// Iterator over entries of a directory tree, as returned by [Filesystem::walk].
+pub type Walk<'f> = Box<dyn Iterator<Item = io::Result<(PathBuf, EntryKind)>> + 'f>;
+
+/// Abstraction over filesystem operations used while scaffolding, so they can be performed either
+/// on disk or in memory.
+pub trait Filesystem: Debug + Send + Sync {
+  /// Reads the whole file.
+  fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+  /// Writes the file, replacing its contents if it exists. The parent directory must exist.
+  fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+  /// Creates the directory and all of its missing parents.
+  fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+  /// Copies the file, replacing the target if it exists.
+  fn copy(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+  /// Moves the file or directory, replacing the target if it exists.
+  fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+  /// Creates a symlink at `link` pointing to `original`, which is stored as is, i.e. relative
+  /// targets are resolved against the parent of `link`. Fails with [io::ErrorKind::Unsupported] if
+  /// the filesystem doesn't support symlinks.
+  fn symlink(&self, original: &Path, link: &Path) -> io::Result<()>;
+
+  /// Removes the file.
+  fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+  /// Removes the directory along with its contents.
+  fn remove_dir_all(&self, path: &Path) -> io::Result<()>;
+
+  /// Returns the kind of the entry, or `None` if it doesn't exist. Symlinks aren't followed.
+  fn kind(&self, path: &Path) -> Option<EntryKind>;
+
+  /// Returns the canonical form of the path, with symlinks resolved.
+  fn canonicalize(&self, path: &Path) -> io::Result<PathBuf>;
+
+  /// Walks the directory tree starting with the `root` itself. If `contents_first` is set,
+  /// contents of directories are yielded before directories themselves.
+  fn walk<'f>(&'f self, root: &Path, contents_first: bool) -> Walk<'f>;
+
+  /// Returns the path on disk if the filesystem is backed by it, which allows to use native
+  /// operations not covered here, e.g. preserving permissions or creating symlinks.
+  fn local_path(&self, path: &Path) -> Option<PathBuf>;
+
+  /// Checks if the entry exists.
+  fn exists(&self, path: &Path) -> bool {
+    self.kind(path).is_some()
+  }
+
+  /// Checks if the entry exists and is a file.
+  fn is_file(&self, path: &Path) -> bool {
+    self.kind(path) == Some(EntryKind::File)
+  }
+
+  /// Checks if the entry exists and is a directory.
+  fn is_dir(&self, path: &Path) -> bool {
+    self.kind(path) == Some(EntryKind::Dir)
+  }
+}
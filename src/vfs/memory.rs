@@ -0,0 +1,370 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::path::PathClean;
+use crate::vfs::{EntryKind, Filesystem, Walk};
+
+/// Entry of the in-memory filesystem.
+#[derive(Clone, Debug)]
+enum Node {
+  File(Vec<u8>),
+  Dir,
+}
+
+impl Node {
+  fn kind(&self) -> EntryKind {
+    match self {
+      | Node::File(..) => EntryKind::File,
+      | Node::Dir => EntryKind::Dir,
+    }
+  }
+}
+
+/// Filesystem kept entirely in memory, e.g. for previewing scaffolds or testing. Doesn't support
+/// symlinks and permissions. The current directory (`.`) and the root directory (`/`) always
+/// exist.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+  /// Entries keyed by cleaned paths. Ordering of paths makes iteration a depth-first traversal.
+  nodes: Mutex<BTreeMap<PathBuf, Node>>,
+}
+
+impl MemoryFs {
+  /// Creates an empty filesystem.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Locks the entries. Poisoning is ignored, since entries are always left consistent.
+  fn nodes(&self) -> MutexGuard<'_, BTreeMap<PathBuf, Node>> {
+    self.nodes.lock().unwrap_or_else(|err| err.into_inner())
+  }
+}
+
+impl Filesystem for MemoryFs {
+  fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+    match self.nodes().get(&path.clean()) {
+      | Some(Node::File(contents)) => Ok(contents.clone()),
+      | Some(Node::Dir) => Err(is_a_directory(path)),
+      | None => Err(not_found(path)),
+    }
+  }
+
+  fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+    let path = path.clean();
+    let mut nodes = self.nodes();
+
+    ensure_parent(&nodes, &path)?;
+
+    if let Some(Node::Dir) = nodes.get(&path) {
+      return Err(is_a_directory(&path));
+    }
+
+    nodes.insert(path, Node::File(contents.to_vec()));
+
+    Ok(())
+  }
+
+  fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+    let path = path.clean();
+    let mut nodes = self.nodes();
+
+    let ancestors = path
+      .ancestors()
+      .filter(|ancestor| !is_root(ancestor))
+      .collect::<Vec<_>>();
+
+    for ancestor in ancestors.into_iter().rev() {
+      match nodes.get(ancestor) {
+        | Some(Node::Dir) => continue,
+        | Some(Node::File(..)) => return Err(already_exists(ancestor)),
+        | None => nodes.insert(ancestor.to_path_buf(), Node::Dir),
+      };
+    }
+
+    Ok(())
+  }
+
+  fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+    let contents = self.read(from)?;
+    self.write(to, &contents)
+  }
+
+  fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+    let (from, to) = (from.clean(), to.clean());
+    let mut nodes = self.nodes();
+
+    if !nodes.contains_key(&from) {
+      return Err(not_found(&from));
+    }
+
+    if to.starts_with(&from) && to != from {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Cannot move '{}' into itself.", from.display()),
+      ));
+    }
+
+    ensure_parent(&nodes, &to)?;
+
+    let moved = drain(&mut nodes, &from);
+
+    drain(&mut nodes, &to);
+
+    for (path, node) in moved {
+      let relative = path.strip_prefix(&from).unwrap_or(&path);
+      nodes.insert(to.join(relative).clean(), node);
+    }
+
+    Ok(())
+  }
+
+  fn remove_file(&self, path: &Path) -> io::Result<()> {
+    let path = path.clean();
+    let mut nodes = self.nodes();
+
+    match nodes.get(&path) {
+      | Some(Node::File(..)) => {
+        nodes.remove(&path);
+        Ok(())
+      },
+      | Some(Node::Dir) => Err(is_a_directory(&path)),
+      | None => Err(not_found(&path)),
+    }
+  }
+
+  fn symlink(&self, _original: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+      io::ErrorKind::Unsupported,
+      "Symlinks are not supported in memory.",
+    ))
+  }
+
+  fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+    let path = path.clean();
+    let mut nodes = self.nodes();
+
+    match nodes.get(&path) {
+      | Some(Node::Dir) => {
+        drain(&mut nodes, &path);
+        Ok(())
+      },
+      | Some(Node::File(..)) => {
+        Err(io::Error::new(
+          io::ErrorKind::InvalidInput,
+          format!("Not a directory: '{}'.", path.display()),
+        ))
+      },
+      | None => Err(not_found(&path)),
+    }
+  }
+
+  fn kind(&self, path: &Path) -> Option<EntryKind> {
+    let path = path.clean();
+
+    if is_root(&path) {
+      return Some(EntryKind::Dir);
+    }
+
+    self.nodes().get(&path).map(Node::kind)
+  }
+
+  fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+    if self.exists(path) {
+      Ok(path.clean())
+    } else {
+      Err(not_found(path))
+    }
+  }
+
+  fn walk<'f>(&'f self, root: &Path, contents_first: bool) -> Walk<'f> {
+    let Some(kind) = self.kind(root) else {
+      return Box::new(std::iter::once(Err(not_found(root))));
+    };
+
+    let cleaned = root.clean();
+
+    // Paths are yielded relative to the given root as is, same as when walking the disk.
+    let mut entries = vec![Ok((root.to_path_buf(), kind))];
+
+    entries.extend(self.nodes().iter().filter_map(|(path, node)| {
+      let relative = if is_root(&cleaned) {
+        path.as_path()
+      } else {
+        path.strip_prefix(&cleaned).ok()?
+      };
+
+      (!relative.as_os_str().is_empty()).then(|| Ok((root.join(relative), node.kind())))
+    }));
+
+    // Reversing the depth-first order puts contents of directories before directories.
+    if contents_first {
+      entries.reverse();
+    }
+
+    Box::new(entries.into_iter())
+  }
+
+  fn local_path(&self, _path: &Path) -> Option<PathBuf> {
+    None
+  }
+}
+
+/// Checks if the cleaned path is the current or the root directory.
+fn is_root(path: &Path) -> bool {
+  path.as_os_str().is_empty() || path == Path::new(".") || path.parent().is_none()
+}
+
+/// Checks that the parent directory of the cleaned path exists.
+fn ensure_parent(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> io::Result<()> {
+  match path.parent() {
+    | Some(parent) if !is_root(parent) => {
+      match nodes.get(parent) {
+        | Some(Node::Dir) => Ok(()),
+        | _ => Err(not_found(parent)),
+      }
+    },
+    | _ => Ok(()),
+  }
+}
+
+/// Removes the entry at the cleaned path along with its contents, returning them.
+fn drain(nodes: &mut BTreeMap<PathBuf, Node>, path: &Path) -> Vec<(PathBuf, Node)> {
+  let paths = nodes
+    .range(path.to_path_buf()..)
+    .map(|(path, _)| path)
+    .take_while(|candidate| candidate.starts_with(path))
+    .cloned()
+    .collect::<Vec<_>>();
+
+  paths
+    .into_iter()
+    .filter_map(|path| nodes.remove_entry(&path))
+    .collect()
+}
+
+fn not_found(path: &Path) -> io::Error {
+  io::Error::new(
+    io::ErrorKind::NotFound,
+    format!("No such file or directory: '{}'.", path.display()),
+  )
+}
+
+fn already_exists(path: &Path) -> io::Error {
+  io::Error::new(
+    io::ErrorKind::AlreadyExists,
+    format!("File exists: '{}'.", path.display()),
+  )
+}
+
+fn is_a_directory(path: &Path) -> io::Error {
+  io::Error::new(
+    io::ErrorKind::InvalidInput,
+    format!("Is a directory: '{}'.", path.display()),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Helpers.
+
+  fn walk(fs: &MemoryFs, root: &str, contents_first: bool) -> Vec<String> {
+    fs.walk(Path::new(root), contents_first)
+      .map(|entry| entry.unwrap().0.display().to_string())
+      .collect()
+  }
+
+  // Tests.
+
+  #[test]
+  fn write_and_read_files() {
+    let fs = MemoryFs::new();
+
+    assert_eq!(
+      fs.write(Path::new("project/README.md"), b"# readme")
+        .unwrap_err()
+        .kind(),
+      io::ErrorKind::NotFound
+    );
+
+    fs.create_dir_all(Path::new("project/src")).unwrap();
+    fs.write(Path::new("project/README.md"), b"# readme")
+      .unwrap();
+    fs.copy(
+      Path::new("project/README.md"),
+      Path::new("project/src/./COPY.md"),
+    )
+    .unwrap();
+
+    assert_eq!(fs.kind(Path::new("project")), Some(EntryKind::Dir));
+    assert!(fs.is_file(Path::new("project/src/COPY.md")));
+    assert_eq!(
+      fs.read(Path::new("project/README.md")).unwrap(),
+      b"# readme"
+    );
+
+    fs.remove_file(Path::new("project/README.md")).unwrap();
+
+    assert!(!fs.exists(Path::new("project/README.md")));
+  }
+
+  #[test]
+  fn rename_and_remove_dirs() {
+    let fs = MemoryFs::new();
+
+    fs.create_dir_all(Path::new("project/template/src"))
+      .unwrap();
+    fs.write(Path::new("project/template/src/main.rs"), b"fn main() {}")
+      .unwrap();
+    fs.write(Path::new("project/template.toml"), b"").unwrap();
+
+    fs.rename(Path::new("project/template"), Path::new("project/app"))
+      .unwrap();
+
+    assert!(!fs.exists(Path::new("project/template")));
+    assert!(fs.is_file(Path::new("project/template.toml")));
+    assert!(fs.is_file(Path::new("project/app/src/main.rs")));
+
+    fs.remove_dir_all(Path::new("project/app")).unwrap();
+
+    assert_eq!(
+      walk(&fs, "project", false),
+      ["project", "project/template.toml"]
+    );
+  }
+
+  #[test]
+  fn walk_contents_first() {
+    let fs = MemoryFs::new();
+
+    fs.create_dir_all(Path::new("project/a/b")).unwrap();
+    fs.write(Path::new("project/a/b/c.txt"), b"").unwrap();
+    fs.write(Path::new("project/a.txt"), b"").unwrap();
+
+    assert_eq!(
+      walk(&fs, "project", false),
+      [
+        "project",
+        "project/a",
+        "project/a/b",
+        "project/a/b/c.txt",
+        "project/a.txt"
+      ]
+    );
+
+    assert_eq!(
+      walk(&fs, "./project", true),
+      [
+        "./project/a.txt",
+        "./project/a/b/c.txt",
+        "./project/a/b",
+        "./project/a",
+        "./project"
+      ]
+    );
+  }
+}
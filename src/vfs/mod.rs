@@ -0,0 +1,7 @@
+pub use filesystem::*;
+pub use memory::*;
+pub use real::*;
+
+mod filesystem;
+mod memory;
+mod real;
@@ -0,0 +1,98 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::vfs::{EntryKind, Filesystem, Walk};
+
+/// Filesystem on disk.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFs;
+
+impl Filesystem for RealFs {
+  fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+    fs::read(path)
+  }
+
+  fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+    fs::write(path, contents)
+  }
+
+  fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+    fs::create_dir_all(path)
+  }
+
+  fn copy(&self, from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to).map(|_| ())
+  }
+
+  fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+    fs::rename(from, to)
+  }
+
+  #[cfg(unix)]
+  fn symlink(&self, original: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+  }
+
+  // Creating symlinks on Windows requires either elevated privileges or developer mode, and
+  // distinguishing between file and directory links, so it's not supported.
+  #[cfg(not(unix))]
+  fn symlink(&self, _original: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+      io::ErrorKind::Unsupported,
+      "Symlinks are not supported on this platform.",
+    ))
+  }
+
+  fn remove_file(&self, path: &Path) -> io::Result<()> {
+    fs::remove_file(path)
+  }
+
+  fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+    fs::remove_dir_all(path)
+  }
+
+  fn kind(&self, path: &Path) -> Option<EntryKind> {
+    let file_type = fs::symlink_metadata(path).ok()?.file_type();
+
+    if file_type.is_symlink() {
+      Some(EntryKind::Symlink)
+    } else if file_type.is_dir() {
+      Some(EntryKind::Dir)
+    } else {
+      Some(EntryKind::File)
+    }
+  }
+
+  fn canonicalize(&self, path: &Path) -> io::Result<PathBuf> {
+    path.canonicalize()
+  }
+
+  fn walk<'f>(&'f self, root: &Path, contents_first: bool) -> Walk<'f> {
+    let it = WalkDir::new(root)
+      .contents_first(contents_first)
+      .into_iter()
+      .map(|entry| {
+        let entry = entry?;
+        let file_type = entry.file_type();
+
+        let kind = if file_type.is_symlink() {
+          EntryKind::Symlink
+        } else if file_type.is_dir() {
+          EntryKind::Dir
+        } else {
+          EntryKind::File
+        };
+
+        Ok((entry.into_path(), kind))
+      });
+
+    Box::new(it)
+  }
+
+  fn local_path(&self, path: &Path) -> Option<PathBuf> {
+    Some(path.to_path_buf())
+  }
+}